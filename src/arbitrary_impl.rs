@@ -0,0 +1,161 @@
+//! [`Arbitrary`] implementations, which only produce values that are valid according to the specification.
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    Error, Id, MaybeBatchedRequest, MaybeBatchedResponse, Notification, Request, RequestParameters,
+    Response, V2,
+};
+
+/// How deeply structured values may nest.
+const MAX_DEPTH: usize = 3;
+/// The most members a structured value, or batch, may have.
+const MAX_LEN: usize = 4;
+
+fn len(u: &mut Unstructured<'_>) -> Result<usize> {
+    u.int_in_range(0..=MAX_LEN)
+}
+
+fn value(u: &mut Unstructured<'_>, depth: usize) -> Result<Value> {
+    let choices = match depth {
+        0 => 5,
+        _ => 7,
+    };
+    Ok(match u.choose_index(choices)? {
+        0 => Value::Null,
+        1 => Value::Bool(u.arbitrary()?),
+        2 => Value::Number(u.arbitrary::<i64>()?.into()),
+        3 => Value::Number(float(u.arbitrary()?).unwrap_or_else(|| 0.into())),
+        4 => Value::String(u.arbitrary()?),
+        5 => Value::Array(array(u, depth - 1)?),
+        _ => Value::Object(object(u, depth - 1)?),
+    })
+}
+
+fn array(u: &mut Unstructured<'_>, depth: usize) -> Result<Vec<Value>> {
+    (0..len(u)?).map(|_| value(u, depth)).collect()
+}
+
+fn object(u: &mut Unstructured<'_>, depth: usize) -> Result<Map<String, Value>> {
+    (0..len(u)?)
+        .map(|_| Ok((u.arbitrary()?, value(u, depth)?)))
+        .collect()
+}
+
+/// `serde_json` doesn't parse all floats exactly,
+/// so only produce those that survive a round-trip.
+fn float(f: f64) -> Option<Number> {
+    let number = Number::from_f64(f)?;
+    match serde_json::from_str::<Number>(&number.to_string()).ok()? == number {
+        true => Some(number),
+        false => None,
+    }
+}
+
+/// Avoids names reserved for extensions.
+fn method(u: &mut Unstructured<'_>) -> Result<String> {
+    let method = String::arbitrary(u)?;
+    match method.starts_with("rpc.") {
+        true => Ok(format!("_{}", method)),
+        false => Ok(method),
+    }
+}
+
+fn batch<'a, T: Arbitrary<'a>>(u: &mut Unstructured<'a>) -> Result<Vec<T>> {
+    // Batches may not be empty.
+    (0..u.int_in_range(1..=MAX_LEN)?)
+        .map(|_| T::arbitrary(u))
+        .collect()
+}
+
+impl<'a> Arbitrary<'a> for V2 {
+    fn arbitrary(_: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(V2)
+    }
+}
+
+/// Numbers are always integers.
+impl<'a> Arbitrary<'a> for Id {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.choose_index(3)? {
+            0 => Id::String(u.arbitrary()?),
+            1 => Id::Number(u.arbitrary::<i64>()?.into()),
+            _ => Id::Null,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for RequestParameters {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.arbitrary()? {
+            true => RequestParameters::ByPosition(array(u, MAX_DEPTH)?),
+            false => RequestParameters::ByName(object(u, MAX_DEPTH)?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Request {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Request {
+            jsonrpc: V2,
+            method: method(u)?,
+            params: u.arbitrary()?,
+            id: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Notification {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Notification {
+            jsonrpc: V2,
+            method: method(u)?,
+            params: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Error {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Error {
+            code: u.arbitrary()?,
+            message: u.arbitrary()?,
+            data: match u.arbitrary()? {
+                true => Some(value(u, MAX_DEPTH)?),
+                false => None,
+            },
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for Response {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Response {
+            jsonrpc: V2,
+            result: match u.arbitrary()? {
+                true => Ok(value(u, MAX_DEPTH)?),
+                false => Err(u.arbitrary()?),
+            },
+            id: u.arbitrary()?,
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for MaybeBatchedRequest {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.arbitrary()? {
+            true => MaybeBatchedRequest::Single(u.arbitrary()?),
+            false => MaybeBatchedRequest::Batch(batch(u)?),
+        })
+    }
+}
+
+impl<'a> Arbitrary<'a> for MaybeBatchedResponse {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(match u.arbitrary()? {
+            true => MaybeBatchedResponse::Single(u.arbitrary()?),
+            false => MaybeBatchedResponse::Batch(batch(u)?),
+        })
+    }
+}
@@ -0,0 +1,112 @@
+//! Serve `JSON-RPC 2.0` from an [`axum`](https://docs.rs/axum) application.
+//!
+//! [`MaybeBatchedRequest`] is an extractor, and [`MaybeBatchedResponse`] and [`Response`] are responders.
+//! Notifications get no response, so a handler using [`Router::handle_batch`](crate::router::Router::handle_batch)
+//! may return `Result<MaybeBatchedResponse, StatusCode>`, with [`StatusCode::NO_CONTENT`] for [`None`].
+
+use std::fmt;
+
+use axum::{
+    body::Bytes,
+    extract::{rejection::BytesRejection, FromRequest, Request},
+    http::{header::CONTENT_TYPE, HeaderValue, StatusCode},
+    response::IntoResponse,
+};
+use serde::{Deserialize as _, Serialize};
+use serde_json::Value;
+
+use crate::{Error, Id, MaybeBatchedRequest, MaybeBatchedResponse, Response, V2};
+
+/// Why a [`MaybeBatchedRequest`] could not be extracted.
+#[derive(Debug)]
+pub enum Rejection {
+    /// The body could not be read.
+    Body(BytesRejection),
+    /// The body was not a valid request.
+    ///
+    /// Responds with an [`Error::PARSE_ERROR`] or [`Error::INVALID_REQUEST`],
+    /// as the specification requires.
+    Invalid(Response),
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Body(e) => e.fmt(f),
+            Rejection::Invalid(Response { result, .. }) => match result {
+                Ok(_) => f.write_str("invalid request"),
+                Err(e) => e.fmt(f),
+            },
+        }
+    }
+}
+
+impl std::error::Error for Rejection {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Rejection::Body(e) => Some(e),
+            Rejection::Invalid(_) => None,
+        }
+    }
+}
+
+impl IntoResponse for Rejection {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            Rejection::Body(it) => it.into_response(),
+            Rejection::Invalid(it) => it.into_response(),
+        }
+    }
+}
+
+/// Empty batches, and batches with any invalid member, are rejected as a whole.
+impl<S: Send + Sync> FromRequest<S> for MaybeBatchedRequest {
+    type Rejection = Rejection;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let body = Bytes::from_request(req, state)
+            .await
+            .map_err(Rejection::Body)?;
+        let value = serde_json::from_slice::<Value>(&body)
+            .map_err(|e| reject(Error::parse_error(e, None)))?;
+        match MaybeBatchedRequest::deserialize(value) {
+            Ok(MaybeBatchedRequest::Batch(it)) if it.is_empty() => {
+                Err(reject(Error::invalid_request("empty batch", None)))
+            }
+            Ok(it) => Ok(it),
+            Err(e) => Err(reject(Error::invalid_request(e, None))),
+        }
+    }
+}
+
+fn reject(error: Error) -> Rejection {
+    Rejection::Invalid(Response {
+        jsonrpc: V2,
+        result: Err(error),
+        id: Id::Null,
+    })
+}
+
+impl IntoResponse for Response {
+    fn into_response(self) -> axum::response::Response {
+        json(&self)
+    }
+}
+
+impl IntoResponse for MaybeBatchedResponse {
+    fn into_response(self) -> axum::response::Response {
+        json(&self)
+    }
+}
+
+/// `JSON-RPC` errors are still reported with [`StatusCode::OK`].
+fn json(body: &impl Serialize) -> axum::response::Response {
+    match serde_json::to_vec(body) {
+        Ok(it) => (
+            [(CONTENT_TYPE, HeaderValue::from_static("application/json"))],
+            it,
+        )
+            .into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
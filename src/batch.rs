@@ -0,0 +1,385 @@
+//! Helpers for working with batches.
+
+use std::{
+    collections::{HashMap, VecDeque},
+    fmt,
+    sync::Arc,
+};
+
+use serde_json::Value;
+
+use crate::{
+    id::{IdGenerator, Sequential},
+    Error, Id, MaybeBatchedRequest, MaybeBatchedResponse, Request, RequestParameters, Response, V2,
+};
+
+/// The result of [`correlate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Correlation<'a> {
+    /// Each request, in the order given, with its response (if any).
+    pub pairs: Vec<(&'a Request, Option<Response>)>,
+    /// Responses whose [`Id`] matches no (remaining) request.
+    ///
+    /// Servers respond with an [`Id::Null`] id if a request could not be parsed,
+    /// so these typically end up here.
+    pub orphans: Vec<Response>,
+}
+
+impl<'a> Correlation<'a> {
+    /// Requests that should have received a response, but didn't.
+    ///
+    /// Notifications are never missing.
+    pub fn missing(&self) -> impl Iterator<Item = &'a Request> + '_ {
+        self.pairs.iter().filter_map(|(request, response)| {
+            match (request.is_notification(), response) {
+                (false, None) => Some(*request),
+                _ => None,
+            }
+        })
+    }
+}
+
+/// Match `responses` to `requests` by [`Id`].
+///
+/// > The Response objects being returned from a batch call MAY be returned in any order within the Array.
+/// > The Client SHOULD match contexts between the set of Request objects and the resulting set of Response objects based on the id member within each Object.
+///
+/// If several requests share an id,
+/// responses with that id are assigned to them in order.
+pub fn correlate(requests: &[Request], responses: MaybeBatchedResponse) -> Correlation<'_> {
+    let mut pending = HashMap::<&Id, VecDeque<usize>>::new();
+    for (ix, request) in requests.iter().enumerate() {
+        if let Some(id) = &request.id {
+            pending.entry(id).or_default().push_back(ix)
+        }
+    }
+
+    let mut matched = requests.iter().map(|_| None).collect::<Vec<_>>();
+    let mut orphans = vec![];
+    let responses = match responses {
+        MaybeBatchedResponse::Single(it) => vec![it],
+        MaybeBatchedResponse::Batch(it) => it,
+    };
+    for response in responses {
+        match pending.get_mut(&response.id).and_then(VecDeque::pop_front) {
+            Some(ix) => matched[ix] = Some(response),
+            None => orphans.push(response),
+        }
+    }
+
+    Correlation {
+        pairs: requests.iter().zip(matched).collect(),
+        orphans,
+    }
+}
+
+/// Collect calls and notifications into a batch, assigning each call a unique [`Id`].
+///
+/// Once the batch has been sent, use [`BatchResponses`] to find the result for each [`Ticket`].
+///
+/// Clones share an [`IdGenerator`].
+#[derive(Clone)]
+pub struct BatchBuilder {
+    requests: Vec<Request>,
+    ids: Arc<dyn IdGenerator + Send + Sync>,
+}
+
+impl fmt::Debug for BatchBuilder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BatchBuilder")
+            .field("requests", &self.requests)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Assigns [`Sequential`] ids.
+impl Default for BatchBuilder {
+    fn default() -> Self {
+        Self::with_ids(Sequential::new())
+    }
+}
+
+/// Identifies a call in a [`BatchBuilder`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use = "the ticket is required to retrieve the result of the call"]
+pub struct Ticket {
+    id: Id,
+}
+
+impl Ticket {
+    /// The [`Id`] assigned to the call.
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl BatchBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Assign ids from `ids`, instead of [`Sequential`]ly.
+    pub fn with_ids(ids: impl IdGenerator + Send + Sync + 'static) -> Self {
+        Self {
+            requests: vec![],
+            ids: Arc::new(ids),
+        }
+    }
+    /// Add a call to the batch.
+    pub fn call(
+        &mut self,
+        method: impl Into<String>,
+        params: impl Into<Option<RequestParameters>>,
+    ) -> Ticket {
+        let id = self.ids.next_id();
+        self.requests.push(Request {
+            jsonrpc: V2,
+            method: method.into(),
+            params: params.into(),
+            id: Some(id.clone()),
+        });
+        Ticket { id }
+    }
+    /// Add a notification to the batch.
+    pub fn notify(
+        &mut self,
+        method: impl Into<String>,
+        params: impl Into<Option<RequestParameters>>,
+    ) {
+        self.requests.push(Request {
+            jsonrpc: V2,
+            method: method.into(),
+            params: params.into(),
+            id: None,
+        })
+    }
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+    /// The requests collected so far.
+    pub fn requests(&self) -> &[Request] {
+        &self.requests
+    }
+    pub fn into_requests(self) -> Vec<Request> {
+        self.requests
+    }
+    /// This is always a [`MaybeBatchedRequest::Batch`].
+    pub fn build(self) -> MaybeBatchedRequest {
+        MaybeBatchedRequest::Batch(self.requests)
+    }
+}
+
+/// The responses to a batch from a [`BatchBuilder`], indexed by [`Ticket`].
+#[derive(Debug, Clone, Default)]
+pub struct BatchResponses {
+    by_id: HashMap<Id, Response>,
+    /// Responses which can't be found by [`Ticket`].
+    rest: Vec<Response>,
+}
+
+impl From<MaybeBatchedResponse> for BatchResponses {
+    fn from(value: MaybeBatchedResponse) -> Self {
+        Self::from(match value {
+            MaybeBatchedResponse::Single(it) => vec![it],
+            MaybeBatchedResponse::Batch(it) => it,
+        })
+    }
+}
+
+impl From<Vec<Response>> for BatchResponses {
+    fn from(value: Vec<Response>) -> Self {
+        let mut by_id = HashMap::new();
+        let mut rest = vec![];
+        for response in value {
+            match response.id {
+                // Never assigned by a `BatchBuilder`.
+                Id::Null => rest.push(response),
+                _ => match by_id.contains_key(&response.id) {
+                    true => rest.push(response),
+                    false => {
+                        by_id.insert(response.id.clone(), response);
+                    }
+                },
+            }
+        }
+        Self { by_id, rest }
+    }
+}
+
+impl BatchResponses {
+    /// Remove and return the result for the call identified by `ticket`.
+    ///
+    /// Returns [`None`] if the server didn't respond to that call,
+    /// or if the result has already been taken.
+    pub fn take(&mut self, ticket: &Ticket) -> Option<Result<Value, Error>> {
+        self.by_id.remove(&ticket.id).map(|it| it.result)
+    }
+    /// Responses which haven't been taken,
+    /// including any which weren't for a [`Ticket`] (e.g with an [`Id::Null`] id).
+    pub fn into_remaining(self) -> impl Iterator<Item = Response> {
+        self.by_id.into_values().chain(self.rest)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn request(id: Option<Value>) -> Request {
+        let mut it = json!({"jsonrpc": "2.0", "method": "m"});
+        if let Some(id) = id {
+            it["id"] = id
+        }
+        serde_json::from_value(it).unwrap()
+    }
+
+    fn response(id: Value, result: Value) -> Response {
+        serde_json::from_value(json!({"jsonrpc": "2.0", "result": result, "id": id})).unwrap()
+    }
+
+    fn failure(id: Value) -> Response {
+        serde_json::from_value(
+            json!({"jsonrpc": "2.0", "error": {"code": -32700, "message": "Parse error"}, "id": id}),
+        )
+        .unwrap()
+    }
+
+    /// The result each request was paired with.
+    fn results(correlation: &Correlation) -> Vec<Option<Value>> {
+        correlation
+            .pairs
+            .iter()
+            .map(|(_, it)| it.clone().map(|it| it.result.unwrap_or(Value::Null)))
+            .collect()
+    }
+
+    #[test]
+    fn out_of_order() {
+        let requests = [
+            request(Some(json!(1))),
+            request(None),
+            request(Some(json!("two"))),
+        ];
+        let correlation = correlate(
+            &requests,
+            MaybeBatchedResponse::Batch(vec![
+                response(json!("two"), json!(2)),
+                response(json!(1), json!(1)),
+            ]),
+        );
+        assert_eq!(
+            results(&correlation),
+            [Some(json!(1)), None, Some(json!(2))]
+        );
+        assert!(correlation.orphans.is_empty());
+        assert_eq!(correlation.missing().count(), 0);
+    }
+
+    #[test]
+    fn missing() {
+        let requests = [request(Some(json!(1))), request(Some(json!(2)))];
+        let correlation = correlate(
+            &requests,
+            MaybeBatchedResponse::Single(response(json!(2), json!(2))),
+        );
+        assert_eq!(results(&correlation), [None, Some(json!(2))]);
+        assert_eq!(correlation.missing().collect::<Vec<_>>(), [&requests[0]]);
+    }
+
+    #[test]
+    fn duplicate_ids() {
+        let requests = [request(Some(json!(1))), request(Some(json!(1)))];
+        let correlation = correlate(
+            &requests,
+            MaybeBatchedResponse::Batch(vec![
+                response(json!(1), json!("first")),
+                response(json!(1), json!("second")),
+                response(json!(1), json!("third")),
+            ]),
+        );
+        assert_eq!(
+            results(&correlation),
+            [Some(json!("first")), Some(json!("second"))]
+        );
+        assert_eq!(correlation.orphans, [response(json!(1), json!("third"))]);
+    }
+
+    #[test]
+    fn orphans() {
+        // Only requests with a null id can claim a null id response.
+        let requests = [request(Some(json!(1))), request(Some(json!(null)))];
+        let correlation = correlate(
+            &requests,
+            MaybeBatchedResponse::Batch(vec![
+                failure(json!(null)),
+                failure(json!(null)),
+                response(json!(3), json!(3)),
+            ]),
+        );
+        assert_eq!(correlation.pairs[1].1, Some(failure(json!(null))));
+        assert_eq!(
+            correlation.orphans,
+            [failure(json!(null)), response(json!(3), json!(3))]
+        );
+        assert_eq!(correlation.missing().collect::<Vec<_>>(), [&requests[0]]);
+    }
+
+    #[test]
+    fn builder() {
+        let mut builder = BatchBuilder::new();
+        let first = builder.call("a", RequestParameters::ByPosition(vec![json!(1)]));
+        builder.notify("b", None);
+        let second = builder.clone().call("c", None);
+        // Clones share ids.
+        let third = builder.call("c", None);
+        assert_eq!(
+            [first.id(), second.id(), third.id()],
+            [&Id::from_u64(0), &Id::from_u64(1), &Id::from_u64(2)]
+        );
+        assert_eq!(builder.len(), 3);
+        assert_eq!(
+            serde_json::to_value(builder.build()).unwrap(),
+            json!([
+                {"jsonrpc": "2.0", "method": "a", "params": [1], "id": 0},
+                {"jsonrpc": "2.0", "method": "b"},
+                {"jsonrpc": "2.0", "method": "c", "id": 2},
+            ])
+        );
+
+        let mut custom = BatchBuilder::with_ids(|| Id::from("x"));
+        assert!(custom.is_empty());
+        assert_eq!(custom.call("a", None).id(), &Id::from("x"));
+    }
+
+    #[test]
+    fn tickets() {
+        let mut builder = BatchBuilder::new();
+        let first = builder.call("a", None);
+        let second = builder.call("b", None);
+        let unanswered = builder.call("c", None);
+        let mut responses = BatchResponses::from(MaybeBatchedResponse::Batch(vec![
+            failure(json!(null)),
+            response(json!(1), json!("second")),
+            response(json!(0), json!("first")),
+            response(json!(0), json!("again")),
+        ]));
+        assert_eq!(responses.take(&second), Some(Ok(json!("second"))));
+        assert_eq!(responses.take(&second), None);
+        assert_eq!(responses.take(&unanswered), None);
+        assert_eq!(
+            responses.into_remaining().collect::<Vec<_>>(),
+            [
+                response(json!(0), json!("first")),
+                failure(json!(null)),
+                response(json!(0), json!("again"))
+            ]
+        );
+
+        let mut single = BatchResponses::from(MaybeBatchedResponse::Single(failure(json!(0))));
+        assert_eq!(single.take(&first), Some(failure(json!(0)).result));
+    }
+}
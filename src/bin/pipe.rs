@@ -0,0 +1,257 @@
+use std::io::{self, BufRead as _, Write as _};
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::thread;
+
+use clap::Parser;
+use jsonrpcli::{
+    client::ClientError, Client, Error, Id, MaybeBatchedRequest, MaybeBatchedResponse, Request,
+    Response, V2,
+};
+
+/// Forward newline-delimited requests on stdin to `url`,
+/// writing each response to stdout in the order the requests arrived.
+///
+/// Lines which aren't requests, and requests which couldn't be sent,
+/// are answered with an error response, as a server would.
+#[derive(Parser)]
+struct Args {
+    url: String,
+    /// Send the members of each batch as individual requests,
+    /// reassembling the responses in the original order.
+    #[arg(long)]
+    split_batch: bool,
+    /// How many requests to have in flight at once when splitting batches.
+    #[arg(long, default_value = "1", requires = "split_batch")]
+    concurrency: NonZeroUsize,
+}
+
+fn main() -> anyhow::Result<()> {
+    let Args {
+        url,
+        split_batch,
+        concurrency,
+    } = Args::parse();
+    let client = Client::new(url);
+    let mut stdout = io::stdout().lock();
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match jsonrpcli::parse::from_slice::<MaybeBatchedRequest>(line.as_bytes()) {
+            Ok(request) => match send(&client, &request, split_batch, concurrency) {
+                Ok(it) => it,
+                Err(e) => failed(&request, &e),
+            },
+            Err(e) => Some(MaybeBatchedResponse::Single(Response {
+                jsonrpc: V2,
+                result: Err(match e.classify() {
+                    serde_json::error::Category::Data => Error::invalid_request(e, None),
+                    _ => Error::parse_error(e, None),
+                }),
+                id: Id::Null,
+            })),
+        };
+        if let Some(response) = response {
+            serde_json::to_writer(&mut stdout, &response)?;
+            writeln!(stdout)?;
+        }
+    }
+    Ok(())
+}
+
+fn send(
+    client: &Client,
+    request: &MaybeBatchedRequest,
+    split_batch: bool,
+    concurrency: NonZeroUsize,
+) -> Result<Option<MaybeBatchedResponse>, ClientError> {
+    match request {
+        // Like a server, which wouldn't forward an empty batch.
+        MaybeBatchedRequest::Batch(batch) if split_batch && batch.is_empty() => {
+            Ok(Some(MaybeBatchedResponse::Single(Response {
+                jsonrpc: V2,
+                result: Err(Error::invalid_request("empty batch", None)),
+                id: Id::Null,
+            })))
+        }
+        MaybeBatchedRequest::Batch(batch) if split_batch => {
+            let responses = call_split(client, batch.clone(), concurrency);
+            // Match the server, which wouldn't respond to a batch of notifications.
+            match responses.is_empty() {
+                true => Ok(None),
+                false => Ok(Some(MaybeBatchedResponse::Batch(responses))),
+            }
+        }
+        request => client.send(request),
+    }
+}
+
+/// Error responses to each call in `request`, which couldn't be sent.
+fn failed(request: &MaybeBatchedRequest, e: &ClientError) -> Option<MaybeBatchedResponse> {
+    let response = |request: &Request| {
+        Some(Response {
+            jsonrpc: V2,
+            result: Err(Error::internal_error(e, None)),
+            id: request.id.clone()?,
+        })
+    };
+    match request {
+        MaybeBatchedRequest::Single(it) => response(it).map(MaybeBatchedResponse::Single),
+        MaybeBatchedRequest::Batch(it) => {
+            let responses = it.iter().filter_map(response).collect::<Vec<_>>();
+            match responses.is_empty() {
+                true => None,
+                false => Some(MaybeBatchedResponse::Batch(responses)),
+            }
+        }
+    }
+}
+
+/// Send each member of `batch` as its own request, with up to `concurrency` in flight.
+///
+/// Responses are returned in the order of their requests,
+/// and members which couldn't be sent are answered with an error.
+fn call_split(client: &Client, batch: Vec<Request>, concurrency: NonZeroUsize) -> Vec<Response> {
+    let workers = concurrency.get().min(batch.len());
+    let queue = Mutex::new(batch.into_iter().enumerate());
+    let mut responses = thread::scope(|scope| {
+        let workers = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut responses = vec![];
+                    loop {
+                        let Some((ix, request)) = queue.lock().unwrap().next() else {
+                            break responses;
+                        };
+                        let request = MaybeBatchedRequest::Single(request);
+                        let response = client
+                            .send(&request)
+                            .unwrap_or_else(|e| failed(&request, &e));
+                        if let Some(response) = response {
+                            responses.push((ix, response))
+                        }
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .map(|it| it.join().expect("worker panicked"))
+            .collect::<Vec<_>>()
+    })
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    responses.sort_by_key(|(ix, _)| *ix);
+    responses
+        .into_iter()
+        .flat_map(|(_, it)| match it {
+            MaybeBatchedResponse::Single(it) => vec![it],
+            MaybeBatchedResponse::Batch(it) => it,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufReader, Read as _},
+        net::TcpListener,
+    };
+
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    /// Serve on a local port, returning its URL.
+    ///
+    /// Calls are answered with their method, except `garbage`, which gets a body that isn't JSON.
+    fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let mut length = 0;
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = line.split_once(": ") {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.parse().unwrap()
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).unwrap();
+                let request = serde_json::from_slice::<Request>(&body).unwrap();
+                let body = match (&*request.method, request.id) {
+                    ("garbage", _) => String::from("garbage"),
+                    (method, Some(id)) => {
+                        json!({"jsonrpc": "2.0", "result": method, "id": id}).to_string()
+                    }
+                    (_, None) => String::new(),
+                };
+                write!(
+                    stream.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    fn split(client: &Client, batch: Value) -> Option<Value> {
+        let batch = serde_json::from_value(batch).unwrap();
+        let response = send(client, &batch, true, NonZeroUsize::new(2).unwrap()).unwrap();
+        response.map(|it| serde_json::to_value(it).unwrap())
+    }
+
+    #[test]
+    fn split_batch() {
+        let client = Client::new(serve());
+        let response = split(
+            &client,
+            json!([
+                {"jsonrpc": "2.0", "method": "one", "id": 1},
+                {"jsonrpc": "2.0", "method": "garbage", "id": 2},
+                {"jsonrpc": "2.0", "method": "notified"},
+                {"jsonrpc": "2.0", "method": "three", "id": 3}
+            ]),
+        )
+        .unwrap();
+        let members = response.as_array().unwrap();
+        assert_eq!(members.len(), 3);
+        assert_eq!(
+            members[0],
+            json!({"jsonrpc": "2.0", "result": "one", "id": 1})
+        );
+        assert_eq!(members[1]["error"]["code"], -32603);
+        assert_eq!(members[1]["id"], 2);
+        assert_eq!(
+            members[2],
+            json!({"jsonrpc": "2.0", "result": "three", "id": 3})
+        );
+    }
+
+    #[test]
+    fn empty_batch() {
+        let client = Client::new(serve());
+        let response = split(&client, json!([])).unwrap();
+        assert_eq!(response["error"]["code"], -32600);
+        assert_eq!(response["id"], Value::Null);
+        assert_eq!(
+            split(&client, json!([{"jsonrpc": "2.0", "method": "notified"}])),
+            None
+        );
+    }
+}
@@ -1,26 +1,90 @@
-use std::io;
+use std::io::{self, Write as _};
 
-use clap::Parser;
-use jsonrpcli::{Id, Request, RequestParameters, V2};
-use serde_json::Value;
+use anyhow::Context as _;
+use clap::{Parser, ValueEnum};
+use jsonrpcli::{
+    id::{IdGenerator as _, UuidV7},
+    Id, Request, RequestParameters, V2,
+};
+use serde_json::{Map, Value};
 
 #[derive(Parser)]
 struct Args {
+    /// Use this id, instead of `null`.
     #[arg(short, long)]
     id: Option<Id>,
+    /// Generate a (time-ordered) UUID for the id, instead of `null`.
+    #[arg(long, conflicts_with = "id")]
+    uuid: bool,
+    /// How to encode the request.
+    #[arg(long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+    /// Send the params by name, given as a single JSON object,
+    /// or as `NAME=VALUE` pairs, where each value is JSON.
+    #[arg(long)]
+    named: bool,
     method: String,
-    params: Vec<Value>,
+    /// JSON values, or with `--named`, an object or `NAME=VALUE` pairs.
+    params: Vec<String>,
 }
 
-fn main() -> serde_json::Result<()> {
-    let Args { method, params, id } = Args::parse();
-    serde_json::to_writer(
-        io::stdout(),
-        &Request {
-            jsonrpc: V2,
-            method,
-            params: Some(RequestParameters::ByPosition(params)),
-            id: Some(id.unwrap_or_default()),
-        },
-    )
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    Json,
+    Msgpack,
+    Cbor,
+}
+
+fn main() -> anyhow::Result<()> {
+    let Args {
+        method,
+        params,
+        id,
+        uuid,
+        format,
+        named,
+    } = Args::parse();
+    let id = match (id, uuid) {
+        (Some(it), _) => it,
+        (None, true) => UuidV7.next_id(),
+        (None, false) => Id::Null,
+    };
+    let request = Request {
+        jsonrpc: V2,
+        method,
+        params: Some(parse_params(&params, named)?),
+        id: Some(id),
+    };
+    let bytes = match format {
+        Format::Json => serde_json::to_vec(&request)?,
+        Format::Msgpack => jsonrpcli::msgpack::to_vec(&request)?,
+        Format::Cbor => jsonrpcli::cbor::to_vec(&request)?,
+    };
+    io::stdout().write_all(&bytes)?;
+    Ok(())
+}
+
+fn parse_params(params: &[String], named: bool) -> anyhow::Result<RequestParameters> {
+    let json = |it: &str| {
+        serde_json::from_str::<Value>(it).with_context(|| format!("invalid param `{}`", it))
+    };
+    match (named, params) {
+        (false, _) => Ok(RequestParameters::ByPosition(
+            params.iter().map(|it| json(it)).collect::<Result<_, _>>()?,
+        )),
+        (true, [it]) if it.trim_start().starts_with('{') => Ok(RequestParameters::ByName(
+            serde_json::from_str(it).with_context(|| format!("invalid params `{}`", it))?,
+        )),
+        (true, _) => Ok(RequestParameters::ByName(
+            params
+                .iter()
+                .map(|it| {
+                    let (name, value) = it
+                        .split_once('=')
+                        .with_context(|| format!("expected `NAME=VALUE`, not `{}`", it))?;
+                    Ok((String::from(name), json(value)?))
+                })
+                .collect::<anyhow::Result<Map<_, _>>>()?,
+        )),
+    }
 }
@@ -0,0 +1,85 @@
+//! An HTTP API for changing [`Settings`](crate::settings::Settings) while running,
+//! on its own address.
+//!
+//! - `GET /` responds with the settings, and the number of open connections.
+//! - `POST /` with a JSON object of any of the settings changes them,
+//!   e.g `{"recording": false}` or `{"deny_method": ["debug_*"]}`,
+//!   and responds like `GET /`.
+//! - `POST /drain` stops accepting connections, and shuts down when the open ones close.
+
+use std::{convert::Infallible, sync::atomic::Ordering};
+
+use http::{header::CONTENT_TYPE, HeaderValue, Method, StatusCode};
+use http_body_util::{BodyExt as _, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::{settings::Patch, Config};
+
+/// Serve the API forever.
+pub async fn serve(listener: TcpListener, config: &'static Config) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((it, _)) => it,
+            Err(e) => {
+                tracing::warn!("admin accept error: {}", e);
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            let service = hyper::service::service_fn(|it| async move {
+                Ok::<_, Infallible>(handle(it, config).await)
+            });
+            if let Err(e) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::warn!("admin connection error: {}", e)
+            }
+        });
+    }
+}
+
+async fn handle(request: http::Request<Incoming>, config: &Config) -> http::Response<Full<Bytes>> {
+    let (parts, body) = request.into_parts();
+    match (parts.method, parts.uri.path()) {
+        (Method::GET, "/") => respond(StatusCode::OK, status(config)),
+        (Method::POST, "/") => {
+            let patch = match body.collect().await {
+                Ok(it) => {
+                    serde_json::from_slice::<Patch>(&it.to_bytes()).map_err(|e| e.to_string())
+                }
+                Err(e) => Err(e.to_string()),
+            };
+            match patch.and_then(|it| config.settings.write().unwrap().apply(it)) {
+                Ok(()) => {
+                    tracing::info!("settings changed through the admin API");
+                    respond(StatusCode::OK, status(config))
+                }
+                Err(e) => respond(StatusCode::BAD_REQUEST, json!({ "error": e })),
+            }
+        }
+        (Method::POST, "/drain") => {
+            config.drain.notify_one();
+            respond(StatusCode::ACCEPTED, status(config))
+        }
+        _ => respond(StatusCode::NOT_FOUND, json!({ "error": "not found" })),
+    }
+}
+
+fn status(config: &Config) -> Value {
+    let mut status = config.settings.read().unwrap().to_json();
+    status["connections"] = Value::from(config.connections.load(Ordering::Relaxed));
+    status
+}
+
+fn respond(status: StatusCode, body: Value) -> http::Response<Full<Bytes>> {
+    let mut response = http::Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
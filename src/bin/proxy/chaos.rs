@@ -0,0 +1,151 @@
+//! Simulating a slow or flaky upstream.
+//!
+//! Each rule is `[GLOB=]VALUE`, applying to calls to methods matching `GLOB`,
+//! or to every method if there's no glob.
+//! The first matching rule wins.
+
+use std::{fmt, str::FromStr, time::Duration};
+
+use jsonrpcli::{method::MethodGlob, stream::Members, Error};
+use serde::Deserialize;
+
+#[derive(Debug, Clone)]
+pub struct PerMethod<T> {
+    glob: Option<MethodGlob>,
+    value: T,
+}
+
+impl<T: FromStr> FromStr for PerMethod<T>
+where
+    T::Err: fmt::Display,
+{
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (glob, value) = match s.split_once('=') {
+            Some((glob, value)) => (
+                Some(
+                    glob.parse()
+                        .map_err(|e| format!("invalid method glob `{}`: {}", glob, e))?,
+                ),
+                value,
+            ),
+            None => (None, s),
+        };
+        Ok(Self {
+            glob,
+            value: value.parse().map_err(|e: T::Err| e.to_string())?,
+        })
+    }
+}
+
+pub fn find<'a, T>(rules: &'a [PerMethod<T>], method: &str) -> Option<&'a T> {
+    rules
+        .iter()
+        .find(|it| it.glob.as_ref().is_none_or(|it| it.matches(method)))
+        .map(|it| &it.value)
+}
+
+/// A fixed delay, like `100ms`, or a range to pick uniformly from, like `50ms..2s`.
+#[derive(Debug, Clone, Copy)]
+pub struct Latency {
+    min: Duration,
+    max: Duration,
+}
+
+impl Latency {
+    fn sample(&self) -> Duration {
+        let Self { min, max } = *self;
+        min + (max - min).mul_f64(fastrand::f64())
+    }
+}
+
+impl FromStr for Latency {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) = match s.split_once("..") {
+            Some((min, max)) => (duration(min)?, duration(max)?),
+            None => (duration(s)?, duration(s)?),
+        };
+        match min <= max {
+            true => Ok(Self { min, max }),
+            false => Err(String::from("the start of the range is after the end")),
+        }
+    }
+}
+
+/// Parse a duration like `100ms` or `2s`.
+pub fn duration(s: &str) -> Result<Duration, String> {
+    let parsed = match (s.strip_suffix("ms"), s.strip_suffix('s')) {
+        (Some(millis), _) => millis.parse().ok().map(Duration::from_millis),
+        (None, Some(secs)) => secs
+            .parse()
+            .ok()
+            .and_then(|it| Duration::try_from_secs_f64(it).ok()),
+        (None, None) => None,
+    };
+    parsed.ok_or_else(|| format!("expected a duration like `100ms` or `2s`, not `{}`", s))
+}
+
+/// A number between 0 and 1.
+#[derive(Debug, Clone, Copy)]
+pub struct Probability(f64);
+
+impl FromStr for Probability {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.parse::<f64>() {
+            Ok(it) if (0.0..=1.0).contains(&it) => Ok(Self(it)),
+            _ => Err(format!(
+                "expected a probability between 0 and 1, not `{}`",
+                s
+            )),
+        }
+    }
+}
+
+pub struct Chaos {
+    pub latency: Vec<PerMethod<Latency>>,
+    pub error_rate: Vec<PerMethod<Probability>>,
+    pub code: Vec<PerMethod<i64>>,
+}
+
+impl Chaos {
+    /// Whether any errors may be injected.
+    pub fn injects_errors(&self) -> bool {
+        !self.error_rate.is_empty()
+    }
+    pub fn injects_latency(&self) -> bool {
+        !self.latency.is_empty()
+    }
+    /// How long to wait before forwarding `body`, the longest delay of any of its calls.
+    pub fn delay(&self, body: &[u8]) -> Duration {
+        #[derive(Deserialize)]
+        struct Call {
+            method: String,
+        }
+        if self.latency.is_empty() {
+            return Duration::ZERO;
+        }
+        Members::<Call>::new(body)
+            .flatten()
+            .filter_map(|it| find(&self.latency, &it.method).map(Latency::sample))
+            .max()
+            .unwrap_or_default()
+    }
+    /// The error to answer a call to `method` with, instead of forwarding it, if one is injected.
+    ///
+    /// The code defaults to [`Error::SERVER_ERROR`].
+    pub fn error(&self, method: &str) -> Option<Error> {
+        let Probability(p) = find(&self.error_rate, method)?;
+        match fastrand::f64() < *p {
+            true => Some(Error::new(
+                find(&self.code, method)
+                    .copied()
+                    .unwrap_or(Error::SERVER_ERROR),
+                "injected by jsonrpcli proxy",
+                None,
+            )),
+            false => None,
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Only accepting connections from some addresses.
+
+use std::{net::IpAddr, str::FromStr};
+
+use crate::unix::Peer;
+
+/// A block of addresses, like `192.168.0.0/16`, or a single address like `10.0.0.1`.
+///
+/// Blocks of IPv4-mapped addresses, like `::ffff:10.0.0.0/104`, are the IPv4 block,
+/// and host bits are cleared, so blocks are always canonical.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|e| format!("invalid address in `{}`: {}", s, e))?;
+        let max = match addr {
+            IpAddr::V4(_) => 32,
+            IpAddr::V6(_) => 128,
+        };
+        let prefix = match prefix {
+            Some(it) => it
+                .parse::<u8>()
+                .ok()
+                .filter(|it| *it <= max)
+                .ok_or_else(|| format!("invalid prefix length in `{}`, expected 0..={}", s, max))?,
+            None => max,
+        };
+        Ok(Self::canonical(addr, prefix))
+    }
+}
+
+impl Cidr {
+    fn canonical(addr: IpAddr, prefix: u8) -> Self {
+        match addr {
+            IpAddr::V6(it) => match it.to_ipv4_mapped().filter(|_| prefix >= 96) {
+                Some(it) => Self::canonical(IpAddr::V4(it), prefix - 96),
+                None => Self {
+                    addr: IpAddr::V6(network(it.into(), prefix, 128).into()),
+                    prefix,
+                },
+            },
+            IpAddr::V4(it) => {
+                let bits = network(u32::from(it).into(), prefix, 32);
+                Self {
+                    addr: IpAddr::V4(u32::try_from(bits).expect("masked to 32 bits").into()),
+                    prefix,
+                }
+            }
+        }
+    }
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        let Self {
+            addr: block,
+            prefix,
+        } = *self;
+        match (block, canonical(addr)) {
+            (IpAddr::V4(block), IpAddr::V4(addr)) => {
+                u128::from(u32::from(block)) == network(u32::from(addr).into(), prefix, 32)
+            }
+            (IpAddr::V6(block), IpAddr::V4(addr)) => {
+                u128::from(block) == network(addr.to_ipv6_mapped().into(), prefix, 128)
+            }
+            (IpAddr::V6(block), IpAddr::V6(addr)) => {
+                u128::from(block) == network(addr.into(), prefix, 128)
+            }
+            (IpAddr::V4(_), IpAddr::V6(_)) => false,
+        }
+    }
+}
+
+/// IPv4 clients of a dual-stack listener show up as `::ffff:a.b.c.d`.
+fn canonical(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(it) => it
+            .to_ipv4_mapped()
+            .map(IpAddr::V4)
+            .unwrap_or(IpAddr::V6(it)),
+        it => it,
+    }
+}
+
+/// The first `prefix` of the `width` bits, with the rest cleared.
+fn network(bits: u128, prefix: u8, width: u8) -> u128 {
+    match prefix {
+        0 => 0,
+        _ => bits >> (width - prefix) << (width - prefix),
+    }
+}
+
+/// `--allow-cidr` and `--deny-cidr`.
+pub struct Access {
+    pub allow: Vec<Cidr>,
+    pub deny: Vec<Cidr>,
+}
+
+impl Access {
+    /// Denied addresses are refused, even if they're also allowed.
+    /// If nothing is allowed explicitly, everything else is.
+    ///
+    /// Unix socket clients are always accepted.
+    pub fn accepts(&self, peer: Peer) -> bool {
+        let Self { allow, deny } = self;
+        let Peer::Tcp(addr) = peer else {
+            return true;
+        };
+        let ip = addr.ip();
+        !deny.iter().any(|it| it.contains(ip))
+            && (allow.is_empty() || allow.iter().any(|it| it.contains(ip)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[track_caller]
+    fn contains(cidr: &str, addr: &str) -> bool {
+        cidr.parse::<Cidr>()
+            .unwrap()
+            .contains(addr.parse().unwrap())
+    }
+
+    #[test]
+    fn v4() {
+        assert!(contains("10.0.0.0/8", "10.1.2.3"));
+        assert!(!contains("10.0.0.0/8", "11.0.0.1"));
+        assert!(contains("10.1.2.3/8", "10.255.0.0"));
+        assert!(contains("10.0.0.1/32", "10.0.0.1"));
+        assert!(!contains("10.0.0.1/32", "10.0.0.2"));
+        assert!(contains("10.0.0.1", "10.0.0.1"));
+        assert!(!contains("10.0.0.1", "10.0.0.2"));
+        assert!(contains("0.0.0.0/0", "192.168.1.1"));
+        assert!(!contains("0.0.0.0/0", "::1"));
+        assert!(contains("10.0.0.0/8", "::ffff:10.0.0.1"));
+    }
+
+    #[test]
+    fn v6() {
+        assert!(contains("fd00::/8", "fd12::1"));
+        assert!(!contains("fd00::/8", "fe80::1"));
+        assert!(contains("::1", "::1"));
+        assert!(!contains("::1/128", "::2"));
+        assert!(contains("::/0", "2001:db8::1"));
+        assert!(contains("::/0", "10.0.0.1"));
+        assert!(!contains("fd00::/8", "10.0.0.1"));
+    }
+
+    #[test]
+    fn mapped() {
+        assert_eq!(
+            "::ffff:10.0.0.0/104".parse::<Cidr>(),
+            "10.0.0.0/8".parse::<Cidr>()
+        );
+        assert!(contains("::ffff:10.0.0.0/104", "10.0.0.1"));
+        assert!(contains("::ffff:10.0.0.0/104", "::ffff:10.0.0.1"));
+        assert!(!contains("::ffff:10.0.0.0/104", "11.0.0.1"));
+        assert!(contains("::ffff:10.0.0.1", "10.0.0.1"));
+        assert!(contains("::ffff:0.0.0.0/96", "192.168.1.1"));
+        // Shorter than the IPv4-mapped block.
+        assert!(contains("::/80", "10.0.0.1"));
+        assert!(!contains("::/80", "2001:db8::1"));
+    }
+
+    #[test]
+    fn canonical() {
+        assert_eq!("10.1.2.3/8".parse::<Cidr>(), "10.0.0.0/8".parse::<Cidr>());
+        assert_eq!("fd12::1/8".parse::<Cidr>(), "fd00::/8".parse::<Cidr>());
+    }
+
+    #[test]
+    fn invalid() {
+        for it in [
+            "10.0.0.0/33",
+            "::/129",
+            "::ffff:10.0.0.0/129",
+            "10.0.0.0/-1",
+            "10.0.0.0/",
+            "10.0.0.0/a",
+            "10.0.0/8",
+            "nope",
+        ] {
+            assert!(it.parse::<Cidr>().is_err(), "{}", it)
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Sharing one upstream response between identical calls which are in flight at the same time.
+
+use std::{collections::HashMap, future::Future, sync::Mutex};
+
+use anyhow::anyhow;
+use http::response::Parts;
+use hyper::body::Bytes;
+use jsonrpcli::{Id, Request};
+use serde_json::{Map, Value};
+use tokio::sync::watch;
+
+use crate::record::{self, Dedup};
+
+type Outcome = Result<(Parts, Bytes), String>;
+
+#[derive(Default)]
+pub struct Coalescer {
+    inflight: Mutex<HashMap<u64, watch::Receiver<Option<Outcome>>>>,
+}
+
+impl Coalescer {
+    /// Run `forward`, unless a call with the same `key` is already in flight,
+    /// in which case wait for its response instead.
+    ///
+    /// `forward` runs in a task of its own, so it finishes for the others
+    /// even if the call which started it is cancelled.
+    pub async fn run(
+        &'static self,
+        key: u64,
+        forward: impl Future<Output = anyhow::Result<(Parts, Bytes)>> + Send + 'static,
+    ) -> anyhow::Result<(Parts, Bytes)> {
+        let mut rx = {
+            let mut inflight = self.inflight.lock().unwrap();
+            match inflight.get(&key) {
+                Some(it) => it.clone(),
+                None => {
+                    let (tx, rx) = watch::channel(None);
+                    inflight.insert(key, rx.clone());
+                    tokio::spawn(async move {
+                        let _leader = Leader {
+                            coalescer: self,
+                            key,
+                        };
+                        let outcome = forward.await.map_err(|e| format!("{:#}", e));
+                        tx.send_replace(Some(outcome));
+                    });
+                    rx
+                }
+            }
+        };
+        let outcome = rx
+            .wait_for(Option::is_some)
+            .await
+            .map_err(|_| anyhow!("the call this was coalesced with failed"))?;
+        match outcome.as_ref().expect("waited for some") {
+            Ok(it) => Ok(it.clone()),
+            Err(e) => Err(anyhow!("{}", e)),
+        }
+    }
+}
+
+/// Stops others from joining a call once it's finished, or has failed.
+struct Leader {
+    coalescer: &'static Coalescer,
+    key: u64,
+}
+
+impl Drop for Leader {
+    fn drop(&mut self) {
+        self.coalescer.inflight.lock().unwrap().remove(&self.key);
+    }
+}
+
+/// Identify a single call by its method and params, returning its id.
+///
+/// Batches and notifications aren't coalesced.
+pub fn key(body: &[u8]) -> Option<(u64, Id)> {
+    let request = serde_json::from_slice::<Request>(body).ok()?;
+    let id = request.id.clone()?;
+    Some((
+        record::fingerprint(&request, &Ok(Value::Null), Dedup::Params),
+        id,
+    ))
+}
+
+/// Give a response to a single call the id `id`.
+pub fn with_id(body: Bytes, id: &Id) -> Bytes {
+    let id = serde_json::to_value(id).expect("ids always serialize");
+    match serde_json::from_slice::<Map<String, Value>>(&body) {
+        Ok(mut it) if it.get("id").is_some_and(|it| *it != id) => {
+            it.insert(String::from("id"), id);
+            Bytes::from(serde_json::to_vec(&it).expect("values always serialize"))
+        }
+        _ => body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
+
+    use tokio::sync::oneshot;
+
+    use super::*;
+
+    fn response(body: &'static str) -> (Parts, Bytes) {
+        let (parts, ()) = http::Response::new(()).into_parts();
+        (parts, Bytes::from_static(body.as_bytes()))
+    }
+
+    #[tokio::test]
+    async fn coalesced() {
+        let coalescer = &*Box::leak(Box::<Coalescer>::default());
+        let forwarded = &*Box::leak(Box::new(AtomicUsize::new(0)));
+        let (release, released) = oneshot::channel::<()>();
+        let leader = coalescer.run(1, async move {
+            forwarded.fetch_add(1, Ordering::Relaxed);
+            released.await?;
+            Ok(response("shared"))
+        });
+        let follower = coalescer.run(1, async move {
+            forwarded.fetch_add(1, Ordering::Relaxed);
+            Ok(response("not shared"))
+        });
+        release.send(()).unwrap();
+        let (leader, follower) = tokio::join!(leader, follower);
+        assert_eq!(leader.unwrap().1, "shared");
+        assert_eq!(follower.unwrap().1, "shared");
+        assert_eq!(forwarded.load(Ordering::Relaxed), 1);
+
+        // Finished calls aren't joined.
+        let after = coalescer.run(1, async { Ok(response("after")) }).await;
+        assert_eq!(after.unwrap().1, "after");
+    }
+
+    #[tokio::test]
+    async fn leader_cancelled() {
+        let coalescer = &*Box::leak(Box::<Coalescer>::default());
+        let (release, released) = oneshot::channel::<()>();
+        let leader = tokio::spawn(coalescer.run(1, async move {
+            released.await?;
+            Ok(response("shared"))
+        }));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        let follower = tokio::spawn(coalescer.run(1, async { Ok(response("not shared")) }));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        leader.abort();
+        assert!(leader.await.unwrap_err().is_cancelled());
+        release.send(()).unwrap();
+        assert_eq!(follower.await.unwrap().unwrap().1, "shared");
+    }
+
+    #[tokio::test]
+    async fn failed() {
+        let coalescer = &*Box::leak(Box::<Coalescer>::default());
+        let outcome = coalescer
+            .run(1, async { Err(anyhow!("upstream gone")) })
+            .await;
+        assert_eq!(outcome.unwrap_err().to_string(), "upstream gone");
+    }
+}
@@ -0,0 +1,71 @@
+//! Letting browsers call the proxy from other origins.
+
+use std::time::Duration;
+
+use http::{
+    header::{
+        ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN,
+        ACCESS_CONTROL_MAX_AGE, ACCESS_CONTROL_REQUEST_METHOD, ORIGIN, VARY,
+    },
+    HeaderMap, HeaderName, HeaderValue, Method, StatusCode,
+};
+
+pub struct Cors {
+    /// `*` allows any origin.
+    pub origins: Vec<String>,
+    pub methods: Vec<Method>,
+    pub headers: Vec<HeaderName>,
+    pub max_age: Duration,
+}
+
+impl Cors {
+    /// `origin`, if it's allowed.
+    fn allowed<'a>(&self, origin: Option<&'a HeaderValue>) -> Option<&'a HeaderValue> {
+        let origin = origin?;
+        self.origins
+            .iter()
+            .any(|it| it == "*" || origin.as_bytes().eq_ignore_ascii_case(it.as_bytes()))
+            .then_some(origin)
+    }
+    /// The response to a preflight request, if `method` and `headers` are one.
+    ///
+    /// Preflights from origins which aren't allowed get a response without CORS headers,
+    /// which browsers treat as a refusal.
+    pub fn preflight(&self, method: &Method, headers: &HeaderMap) -> Option<http::Response<()>> {
+        if method != Method::OPTIONS || !headers.contains_key(ACCESS_CONTROL_REQUEST_METHOD) {
+            return None;
+        }
+        let mut response = http::Response::new(());
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        let origin = headers.get(ORIGIN);
+        if self.allowed(origin).is_some() {
+            let out = response.headers_mut();
+            self.respond(origin, out);
+            out.insert(
+                ACCESS_CONTROL_ALLOW_METHODS,
+                list(self.methods.iter().map(Method::as_str)),
+            );
+            out.insert(
+                ACCESS_CONTROL_ALLOW_HEADERS,
+                list(self.headers.iter().map(HeaderName::as_str)),
+            );
+            out.insert(
+                ACCESS_CONTROL_MAX_AGE,
+                HeaderValue::from(self.max_age.as_secs()),
+            );
+        }
+        Some(response)
+    }
+    /// Add CORS headers to the response to a request from `origin`, if it's allowed.
+    pub fn respond(&self, origin: Option<&HeaderValue>, out: &mut HeaderMap) {
+        if let Some(origin) = self.allowed(origin) {
+            out.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin.clone());
+            out.append(VARY, HeaderValue::from_static("origin"));
+        }
+    }
+}
+
+fn list<'a>(items: impl Iterator<Item = &'a str>) -> HeaderValue {
+    HeaderValue::try_from(items.collect::<Vec<_>>().join(", "))
+        .expect("methods and header names are valid header values")
+}
@@ -0,0 +1,163 @@
+//! A web page showing the traffic through the proxy, on its own address.
+//!
+//! - `GET /` responds with the page, which polls the endpoint below.
+//! - `GET /calls?after=SEQ` responds with the calls after `SEQ`, oldest first,
+//!   and the stats for each method, like
+//!   `{"calls": [{"seq": 1, "time": "...", "method": "eth_call", ...}], "stats": {"eth_call": {...}}}`.
+
+use std::{
+    collections::{BTreeMap, VecDeque},
+    convert::Infallible,
+    sync::Mutex,
+    time::SystemTime,
+};
+
+use http::{header::CONTENT_TYPE, HeaderValue, Method, StatusCode};
+use http_body_util::Full;
+use hyper::body::{Bytes, Incoming};
+use hyper_util::rt::TokioIo;
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+use crate::{har, log::Call, Config};
+
+/// How many calls to keep for the page.
+const KEEP: usize = 1000;
+
+const PAGE: &str = include_str!("dashboard.html");
+
+#[derive(Default)]
+pub struct Traffic(Mutex<Inner>);
+
+#[derive(Default)]
+struct Inner {
+    /// Oldest first.
+    recent: VecDeque<Entry>,
+    /// The `seq` of the next call.
+    next: u64,
+    /// For every call, not just the recent ones.
+    stats: BTreeMap<String, Stats>,
+}
+
+#[derive(Serialize)]
+struct Entry {
+    seq: u64,
+    time: String,
+    #[serde(flatten)]
+    call: Call,
+}
+
+#[derive(Default, Serialize)]
+struct Stats {
+    calls: u64,
+    /// Calls with an error code, or an HTTP status which isn't a success.
+    errors: u64,
+    total_ms: f64,
+    max_ms: f64,
+}
+
+impl Traffic {
+    pub fn push(&self, calls: Vec<Call>) {
+        let Inner {
+            recent,
+            next,
+            stats,
+        } = &mut *self.0.lock().unwrap();
+        let time = har::timestamp(SystemTime::now());
+        for call in calls {
+            let stats = stats.entry(call.method.clone()).or_default();
+            stats.calls += 1;
+            if call.error_code.is_some() || !(200..300).contains(&call.status) {
+                stats.errors += 1
+            }
+            stats.total_ms += call.duration_ms;
+            stats.max_ms = stats.max_ms.max(call.duration_ms);
+            if recent.len() == KEEP {
+                recent.pop_front();
+            }
+            *next += 1;
+            recent.push_back(Entry {
+                seq: *next,
+                time: time.clone(),
+                call,
+            })
+        }
+    }
+    fn after(&self, seq: u64) -> Value {
+        let Inner { recent, stats, .. } = &*self.0.lock().unwrap();
+        let calls = recent.iter().filter(|it| it.seq > seq).collect::<Vec<_>>();
+        json!({ "calls": calls, "stats": stats })
+    }
+}
+
+/// Serve the page forever.
+pub async fn serve(listener: TcpListener, config: &'static Config) {
+    loop {
+        let stream = match listener.accept().await {
+            Ok((it, _)) => it,
+            Err(e) => {
+                tracing::warn!("dashboard accept error: {}", e);
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            let service =
+                hyper::service::service_fn(
+                    |it| async move { Ok::<_, Infallible>(handle(it, config)) },
+                );
+            if let Err(e) = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                tracing::warn!("dashboard connection error: {}", e)
+            }
+        });
+    }
+}
+
+fn handle(request: http::Request<Incoming>, config: &Config) -> http::Response<Full<Bytes>> {
+    let Some(traffic) = &config.traffic else {
+        return respond(
+            StatusCode::NOT_FOUND,
+            "application/json",
+            json!({}).to_string(),
+        );
+    };
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, "/") => respond(StatusCode::OK, "text/html; charset=utf-8", PAGE),
+        (&Method::GET, "/calls") => {
+            let after = request
+                .uri()
+                .query()
+                .unwrap_or_default()
+                .split('&')
+                .find_map(|it| it.strip_prefix("after="))
+                .and_then(|it| it.parse().ok())
+                .unwrap_or(0);
+            respond(
+                StatusCode::OK,
+                "application/json",
+                traffic.after(after).to_string(),
+            )
+        }
+        _ => respond(
+            StatusCode::NOT_FOUND,
+            "application/json",
+            json!({ "error": "not found" }).to_string(),
+        ),
+    }
+}
+
+fn respond(
+    status: StatusCode,
+    content_type: &'static str,
+    body: impl Into<Bytes>,
+) -> http::Response<Full<Bytes>> {
+    let mut response = http::Response::new(Full::new(body.into()));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    response
+}
@@ -0,0 +1,322 @@
+//! Recording calls to a SQLite database, so they can be queried later.
+//!
+//! Each call is a row in the `calls` table, indexed by method and params,
+//! as is each notification from the upstream.
+//!
+//! Params are hashed with SHA-256, so the hashes are the same for every build,
+//! and can be computed by other tools.
+//!
+//! Rows are written on a thread of their own, so inserts don't block the runtime.
+
+use std::{
+    path::Path,
+    sync::{mpsc, Mutex},
+    thread::JoinHandle,
+};
+
+use jsonrpcli::{Error, Request};
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::{Digest as _, Sha256};
+
+use crate::record::Metadata;
+
+const SCHEMA: &str = "
+    PRAGMA journal_mode = WAL;
+    CREATE TABLE IF NOT EXISTS calls (
+        id INTEGER PRIMARY KEY,
+        method TEXT NOT NULL,
+        -- JSON.
+        request_id TEXT,
+        -- JSON, or NULL if they were omitted.
+        params TEXT,
+        -- The SHA-256, in lowercase hex, of the canonical JSON of `[method, params]`,
+        -- like `jsonrpcli::canonical::to_vec`, with params omitted as `null`.
+        params_hash TEXT NOT NULL,
+        -- JSON. Exactly one of `result` and `error` is set, unless this is a notification.
+        result TEXT,
+        error TEXT,
+        -- The bodies of the call and its response, as they were sent, but redacted.
+        -- Members of a batch are recorded separately.
+        -- Notifications from the upstream are the `request`, with no `response`.
+        request TEXT NOT NULL,
+        response TEXT,
+        -- RFC 3339.
+        started TEXT NOT NULL,
+        duration_ms REAL,
+        status INTEGER NOT NULL,
+        upstream TEXT
+    );
+    CREATE INDEX IF NOT EXISTS calls_by_params ON calls (method, params_hash);
+";
+
+pub struct Db {
+    rows: Mutex<Option<mpsc::Sender<Row>>>,
+    writer: Mutex<Option<JoinHandle<()>>>,
+}
+
+/// The columns of the `calls` table, in order.
+struct Row {
+    method: String,
+    request_id: Option<String>,
+    params: Option<String>,
+    params_hash: String,
+    result: Option<String>,
+    error: Option<String>,
+    request: String,
+    response: Option<String>,
+    started: String,
+    duration_ms: Option<f64>,
+    status: u16,
+    upstream: Option<String>,
+}
+
+impl Db {
+    /// Open the database at `path`, creating it if it doesn't exist.
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(SCHEMA)?;
+        let (tx, rx) = mpsc::channel();
+        let writer = std::thread::spawn(move || write(conn, rx));
+        Ok(Self {
+            rows: Mutex::new(Some(tx)),
+            writer: Mutex::new(Some(writer)),
+        })
+    }
+    /// Record a call, and the `bodies` of it and its response.
+    ///
+    /// Notifications from the upstream have no `result` or response.
+    pub fn insert(
+        &self,
+        request: &Request,
+        result: Option<&Result<Value, Error>>,
+        bodies: (&str, Option<&str>),
+        metadata: &Metadata,
+    ) {
+        let (result, error) = match result {
+            Some(Ok(it)) => (Some(json(it)), None),
+            Some(Err(it)) => (None, Some(json(it))),
+            None => (None, None),
+        };
+        let row = Row {
+            method: request.method.clone(),
+            request_id: request.id.as_ref().map(json),
+            params: request.params.as_ref().map(json),
+            params_hash: params_hash(request),
+            result,
+            error,
+            request: bodies.0.into(),
+            response: bodies.1.map(Into::into),
+            started: crate::har::timestamp(metadata.started),
+            duration_ms: metadata.duration.map(|it| it.as_secs_f64() * 1000.0),
+            status: metadata.status.as_u16(),
+            upstream: metadata.upstream.clone(),
+        };
+        if let Some(rows) = &*self.rows.lock().unwrap() {
+            let _ = rows.send(row);
+        }
+    }
+    /// Wait for the rows which have been inserted to be written.
+    ///
+    /// Later inserts are dropped.
+    pub fn finish(&self) {
+        drop(self.rows.lock().unwrap().take());
+        if let Some(writer) = self.writer.lock().unwrap().take() {
+            if writer.join().is_err() {
+                tracing::warn!("the database writer panicked");
+            }
+        }
+    }
+}
+
+impl Drop for Db {
+    fn drop(&mut self) {
+        self.finish()
+    }
+}
+
+fn write(conn: Connection, rows: mpsc::Receiver<Row>) {
+    for row in rows {
+        let result = conn.execute(
+            "INSERT INTO calls (method, request_id, params, params_hash, result, error, request, response, started, duration_ms, status, upstream)
+            VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                row.method,
+                row.request_id,
+                row.params,
+                row.params_hash,
+                row.result,
+                row.error,
+                row.request,
+                row.response,
+                row.started,
+                row.duration_ms,
+                row.status,
+                row.upstream,
+            ],
+        );
+        if let Err(e) = result {
+            tracing::warn!("couldn't write recording: {}", e)
+        }
+    }
+}
+
+/// See the `params_hash` column.
+fn params_hash(request: &Request) -> String {
+    let canonical = jsonrpcli::canonical::to_vec(&(&request.method, &request.params))
+        .expect("calls always serialize");
+    Sha256::digest(canonical)
+        .iter()
+        .map(|it| format!("{:02x}", it))
+        .collect()
+}
+
+fn json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).expect("values always serialize")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, SystemTime};
+
+    use http::StatusCode;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn insert() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("calls.db");
+        let db = Db::open(&path).unwrap();
+        let metadata = Metadata {
+            started: SystemTime::UNIX_EPOCH,
+            duration: Some(Duration::from_millis(5)),
+            status: StatusCode::OK,
+            upstream: Some(String::from("http://upstream/")),
+        };
+        let request = |params| {
+            serde_json::from_value::<Request>(
+                json!({"jsonrpc": "2.0", "method": "m", "params": params, "id": 1}),
+            )
+            .unwrap()
+        };
+        let bodies = (
+            r#"{"jsonrpc": "2.0", "method": "m", "params": [1], "id": 1}"#,
+            Some(r#"{"jsonrpc": "2.0", "result": "one", "id": 1}"#),
+        );
+        db.insert(
+            &request(json!([1])),
+            Some(&Ok(json!("one"))),
+            bodies,
+            &metadata,
+        );
+        db.insert(
+            &request(json!([1])),
+            Some(&Err(Error::new(-1, "failed", None))),
+            bodies,
+            &metadata,
+        );
+        db.insert(
+            &request(json!([2])),
+            Some(&Ok(json!("two"))),
+            bodies,
+            &metadata,
+        );
+
+        let notification = serde_json::from_value::<Request>(
+            json!({"jsonrpc": "2.0", "method": "subscription", "params": {"result": 1}}),
+        )
+        .unwrap();
+        db.insert(
+            &notification,
+            None,
+            (r#"{"method": "subscription"}"#, None),
+            &metadata,
+        );
+        drop(db);
+
+        let conn = Connection::open(&path).unwrap();
+        let mut statement = conn
+            .prepare("SELECT params, result, error, started, duration_ms, status, upstream FROM calls WHERE method = 'm' ORDER BY id")
+            .unwrap();
+        let rows = statement
+            .query_map([], |it| {
+                Ok((
+                    it.get::<_, String>(0)?,
+                    it.get::<_, Option<String>>(1)?,
+                    it.get::<_, Option<String>>(2)?,
+                    it.get::<_, String>(3)?,
+                    it.get::<_, f64>(4)?,
+                    it.get::<_, u16>(5)?,
+                    it.get::<_, String>(6)?,
+                ))
+            })
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            rows[0],
+            (
+                String::from("[1]"),
+                Some(String::from("\"one\"")),
+                None,
+                crate::har::timestamp(SystemTime::UNIX_EPOCH),
+                5.0,
+                200,
+                String::from("http://upstream/"),
+            )
+        );
+        let (id, request, response) = conn
+            .query_row(
+                "SELECT request_id, request, response FROM calls ORDER BY id",
+                [],
+                |it| {
+                    Ok((
+                        it.get::<_, String>(0)?,
+                        it.get::<_, String>(1)?,
+                        it.get::<_, Option<String>>(2)?,
+                    ))
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            (id.as_str(), request.as_str(), response.as_deref()),
+            ("1", bodies.0, bodies.1)
+        );
+        assert_eq!(
+            rows[1].2.as_deref(),
+            Some(r#"{"code":-1,"message":"failed"}"#)
+        );
+        let hashes = conn
+            .prepare("SELECT params_hash FROM calls ORDER BY id")
+            .unwrap()
+            .query_map([], |it| it.get::<_, String>(0))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(hashes[0], hashes[1]);
+        let row = conn
+            .query_row(
+                "SELECT request_id, result, error, response FROM calls WHERE method = 'subscription'",
+                [],
+                |it| {
+                    Ok([
+                        it.get::<_, Option<String>>(0)?,
+                        it.get(1)?,
+                        it.get(2)?,
+                        it.get(3)?,
+                    ])
+                },
+            )
+            .unwrap();
+        assert_eq!(row, [None, None, None, None]);
+        assert_ne!(hashes[0], hashes[2]);
+        // The same for every build.
+        assert_eq!(
+            hashes[0],
+            "071a0c9b06f9c86c33cf460ee18915d11f9272c237b591aed04c4d3d5bb4a0a4"
+        );
+    }
+}
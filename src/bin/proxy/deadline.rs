@@ -0,0 +1,58 @@
+//! Giving up on slow upstreams.
+
+use std::{str::FromStr, time::Duration};
+
+use hyper::body::Bytes;
+use jsonrpcli::{stream::Members, Error};
+use serde::Deserialize;
+
+use crate::{
+    chaos::{self, PerMethod},
+    policy,
+};
+
+/// How long to wait for the upstream, like `500ms` or `30s`.
+#[derive(Debug, Clone, Copy)]
+pub struct Timeout(pub Duration);
+
+impl FromStr for Timeout {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        chaos::duration(s).map(Self)
+    }
+}
+
+pub struct Deadlines(pub Vec<PerMethod<Timeout>>);
+
+impl Deadlines {
+    /// How long to wait for the upstream to respond to `body`, the longest timeout of any of its calls.
+    ///
+    /// Returns [`None`] if any call has no timeout.
+    pub fn timeout(&self, body: &[u8]) -> Option<Duration> {
+        #[derive(Deserialize)]
+        struct Call {
+            method: String,
+        }
+        let Self(rules) = self;
+        if rules.is_empty() {
+            return None;
+        }
+        Members::<Call>::new(body)
+            .flatten()
+            .map(|it| chaos::find(rules, &it.method).map(|Timeout(it)| *it))
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .max()
+    }
+}
+
+/// Answer each call in `body` with an error, because the upstream didn't respond within `after`.
+pub fn timed_out(body: Bytes, after: Duration) -> http::Response<Vec<u8>> {
+    let filtered = policy::filter(body, |_| {
+        Some(Err(Error::server_error(
+            format_args!("the upstream didn't respond within {:?}", after),
+            None,
+        )))
+    });
+    jsonrpcli::http::respond(filtered.response())
+}
@@ -0,0 +1,105 @@
+//! Decoding compressed responses, so that they can be recorded.
+//!
+//! Only `gzip`, `deflate` and `br` are supported.
+
+use std::io::Read as _;
+
+use flate2::read::{DeflateDecoder, MultiGzDecoder, ZlibDecoder};
+use http::{header::CONTENT_ENCODING, HeaderMap};
+use hyper::body::Bytes;
+
+/// Undo the `Content-Encoding` in `headers`.
+pub fn decode(headers: &HeaderMap, body: &Bytes) -> Result<Bytes, String> {
+    let mut decoded = body.clone();
+    let codings = headers
+        .get_all(CONTENT_ENCODING)
+        .iter()
+        .flat_map(|it| it.to_str().unwrap_or_default().split(','))
+        .map(str::trim)
+        .filter(|it| !it.is_empty())
+        .collect::<Vec<_>>();
+    // Codings are listed in the order they were applied.
+    for coding in codings.into_iter().rev() {
+        let mut out = vec![];
+        let read = match coding.to_ascii_lowercase().as_str() {
+            "identity" => continue,
+            "gzip" | "x-gzip" => MultiGzDecoder::new(&*decoded).read_to_end(&mut out),
+            // Should be zlib, but some servers send raw deflate.
+            "deflate" => ZlibDecoder::new(&*decoded)
+                .read_to_end(&mut out)
+                .or_else(|_| {
+                    out.clear();
+                    DeflateDecoder::new(&*decoded).read_to_end(&mut out)
+                }),
+            "br" => brotli_decompressor::Decompressor::new(&*decoded, 4096).read_to_end(&mut out),
+            _ => return Err(format!("unsupported content encoding `{}`", coding)),
+        };
+        read.map_err(|e| format!("invalid `{}` body: {}", coding, e))?;
+        decoded = Bytes::from(out)
+    }
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write as _;
+
+    use flate2::{
+        write::{DeflateEncoder, GzEncoder, ZlibEncoder},
+        Compression,
+    };
+    use http::HeaderValue;
+
+    use super::*;
+
+    const BODY: &[u8] = br#"{"jsonrpc":"2.0","result":1,"id":1}"#;
+
+    fn encoded(codings: &str, body: &[u8]) -> Result<Bytes, String> {
+        let mut headers = HeaderMap::new();
+        headers.insert(CONTENT_ENCODING, HeaderValue::from_str(codings).unwrap());
+        decode(&headers, &Bytes::copy_from_slice(body))
+    }
+
+    fn gzip(body: &[u8]) -> Vec<u8> {
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(body).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(body: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        brotli::BrotliCompress(&mut &*body, &mut out, &Default::default()).unwrap();
+        out
+    }
+
+    #[test]
+    fn codings() {
+        let mut zlib = ZlibEncoder::new(vec![], Compression::default());
+        zlib.write_all(BODY).unwrap();
+        let mut deflate = DeflateEncoder::new(vec![], Compression::default());
+        deflate.write_all(BODY).unwrap();
+        for (codings, body) in [
+            ("identity", BODY.to_vec()),
+            ("gzip", gzip(BODY)),
+            ("x-gzip", gzip(BODY)),
+            ("deflate", zlib.finish().unwrap()),
+            ("deflate", deflate.finish().unwrap()),
+            ("br", brotli(BODY)),
+            ("gzip, br", brotli(&gzip(BODY))),
+        ] {
+            assert_eq!(encoded(codings, &body).unwrap(), BODY, "{}", codings)
+        }
+        assert_eq!(decode(&HeaderMap::new(), &Bytes::from(BODY)).unwrap(), BODY);
+    }
+
+    #[test]
+    fn unsupported() {
+        assert_eq!(
+            encoded("zstd", BODY).unwrap_err(),
+            "unsupported content encoding `zstd`"
+        );
+        assert!(encoded("br", BODY)
+            .unwrap_err()
+            .starts_with("invalid `br` body"));
+    }
+}
@@ -0,0 +1,146 @@
+//! Sending calls to a fallback upstream when the primary fails.
+
+use std::{
+    fmt,
+    str::FromStr,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use http::{response::Parts, Uri};
+use hyper::body::Bytes;
+use jsonrpcli::stream::Members;
+use serde::Deserialize;
+
+use crate::redact;
+
+/// A reason to fail over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trigger {
+    /// The connection to the upstream failed.
+    Connect,
+    /// The upstream didn't respond in time.
+    Timeout,
+    /// The upstream responded with a 5xx status.
+    ServerError,
+    /// The upstream responded with this JSON-RPC error code, for any call.
+    Code(i64),
+}
+
+impl FromStr for Trigger {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "connect" => Ok(Self::Connect),
+            "timeout" => Ok(Self::Timeout),
+            "5xx" => Ok(Self::ServerError),
+            _ => s.parse().map(Self::Code).map_err(|_| {
+                format!(
+                    "expected `connect`, `timeout`, `5xx` or a JSON-RPC error code, not `{}`",
+                    s
+                )
+            }),
+        }
+    }
+}
+
+/// Why a call to an upstream failed.
+#[derive(Debug)]
+pub enum Failure {
+    Connect(anyhow::Error),
+    Timeout(Duration),
+    Other(anyhow::Error),
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Failure::Connect(e) => write!(f, "couldn't connect to upstream: {:#}", e),
+            Failure::Timeout(it) => write!(f, "upstream didn't respond within {:?}", it),
+            Failure::Other(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+impl std::error::Error for Failure {}
+
+pub struct Failover {
+    pub fallback: Uri,
+    triggers: Vec<Trigger>,
+    /// How long to wait for the primary, for [`Trigger::Timeout`].
+    pub timeout: Duration,
+    cooldown: Duration,
+    /// Until when calls skip the primary.
+    until: Mutex<Option<Instant>>,
+}
+
+impl Failover {
+    /// If `triggers` is empty, connection errors, timeouts and 5xx statuses are all triggers.
+    pub fn new(
+        fallback: Uri,
+        triggers: Vec<Trigger>,
+        timeout: Duration,
+        cooldown: Duration,
+    ) -> Self {
+        Self {
+            fallback,
+            triggers: match triggers.is_empty() {
+                true => vec![Trigger::Connect, Trigger::Timeout, Trigger::ServerError],
+                false => triggers,
+            },
+            timeout,
+            cooldown,
+            until: Mutex::new(None),
+        }
+    }
+    /// Whether calls should skip the primary, because it failed recently.
+    pub fn cooling_down(&self) -> bool {
+        self.until
+            .lock()
+            .unwrap()
+            .is_some_and(|it| Instant::now() < it)
+    }
+    /// Why `outcome` from the primary is a reason to fail over, if it is.
+    pub fn trigger(&self, outcome: &Result<(Parts, Bytes), Failure>) -> Option<String> {
+        self.triggers.iter().find_map(|it| match (it, outcome) {
+            (Trigger::Connect, Err(e @ Failure::Connect(_)))
+            | (Trigger::Timeout, Err(e @ Failure::Timeout(_))) => Some(e.to_string()),
+            (Trigger::ServerError, Ok((parts, _))) if parts.status.is_server_error() => {
+                Some(format!("upstream responded with {}", parts.status))
+            }
+            (Trigger::Code(code), Ok((_, body))) if error_codes(body).contains(code) => {
+                Some(format!("upstream responded with error code {}", code))
+            }
+            _ => None,
+        })
+    }
+    /// Skip the primary for the cool-down.
+    pub fn trip(&self, reason: &str) {
+        let mut until = self.until.lock().unwrap();
+        if until.is_none_or(|it| it <= Instant::now()) {
+            tracing::warn!(
+                "failing over to {} for {:?}: {}",
+                redact::uri(&self.fallback),
+                self.cooldown,
+                reason
+            )
+        }
+        *until = Some(Instant::now() + self.cooldown)
+    }
+}
+
+/// The error codes in a (possibly batched) response.
+fn error_codes(body: &[u8]) -> Vec<i64> {
+    #[derive(Deserialize)]
+    struct Member {
+        error: Option<Code>,
+    }
+    #[derive(Deserialize)]
+    struct Code {
+        code: i64,
+    }
+    Members::<Member>::new(body)
+        .flatten()
+        .filter_map(|it| Some(it.error?.code))
+        .collect()
+}
@@ -0,0 +1,228 @@
+//! Accumulate exchanges with the upstream into an HTTP Archive (HAR 1.2),
+//! for loading into browser devtools and other HTTP tools.
+//!
+//! Credentials are removed from the URLs and headers.
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use http::{
+    header::{CONTENT_LENGTH, CONTENT_TYPE},
+    HeaderMap,
+};
+use serde::Serialize;
+
+use crate::redact;
+
+#[derive(Debug, Default)]
+pub struct Log {
+    entries: Vec<Entry>,
+}
+
+impl Log {
+    /// Record an exchange which started at `started`, and took `time`.
+    pub fn push(
+        &mut self,
+        started: SystemTime,
+        time: Duration,
+        url: String,
+        (request, request_body): (&http::request::Parts, String),
+        (response, response_body): (&http::response::Parts, String),
+    ) {
+        let millis = time.as_micros() as f64 / 1000.0;
+        self.entries.push(Entry {
+            started_date_time: timestamp(started),
+            time: millis,
+            request: Request {
+                method: request.method.to_string(),
+                url,
+                http_version: format!("{:?}", request.version),
+                headers: headers(&request.headers),
+                query_string: vec![],
+                cookies: vec![],
+                headers_size: -1,
+                body_size: request_body.len(),
+                post_data: PostData {
+                    mime_type: mime_type(&request.headers),
+                    text: request_body,
+                },
+            },
+            response: Response {
+                status: response.status.as_u16(),
+                status_text: String::from(response.status.canonical_reason().unwrap_or_default()),
+                http_version: format!("{:?}", response.version),
+                headers: headers(&response.headers),
+                cookies: vec![],
+                content: Content {
+                    size: response_body.len(),
+                    mime_type: mime_type(&response.headers),
+                    text: response_body,
+                },
+                redirect_url: String::new(),
+                headers_size: -1,
+                body_size: response_body_size(&response.headers),
+            },
+            cache: Cache {},
+            timings: Timings {
+                send: 0.0,
+                wait: millis,
+                receive: 0.0,
+            },
+        })
+    }
+    pub fn to_har(&self) -> impl Serialize + '_ {
+        #[derive(Serialize)]
+        struct Har<'a> {
+            log: Inner<'a>,
+        }
+        #[derive(Serialize)]
+        struct Inner<'a> {
+            version: &'static str,
+            creator: Creator,
+            entries: &'a [Entry],
+        }
+        #[derive(Serialize)]
+        struct Creator {
+            name: &'static str,
+            version: &'static str,
+        }
+        Har {
+            log: Inner {
+                version: "1.2",
+                creator: Creator {
+                    name: env!("CARGO_PKG_NAME"),
+                    version: env!("CARGO_PKG_VERSION"),
+                },
+                entries: &self.entries,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    started_date_time: String,
+    /// Milliseconds.
+    time: f64,
+    request: Request,
+    response: Response,
+    cache: Cache,
+    timings: Timings,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Request {
+    method: String,
+    url: String,
+    http_version: String,
+    headers: Vec<Header>,
+    query_string: Vec<Header>,
+    cookies: Vec<Header>,
+    headers_size: i64,
+    body_size: usize,
+    post_data: PostData,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PostData {
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Response {
+    status: u16,
+    status_text: String,
+    http_version: String,
+    headers: Vec<Header>,
+    cookies: Vec<Header>,
+    content: Content,
+    #[serde(rename = "redirectURL")]
+    redirect_url: String,
+    headers_size: i64,
+    /// `-1` if unknown.
+    body_size: i64,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Content {
+    size: usize,
+    mime_type: String,
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+struct Cache {}
+
+#[derive(Debug, Serialize)]
+struct Timings {
+    send: f64,
+    wait: f64,
+    receive: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct Header {
+    name: String,
+    value: String,
+}
+
+fn headers(headers: &HeaderMap) -> Vec<Header> {
+    headers
+        .iter()
+        .map(|(name, value)| Header {
+            name: name.to_string(),
+            value: redact::header(name, value),
+        })
+        .collect()
+}
+
+fn mime_type(headers: &HeaderMap) -> String {
+    headers
+        .get(CONTENT_TYPE)
+        .and_then(|it| it.to_str().ok())
+        .unwrap_or("application/json")
+        .to_owned()
+}
+
+/// The response body may have been compressed.
+fn response_body_size(headers: &HeaderMap) -> i64 {
+    headers
+        .get(CONTENT_LENGTH)
+        .and_then(|it| it.to_str().ok()?.parse().ok())
+        .unwrap_or(-1)
+}
+
+/// ISO 8601, in UTC.
+pub fn timestamp(time: SystemTime) -> String {
+    let since = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since.as_secs();
+    let (days, rem) = (secs / 86400, secs % 86400);
+    // Howard Hinnant's `civil_from_days`.
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = match mp < 10 {
+        true => mp + 3,
+        false => mp - 9,
+    };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60,
+        since.subsec_millis()
+    )
+}
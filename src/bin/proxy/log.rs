@@ -0,0 +1,133 @@
+//! Logging to stderr, as text or as JSON objects.
+//!
+//! Verbosity is set with `RUST_LOG`, like `RUST_LOG=debug`, defaulting to `info`.
+
+use std::{
+    collections::HashMap,
+    io::{self, IsTerminal as _},
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use http::StatusCode;
+use jsonrpcli::{stream::Members, Id};
+use serde::{Deserialize, Serialize};
+use tracing_subscriber::EnvFilter;
+
+use crate::unix::Peer;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// The message, followed by any fields as `key=value`.
+    Text,
+    /// A JSON object per line, with `timestamp`, `level` and `message` members,
+    /// and a member for each field.
+    Json,
+}
+
+/// # Panics
+/// - If called more than once.
+pub fn init(format: Format) {
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::builder()
+                .with_default_directive(tracing::Level::INFO.into())
+                .from_env_lossy(),
+        )
+        .with_writer(io::stderr)
+        .with_ansi(io::stderr().is_terminal());
+    match format {
+        Format::Text => builder.init(),
+        Format::Json => builder
+            .json()
+            .flatten_event(true)
+            .with_current_span(false)
+            .init(),
+    }
+}
+
+/// Where a response came from, in its extensions.
+#[derive(Debug, Clone)]
+pub struct Upstream(pub String);
+
+/// A call in an exchange with a client, as it's logged.
+#[derive(Debug, Clone, Serialize)]
+pub struct Call {
+    pub method: String,
+    /// Serialized.
+    pub id: Option<String>,
+    pub client: String,
+    pub upstream: Option<String>,
+    pub status: u16,
+    pub duration_ms: f64,
+    pub error_code: Option<i64>,
+}
+
+/// The calls in a (possibly batched) exchange with a client.
+///
+/// Calls which were `answered` without an upstream don't have an `upstream`.
+pub fn calls(
+    client: Peer,
+    request: &[u8],
+    status: StatusCode,
+    response: &[u8],
+    upstream: Option<&Upstream>,
+    answered: &[Id],
+    duration: Duration,
+) -> Vec<Call> {
+    #[derive(Deserialize)]
+    struct Request {
+        method: String,
+        id: Option<Id>,
+    }
+    #[derive(Deserialize)]
+    struct Member {
+        id: Option<Id>,
+        error: Option<Code>,
+    }
+    #[derive(Deserialize)]
+    struct Code {
+        code: i64,
+    }
+    let codes = Members::<Member>::new(response)
+        .flatten()
+        .filter_map(|it| Some((it.id?, it.error?.code)))
+        .collect::<HashMap<_, _>>();
+    Members::<Request>::new(request)
+        .flatten()
+        .map(|Request { method, id }| Call {
+            method,
+            error_code: id.as_ref().and_then(|it| codes.get(it)).copied(),
+            upstream: upstream
+                .filter(|_| id.as_ref().is_none_or(|it| !answered.contains(it)))
+                .map(|it| it.0.clone()),
+            id: id.map(|it| serde_json::to_string(&it).expect("ids always serialize")),
+            client: client.to_string(),
+            status: status.as_u16(),
+            duration_ms: duration.as_micros() as f64 / 1000.0,
+        })
+        .collect()
+}
+
+/// Log a call from [`calls`].
+pub fn access(call: &Call) {
+    let Call {
+        method,
+        id,
+        client,
+        upstream,
+        status,
+        duration_ms,
+        error_code,
+    } = call;
+    tracing::info!(
+        method = method.as_str(),
+        id = id.as_deref(),
+        client = client.as_str(),
+        upstream = upstream.as_deref(),
+        status,
+        duration_ms,
+        error_code,
+        "call"
+    );
+}
@@ -0,0 +1,1652 @@
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::Context as _;
+use clap::{Parser, ValueEnum};
+use http::{
+    header::{AUTHORIZATION, CONTENT_LENGTH, ORIGIN},
+    HeaderMap, HeaderName, HeaderValue, StatusCode, Uri, Version,
+};
+use http_body_util::{combinators::UnsyncBoxBody, BodyExt as _, Full};
+use hyper::body::{Bytes, Incoming};
+use hyper_util::client::legacy::Client;
+use jsonrpcli::{contract::Contract, method::MethodGlob, stream::Members, Id};
+use serde_json::value::RawValue;
+use std::pin::pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpListener;
+use tokio::sync::Notify;
+
+use crate::chaos::{Chaos, Latency, PerMethod, Probability};
+use crate::cidr::{Access, Cidr};
+use crate::coalesce::Coalescer;
+use crate::cors::Cors;
+use crate::dashboard::Traffic;
+use crate::db::Db;
+use crate::deadline::{Deadlines, Timeout};
+use crate::failover::{Failover, Failure, Trigger};
+use crate::policy::Filtered;
+use crate::record::{Dedup, Envelope, Metadata, Pending};
+use crate::reload::Flags;
+use crate::replay::Replay;
+use crate::rewrite::{Applied, Rules};
+use crate::route::{Route, Routes};
+use crate::settings::Settings;
+use crate::shadow::Shadow;
+use crate::sink::{Rotating, Sink};
+use crate::subscription::{Subscription, Subscriptions};
+use crate::tee::Tee;
+use crate::unix::{Connector, Listener, Local, Peer};
+use crate::upstream::{Balance, Lease};
+
+mod admin;
+mod chaos;
+mod cidr;
+mod coalesce;
+mod cors;
+mod dashboard;
+mod db;
+mod deadline;
+mod encoding;
+mod failover;
+mod har;
+mod log;
+mod openrpc;
+mod policy;
+mod record;
+mod redact;
+mod reload;
+mod replay;
+mod rewrite;
+mod route;
+mod settings;
+mod shadow;
+mod sink;
+mod sse;
+mod subscription;
+mod tee;
+mod tls;
+mod unix;
+mod upstream;
+mod ws;
+
+struct Config {
+    /// Replaced on reload.
+    routes: RwLock<Arc<Routes>>,
+    record_errors: bool,
+    emit: Emit,
+    document: Mutex<openrpc::Document>,
+    har: Mutex<har::Log>,
+    sink: Mutex<Sink>,
+    /// Pairings are recorded here instead of the sink.
+    db: Option<Db>,
+    dedup: Option<Dedup>,
+    /// Fingerprints of the calls recorded so far, for deduplication.
+    seen: Mutex<HashSet<u64>>,
+    settings: RwLock<Settings>,
+    chaos: Chaos,
+    replay: Option<Replay>,
+    record_missing: bool,
+    contract: Option<Contract>,
+    /// Answer calls which don't match the contract with an error, instead of forwarding them.
+    openrpc_reject: bool,
+    coalesce: Option<Coalescer>,
+    /// Replaced on reload.
+    rewrite: RwLock<Option<Arc<Rules>>>,
+    max_record_bytes: usize,
+    max_body_size: Option<usize>,
+    failover: Option<Failover>,
+    deadlines: Deadlines,
+    shadow: Option<Shadow>,
+    /// Added to requests to the upstreams, replacing the client's.
+    upstream_headers: HeaderMap,
+    cors: Option<Cors>,
+    /// Recent calls, for `--dashboard`.
+    traffic: Option<Traffic>,
+    /// Open connections from clients.
+    connections: AtomicUsize,
+    /// Notified to shut down gracefully.
+    drain: Notify,
+}
+
+/// What to record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum Emit {
+    /// An `ExamplePairing` for each call, as it happens.
+    ///
+    /// When the call was made, how long it took, the HTTP status and the upstream
+    /// are in the `x-jsonrpcli-started`, `x-jsonrpcli-duration-ms`, `x-jsonrpcli-status`
+    /// and `x-jsonrpcli-upstream` fields.
+    ///
+    /// WebSocket subscriptions are recorded when they end, as the pairing for the subscribe call,
+    /// with their notifications in the `x-jsonrpcli-subscription` field.
+    Pairings,
+    /// An OpenRPC document describing every method that was called, on shutdown.
+    ///
+    /// Schemas for the params and results are inferred from the observed values,
+    /// and the calls are attached as examples.
+    Openrpc,
+    /// A HAR file of every exchange with the upstream, on shutdown.
+    ///
+    /// Credentials are removed from the URLs and headers,
+    /// and `--redact` applies to the bodies.
+    Har,
+}
+
+#[derive(Parser)]
+struct Args {
+    /// Where to listen, like `127.0.0.1:8080` or `unix:/path.sock`.
+    local: Local,
+    /// The upstream, like `http://127.0.0.1:8545` or `unix:/path.sock`.
+    #[arg(required_unless_present = "config", value_parser = unix::uri)]
+    remote: Option<Uri>,
+    /// Also balance calls across this upstream.
+    #[arg(long = "remote", value_name = "URI", value_parser = unix::uri)]
+    remotes: Vec<Uri>,
+    /// Send calls to methods matching `GLOB` to `URI` instead, e.g `debug_*=http://tracing-node`.
+    ///
+    /// The first matching glob wins, and calls which don't match any go to the remote.
+    /// Repeat a glob to balance its calls across several upstreams.
+    /// Batches are split by route, and WebSocket connections are refused.
+    #[arg(long, value_name = "GLOB=URI")]
+    route: Vec<Route>,
+    /// How to pick an upstream when there are several.
+    #[arg(long, value_enum, default_value_t = Balance::RoundRobin)]
+    balance: Balance,
+    /// How often to check the health of each upstream, when there are several.
+    ///
+    /// Upstreams which fail a check, or the connection for a call, aren't picked until they pass a check.
+    #[arg(long, value_name = "SECS", default_value_t = 5)]
+    health_interval: u64,
+    /// Send calls here when the upstream fails, see `--fallback-on`.
+    #[arg(long, value_name = "URI", value_parser = unix::uri)]
+    fallback: Option<Uri>,
+    /// What counts as the upstream failing:
+    /// `connect`, `timeout`, `5xx`, or a JSON-RPC error code in the response.
+    ///
+    /// Defaults to `connect`, `timeout` and `5xx`.
+    #[arg(
+        long,
+        value_name = "TRIGGER",
+        requires = "fallback",
+        allow_negative_numbers = true
+    )]
+    fallback_on: Vec<Trigger>,
+    /// How long to wait for the upstream before failing over.
+    #[arg(long, value_name = "DURATION", default_value = "10s", value_parser = chaos::duration)]
+    fallback_timeout: Duration,
+    /// How long to send calls straight to the fallback after the upstream fails,
+    /// before trying it again.
+    #[arg(long, value_name = "DURATION", default_value = "30s", value_parser = chaos::duration)]
+    cooldown: Duration,
+    /// Answer calls with an error if the upstream doesn't respond within this long,
+    /// e.g `30s` or `debug_*=120s`.
+    ///
+    /// `GLOB=` limits the rule to matching methods, and the first matching rule applies.
+    /// Batches wait for the longest timeout of their calls,
+    /// and streamed responses only need to start within it.
+    #[arg(long, value_name = "[GLOB=]DURATION")]
+    upstream_timeout: Vec<PerMethod<Timeout>>,
+    /// Add this header to requests to the upstreams, e.g `x-api-key: 1234`.
+    ///
+    /// It replaces any header of the same name from the client.
+    /// It isn't sent to the shadow, and isn't recorded.
+    #[arg(long, value_name = "NAME: VALUE", value_parser = header)]
+    upstream_header: Vec<(HeaderName, HeaderValue)>,
+    /// Authenticate to the upstreams with this bearer token,
+    /// like `--upstream-header 'authorization: Bearer TOKEN'`.
+    #[arg(
+        long,
+        value_name = "TOKEN",
+        env = "JSONRPCLI_UPSTREAM_BEARER",
+        hide_env_values = true
+    )]
+    upstream_bearer: Option<String>,
+    /// Also send each call to this upstream, in the background,
+    /// and log any differences between its responses and the upstream's.
+    #[arg(long, value_name = "URI", value_parser = unix::uri)]
+    shadow: Option<Uri>,
+    /// Don't compare the value at this JSON Pointer into the responses, e.g `/result/timestamp`.
+    ///
+    /// A `*` segment matches any member or element.
+    #[arg(long, value_name = "POINTER", requires = "shadow", value_parser = pointer)]
+    shadow_ignore: Vec<String>,
+    /// Don't record responses bigger than this.
+    ///
+    /// Responses are streamed to the client as they arrive,
+    /// unless they need to be changed, like for `--rewrite` or `--coalesce`,
+    /// and this bounds how much of them is kept for recording.
+    #[arg(long, value_name = "BYTES", default_value_t = 64 << 20)]
+    max_record_bytes: usize,
+    /// Answer requests with bodies bigger than this with a `413 Payload Too Large`,
+    /// instead of reading them.
+    #[arg(long, value_name = "BYTES")]
+    max_body_size: Option<usize>,
+    /// Also record calls which returned an error,
+    /// with the error object in the `x-jsonrpcli-error` field of the pairing.
+    #[arg(long)]
+    record_errors: bool,
+    #[arg(long, value_enum, default_value_t = Emit::Pairings)]
+    emit: Emit,
+    /// Write recordings to files in this directory, instead of stdout.
+    #[arg(long)]
+    record_dir: Option<PathBuf>,
+    /// Record calls to this SQLite database, instead of stdout.
+    ///
+    /// Each call is a row in the `calls` table, with its method, id, params, result or error,
+    /// the (redacted) bodies of the call and its response, start time, duration, HTTP status
+    /// and upstream. Values are JSON, and rows are indexed by method and a SHA-256 hash of the params.
+    /// Notifications from the upstream are rows too, with no response.
+    ///
+    /// This replaces `--emit`.
+    #[arg(
+        long,
+        value_name = "PATH",
+        conflicts_with_all = ["record_dir", "record_missing", "emit"]
+    )]
+    record_db: Option<PathBuf>,
+    /// Start a new file once the current one has this many (uncompressed) bytes.
+    #[arg(long, requires = "record_dir")]
+    rotate_bytes: Option<u64>,
+    /// Start a new file once the current one is this many seconds old.
+    #[arg(long, requires = "record_dir")]
+    rotate_secs: Option<u64>,
+    /// Compress files with gzip.
+    #[arg(long, requires = "record_dir")]
+    gzip: bool,
+    /// Only record the first of identical calls.
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "params",
+        value_name = "BY"
+    )]
+    dedup: Option<Dedup>,
+    /// Replace the value at this JSON Pointer with a placeholder in recordings.
+    ///
+    /// Pointers are into `{"params": ..., "result": ..., "error": ...}`,
+    /// and a `*` segment matches any member or element, e.g `/params/*/privateKey`.
+    #[arg(long, value_name = "POINTER", value_parser = pointer)]
+    redact: Vec<String>,
+    /// Only record calls to methods matching this glob, e.g `eth_get*`.
+    ///
+    /// Other calls are still proxied.
+    #[arg(long, value_name = "GLOB")]
+    record_only: Vec<MethodGlob>,
+    /// Don't record calls to methods matching this glob, e.g `eth_blockNumber`.
+    ///
+    /// Takes priority over `--record-only`.
+    #[arg(long, value_name = "GLOB")]
+    record_except: Vec<MethodGlob>,
+    /// Answer calls to methods matching this glob with an error, instead of forwarding them,
+    /// e.g `admin_*`.
+    #[arg(long, value_name = "GLOB")]
+    deny_method: Vec<MethodGlob>,
+    /// Delay forwarding calls by this long, e.g `100ms`, `50ms..2s` or `eth_call=1s`.
+    ///
+    /// Ranges are sampled uniformly.
+    /// `GLOB=` limits the rule to matching methods, and the first matching rule applies.
+    #[arg(long, value_name = "[GLOB=]LATENCY")]
+    inject_latency: Vec<PerMethod<Latency>>,
+    /// Answer this fraction of calls with an error, instead of forwarding them,
+    /// e.g `0.1` or `eth_*=0.5`.
+    #[arg(long, value_name = "[GLOB=]P")]
+    inject_error_rate: Vec<PerMethod<Probability>>,
+    /// The code of injected errors, e.g `-32005` or `eth_call=3`.
+    ///
+    /// Defaults to -32000.
+    #[arg(long, value_name = "[GLOB=]CODE", allow_negative_numbers = true)]
+    inject_code: Vec<PerMethod<i64>>,
+    /// Answer calls from this file of pairings, instead of forwarding them.
+    ///
+    /// Calls match on method and params.
+    /// The file is gzipped if it ends in `.gz`.
+    #[arg(long, value_name = "PATH")]
+    replay: Option<PathBuf>,
+    /// Forward calls which aren't in the `--replay` file, and append them to it.
+    ///
+    /// Gzipped files are appended to in gzip members, which are read back as one stream.
+    #[arg(long, requires = "replay", conflicts_with_all = ["record_dir", "emit"])]
+    record_missing: bool,
+    /// Check the params and result of each call against the methods in this OpenRPC document,
+    /// logging any violations.
+    ///
+    /// Schemas are checked as JSON Schema draft 7, including `format`s.
+    #[arg(long, value_name = "PATH")]
+    openrpc: Option<PathBuf>,
+    /// Answer calls with params which don't match `--openrpc` with an error, instead of forwarding them.
+    ///
+    /// Results which don't match are only logged, since the call has already been made.
+    #[arg(long, requires = "openrpc")]
+    openrpc_reject: bool,
+    /// Forward only one of identical calls which are in flight at the same time,
+    /// and give its response to all of them.
+    ///
+    /// Calls are identical if they have the same method and params.
+    /// Batches aren't coalesced.
+    #[arg(long)]
+    coalesce: bool,
+    /// Rewrite calls with the rules in this file.
+    ///
+    /// The file is a JSON list of rules like
+    /// `{"method": "eth_*", "rename": "eth_other", "params": [...], "result": [...]}`,
+    /// where `params` and `result` are JSON Patch operations.
+    /// Only `method` is required, and the first matching rule applies.
+    #[arg(long, value_name = "PATH")]
+    rewrite: Option<PathBuf>,
+    /// Load upstreams, rewrite rules and settings from this JSON file, and reload it on `SIGHUP`.
+    ///
+    /// The file is an object like `{"remote": ["http://node"], "route": ["debug_*=http://tracing"]}`,
+    /// with members named like flags, which take their place:
+    /// `remote`, `route`, `balance`, `rewrite`,
+    /// `redact`, `record_only`, `record_except` and `deny_method`.
+    #[arg(long, value_name = "PATH")]
+    config: Option<PathBuf>,
+    /// Serve an API for changing settings while running on this address, see below.
+    ///
+    /// `GET /` shows the settings, and `POST /` with a JSON object like
+    /// `{"recording": false, "chaos": false, "deny_method": ["debug_*"]}` changes them.
+    /// `redact`, `record_only` and `record_except` can also be changed.
+    /// `POST /drain` shuts down gracefully.
+    ///
+    /// There's no authentication, so this should be a loopback address.
+    #[arg(long, value_name = "ADDR")]
+    admin: Option<SocketAddr>,
+    /// Serve a web page showing recent calls, and stats for each method, on this address.
+    ///
+    /// It's read-only, but shows the method and id of every call.
+    #[arg(long, value_name = "ADDR")]
+    dashboard: Option<SocketAddr>,
+    /// Allow browsers on this origin to call the proxy, e.g `http://localhost:3000`,
+    /// or `*` for any origin.
+    #[arg(long, value_name = "ORIGIN")]
+    cors_origin: Vec<String>,
+    /// The methods browsers may use, for `--cors-origin`.
+    #[arg(
+        long,
+        value_name = "METHOD",
+        value_delimiter = ',',
+        default_value = "GET,POST,OPTIONS"
+    )]
+    cors_method: Vec<http::Method>,
+    /// The request headers browsers may send, for `--cors-origin`.
+    #[arg(
+        long,
+        value_name = "NAME",
+        value_delimiter = ',',
+        default_value = "content-type,authorization"
+    )]
+    cors_header: Vec<HeaderName>,
+    /// How long browsers may cache the response to a preflight request, for `--cors-origin`.
+    #[arg(long, value_name = "SECS", default_value_t = 600)]
+    cors_max_age: u64,
+    /// Only accept connections from clients in this block, like `192.168.1.0/24`.
+    ///
+    /// May be given more than once.
+    /// Clients on a Unix socket are always accepted.
+    #[arg(long, value_name = "CIDR")]
+    allow_cidr: Vec<Cidr>,
+    /// Refuse connections from clients in this block, even if they're in an `--allow-cidr`.
+    #[arg(long, value_name = "CIDR")]
+    deny_cidr: Vec<Cidr>,
+    /// How to write logs to stderr.
+    ///
+    /// Each proxied call is logged with its method, id, client, upstream, HTTP status,
+    /// duration and error code.
+    /// Filter logs with `RUST_LOG`, like `RUST_LOG=proxy=debug`.
+    #[arg(long, value_enum, default_value_t = log::Format::Text)]
+    log_format: log::Format,
+    /// Serve HTTPS with this PEM-encoded certificate chain.
+    #[arg(long, requires = "tls_key", value_name = "PATH")]
+    tls_cert: Option<PathBuf>,
+    /// The PEM-encoded private key for `--tls-cert`.
+    #[arg(long, requires = "tls_cert", value_name = "PATH")]
+    tls_key: Option<PathBuf>,
+}
+
+/// A connection from a client.
+trait Io: AsyncRead + AsyncWrite + Send + Unpin {}
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Io for T {}
+
+fn header(s: &str) -> Result<(HeaderName, HeaderValue), String> {
+    let (name, value) = s
+        .split_once(':')
+        .ok_or_else(|| format!("expected `NAME: VALUE`, not `{}`", s))?;
+    let name = HeaderName::try_from(name.trim()).map_err(|e| e.to_string())?;
+    let mut value = HeaderValue::try_from(value.trim()).map_err(|e| e.to_string())?;
+    value.set_sensitive(true);
+    Ok((name, value))
+}
+
+fn pointer(s: &str) -> Result<String, String> {
+    match s.starts_with('/') {
+        true => Ok(String::from(s)),
+        false => Err(String::from("JSON Pointers must start with `/`")),
+    }
+}
+
+/// The body of a response to a client.
+type Body = UnsyncBoxBody<Bytes, hyper::Error>;
+
+fn full(body: Bytes) -> Body {
+    Full::new(body)
+        .map_err(|never| match never {})
+        .boxed_unsync()
+}
+
+async fn proxy(
+    request: http::Request<Incoming>,
+    client: &'static Client<Connector, Body>,
+    config: &'static Config,
+    peer: Peer,
+) -> anyhow::Result<http::Response<Body>> {
+    if ws::is_upgrade(request.headers()) {
+        let response = ws::upgrade(request, client, config).await?;
+        return Ok(response.map(|it| it.map_err(|never| match never {}).boxed_unsync()));
+    }
+    if let Some(response) = config
+        .cors
+        .as_ref()
+        .and_then(|it| it.preflight(request.method(), request.headers()))
+    {
+        return Ok(response.map(|()| full(Bytes::new())));
+    }
+    let origin = request.headers().get(ORIGIN).cloned();
+    let start = Instant::now();
+    let (req_parts, req_body) = request.into_parts();
+    let exchanged = match passthrough(config) {
+        true => forward_passthrough(client, config, req_parts, req_body)
+            .await
+            .map(|(parts, tee, sent)| (parts, Reply::Streaming(tee), vec![], sent)),
+        false => {
+            let Some(req_body) =
+                collect(req_body, &req_parts.headers, config.max_body_size).await?
+            else {
+                let limit = config.max_body_size.unwrap_or_default();
+                tracing::warn!(client = %peer, "request body is bigger than {} bytes", limit);
+                let (mut parts, body) = too_large(limit).into_parts();
+                if let Some(cors) = &config.cors {
+                    cors.respond(origin.as_ref(), &mut parts.headers)
+                }
+                return Ok(http::Response::from_parts(parts, full(Bytes::from(body))));
+            };
+            exchange(client, config, req_parts, req_body.clone())
+                .await
+                .map(|(parts, reply, answered)| {
+                    (parts, reply, answered, Arc::new(Mutex::new(Some(req_body))))
+                })
+        }
+    };
+    let (mut resp_parts, reply, answered, sent) = match exchanged {
+        Ok(it) => it,
+        Err(e) => {
+            tracing::warn!(client = %peer, error = format!("{:#}", e), "couldn't proxy request");
+            return Err(e);
+        }
+    };
+    if let Some(cors) = &config.cors {
+        cors.respond(origin.as_ref(), &mut resp_parts.headers)
+    }
+    let status = resp_parts.status;
+    let upstream = resp_parts.extensions.get::<log::Upstream>().cloned();
+    let access = move |resp_body: &[u8]| {
+        let req_body = sent.lock().unwrap().clone().unwrap_or_default();
+        let calls = log::calls(
+            peer,
+            &req_body,
+            status,
+            resp_body,
+            upstream.as_ref(),
+            &answered,
+            start.elapsed(),
+        );
+        calls.iter().for_each(log::access);
+        if let Some(traffic) = &config.traffic {
+            traffic.push(calls)
+        }
+    };
+    let body = match reply {
+        Reply::Buffered(it) => {
+            access(&it);
+            full(it)
+        }
+        Reply::Streaming(it) => it
+            .on_end(move |it| access(it.map(|it| &**it).unwrap_or_default()))
+            .boxed_unsync(),
+    };
+    Ok(http::Response::from_parts(resp_parts, body))
+}
+
+/// Whether requests can be streamed to the upstream as they arrive, without looking at them first,
+/// because nothing would answer, change, route or time out their calls.
+fn passthrough(config: &Config) -> bool {
+    let settings = config.settings.read().unwrap();
+    settings.deny_method.is_empty()
+        && !(settings.chaos && (config.chaos.injects_errors() || config.chaos.injects_latency()))
+        && config.replay.is_none()
+        && config.contract.is_none()
+        && config.rewrite.read().unwrap().is_none()
+        && config.coalesce.is_none()
+        && config.failover.is_none()
+        && config.deadlines.0.is_empty()
+        && config.max_body_size.is_none()
+        && config.routes.read().unwrap().is_empty()
+}
+
+/// Collect a request body, or [`None`] if it's bigger than `limit`.
+///
+/// Bodies which declare a bigger `Content-Length` aren't read at all.
+async fn collect(
+    body: Incoming,
+    headers: &HeaderMap,
+    limit: Option<usize>,
+) -> anyhow::Result<Option<Bytes>> {
+    let Some(limit) = limit else {
+        return Ok(Some(body.collect().await?.to_bytes()));
+    };
+    let declared = headers
+        .get(CONTENT_LENGTH)
+        .and_then(|it| it.to_str().ok()?.parse::<u64>().ok());
+    if declared.is_some_and(|it| it > limit as u64) {
+        return Ok(None);
+    }
+    match http_body_util::Limited::new(body, limit).collect().await {
+        Ok(it) => Ok(Some(it.to_bytes())),
+        Err(e) if e.is::<http_body_util::LengthLimitError>() => Ok(None),
+        Err(e) => Err(anyhow::anyhow!(e)),
+    }
+}
+
+/// A `413 Payload Too Large`, with an error for the client.
+fn too_large(limit: usize) -> http::Response<Vec<u8>> {
+    let mut response = jsonrpcli::http::respond(Some(jsonrpcli::MaybeBatchedResponse::Single(
+        jsonrpcli::Response {
+            jsonrpc: jsonrpcli::V2,
+            result: Err(jsonrpcli::Error::parse_error(
+                format_args!("the request body is bigger than {} bytes", limit),
+                None,
+            )),
+            id: Id::Null,
+        },
+    )));
+    *response.status_mut() = StatusCode::PAYLOAD_TOO_LARGE;
+    response
+}
+
+enum Reply {
+    Buffered(Bytes),
+    /// Straight from the upstream.
+    Streaming(Tee),
+}
+
+/// Answer, forward and rewrite the calls in `req_body`, returning the response,
+/// and the ids of the calls which were answered locally.
+///
+/// The response is streamed if it doesn't need to be changed.
+async fn exchange(
+    client: &'static Client<Connector, Body>,
+    config: &'static Config,
+    req_parts: http::request::Parts,
+    req_body: Bytes,
+) -> anyhow::Result<(http::response::Parts, Reply, Vec<Id>)> {
+    let filtered = filter(config, req_body);
+    let answered = filtered
+        .answered
+        .iter()
+        .map(|it| it.id.clone())
+        .collect::<Vec<_>>();
+    let Some(req_body) = filtered.forward.clone() else {
+        let (parts, body) = jsonrpcli::http::respond(filtered.response()).into_parts();
+        return Ok((parts, Reply::Buffered(Bytes::from(body)), answered));
+    };
+
+    let rewrite = config.rewrite.read().unwrap().clone();
+    let (req_body, applied) = match &rewrite {
+        Some(rules) => rules.request(req_body),
+        None => (req_body, Applied::default()),
+    };
+
+    let coalesced = config.coalesce.as_ref().zip(coalesce::key(&req_body));
+    let unchanged = applied.is_empty() && coalesced.is_none() && config.failover.is_none();
+    let streamable = filtered.answered.is_empty()
+        && unchanged
+        && config.routes.read().unwrap().pool(&req_body).is_some();
+    if streamable {
+        let (parts, reply) = forward_streaming(client, config, req_parts, req_body).await?;
+        return Ok((parts, reply, answered));
+    }
+
+    let forward = forward(client, config, req_parts, req_body.clone());
+    let (mut resp_parts, mut resp_body) = match coalesced {
+        Some((coalescer, (key, id))) => {
+            let (mut parts, body) = coalescer.run(key, forward).await?;
+            parts.headers.remove(CONTENT_LENGTH);
+            let body = messages(&parts.headers, body, |it| coalesce::with_id(it, &id));
+            (parts, body)
+        }
+        None => forward.await?,
+    };
+    if let Some(rules) = &rewrite {
+        resp_body = messages(&resp_parts.headers, resp_body, |it| {
+            rules.response(it, &applied)
+        });
+        resp_parts.headers.remove(CONTENT_LENGTH);
+    }
+
+    if !filtered.answered.is_empty() {
+        resp_body = messages(&resp_parts.headers, resp_body, |it| {
+            policy::merge(it, &filtered.answered)
+        });
+        resp_parts.headers.remove(CONTENT_LENGTH);
+    }
+
+    Ok((resp_parts, Reply::Buffered(resp_body), answered))
+}
+
+/// Change a response `body` with `f`, or each message in it if it's streamed.
+fn messages(headers: &HeaderMap, body: Bytes, f: impl Fn(Bytes) -> Bytes) -> Bytes {
+    match sse::Framing::of(headers) {
+        Some(framing) => Bytes::from(sse::map(framing, &body, |it| {
+            f(Bytes::copy_from_slice(it)).to_vec()
+        })),
+        None => f(body),
+    }
+}
+
+/// Remove the calls in `body` which are answered locally, see [`answer`].
+fn filter(config: &Config, body: Bytes) -> Filtered {
+    let unfiltered = {
+        let settings = config.settings.read().unwrap();
+        settings.deny_method.is_empty()
+            && !(settings.chaos && config.chaos.injects_errors())
+            && config.replay.is_none()
+            && config.contract.is_none()
+    };
+    match unfiltered {
+        true => Filtered::unchanged(body),
+        false => policy::filter(body, |request| answer(config, request)),
+    }
+}
+
+/// What's needed to record an exchange with the upstream, once it's complete.
+struct Exchange {
+    body: Bytes,
+    /// The request as the client sent it, for [`Config::shadow`].
+    mirrored: Option<http::request::Parts>,
+    /// The request as the client sent it, for [`Emit::Har`].
+    archived: Option<http::request::Parts>,
+    started: SystemTime,
+    start: Instant,
+}
+
+/// Prepare to send `body` upstream, after any injected latency.
+async fn prepare(
+    config: &Config,
+    mut parts: http::request::Parts,
+    body: Bytes,
+) -> (http::request::Parts, Exchange) {
+    // Clients may have negotiated HTTP/2, but the upstream connection is HTTP/1.
+    parts.version = Version::HTTP_11;
+    // The body may have changed.
+    parts.headers.remove(CONTENT_LENGTH);
+
+    let mirrored = config.shadow.is_some().then(|| parts.clone());
+    let archived = (config.emit == Emit::Har).then(|| parts.clone());
+    // After cloning, so that credentials aren't mirrored or archived.
+    parts.headers.extend(config.upstream_headers.clone());
+    let delay = match config.settings.read().unwrap().chaos {
+        true => config.chaos.delay(&body),
+        false => Duration::ZERO,
+    };
+    tokio::time::sleep(delay).await;
+    let exchange = Exchange {
+        body,
+        mirrored,
+        archived,
+        started: SystemTime::now(),
+        start: Instant::now(),
+    };
+    (parts, exchange)
+}
+
+impl Exchange {
+    /// Record the exchange, and mirror it to the shadow.
+    fn finish(
+        self,
+        client: &'static Client<Connector, Body>,
+        config: &'static Config,
+        parts: &http::response::Parts,
+        resp_body: &Bytes,
+    ) {
+        let Self {
+            body,
+            mirrored,
+            archived,
+            started,
+            start,
+        } = self;
+        // Clients get the body as it was sent, but recordings need it decompressed.
+        let decoded = match encoding::decode(&parts.headers, resp_body) {
+            Ok(it) => it,
+            Err(e) => {
+                tracing::warn!("not recording a response which couldn't be decoded: {}", e);
+                return;
+            }
+        };
+        let metadata = Metadata {
+            started,
+            duration: Some(start.elapsed()),
+            status: parts.status,
+            upstream: parts
+                .extensions
+                .get::<log::Upstream>()
+                .map(|it| it.0.clone()),
+        };
+        match sse::Framing::of(&parts.headers) {
+            Some(framing) => {
+                // Timed from when the request was sent, rather than now.
+                let pending = record::calls(&body)
+                    .map(|(id, it)| {
+                        (
+                            id,
+                            Pending {
+                                started,
+                                start,
+                                ..it
+                            },
+                        )
+                    })
+                    .collect();
+                let pending = Mutex::new(pending);
+                for it in sse::Decoder::new(framing).push(&decoded) {
+                    record_message(
+                        config,
+                        &pending,
+                        None,
+                        &it,
+                        parts.status,
+                        parts.extensions.get::<log::Upstream>(),
+                    )
+                }
+            }
+            None => record(config, &body, &decoded, &metadata),
+        }
+        if let Some(request) = archived.filter(|_| any_included(config, &body)) {
+            let redactions = &config.settings.read().unwrap().redactions;
+            config.har.lock().unwrap().push(
+                started,
+                start.elapsed(),
+                metadata.upstream.unwrap_or_default(),
+                (&request, redactions.body(&body)),
+                (parts, redactions.body(&decoded)),
+            )
+        }
+        if let Some((shadow, mirrored)) = config.shadow.as_ref().zip(mirrored) {
+            tokio::spawn(async move {
+                match send(client, mirrored, &body, &shadow.uri, None).await {
+                    Ok((parts, it)) => match encoding::decode(&parts.headers, &it) {
+                        Ok(it) => shadow.compare(&body, &decoded, &it),
+                        Err(e) => tracing::warn!("couldn't decode the shadow's response: {}", e),
+                    },
+                    Err(e) => tracing::warn!("shadow error: {}", e),
+                }
+            });
+        }
+    }
+}
+
+/// Send `body` upstream, and record the exchange.
+async fn forward(
+    client: &'static Client<Connector, Body>,
+    config: &'static Config,
+    parts: http::request::Parts,
+    body: Bytes,
+) -> anyhow::Result<(http::response::Parts, Bytes)> {
+    let (parts, exchange) = prepare(config, parts, body).await;
+    let body = &exchange.body;
+    let timeout = config.deadlines.timeout(body);
+    let outcome = match &config.failover {
+        None => send_primary(client, config, parts, body, timeout).await,
+        Some(failover) => 'primary: {
+            if !failover.cooling_down() {
+                let within = timeout.map_or(failover.timeout, |it| it.min(failover.timeout));
+                let outcome = send_primary(client, config, parts.clone(), body, Some(within)).await;
+                match failover.trigger(&outcome) {
+                    None => break 'primary outcome,
+                    Some(reason) => failover.trip(&reason),
+                }
+            }
+            send(client, parts, body, &failover.fallback, timeout).await
+        }
+    };
+    let (parts, resp_body) = match outcome {
+        Err(Failure::Timeout(it)) => {
+            let (parts, body) = deadline::timed_out(exchange.body, it).into_parts();
+            return Ok((parts, Bytes::from(body)));
+        }
+        outcome => outcome?,
+    };
+    exchange.finish(client, config, &parts, &resp_body);
+    Ok((parts, resp_body))
+}
+
+/// A copy of a request body, once it has been sent upstream,
+/// or [`None`] if it wasn't kept.
+type Sent = Arc<Mutex<Option<Bytes>>>;
+
+/// Like [`forward`], but stream the response back as it arrives,
+/// recording it once it's complete.
+///
+/// `body` must not be split across routes.
+async fn forward_streaming(
+    client: &'static Client<Connector, Body>,
+    config: &'static Config,
+    parts: http::request::Parts,
+    body: Bytes,
+) -> anyhow::Result<(http::response::Parts, Reply)> {
+    let (parts, exchange) = prepare(config, parts, body).await;
+    let upstream = config
+        .routes
+        .read()
+        .unwrap()
+        .pool(&exchange.body)
+        .expect("streamed bodies aren't split")
+        .pick();
+    let request = request(client, parts, full(exchange.body.clone()), upstream.uri());
+    let outcome = match config.deadlines.timeout(&exchange.body) {
+        Some(it) => tokio::time::timeout(it, request)
+            .await
+            .unwrap_or(Err(Failure::Timeout(it))),
+        None => request.await,
+    };
+    match &outcome {
+        Err(Failure::Timeout(it)) => {
+            let (parts, body) = deadline::timed_out(exchange.body, *it).into_parts();
+            return Ok((parts, Reply::Buffered(Bytes::from(body))));
+        }
+        Err(Failure::Connect(_)) => upstream.eject(),
+        _ => {}
+    }
+    let (parts, incoming) = outcome?.into_parts();
+    let pending = Arc::new(Mutex::new(record::calls(&exchange.body).collect()));
+    let sent = Arc::new(Mutex::new(Some(exchange.body.clone())));
+    let tee = stream(
+        client, config, &parts, incoming, upstream, sent, pending, exchange,
+    );
+    Ok((parts, Reply::Streaming(tee)))
+}
+
+/// Stream the client's request to the upstream as it arrives, and the response back,
+/// recording the exchange once both are complete.
+///
+/// Calls aren't looked at before they're forwarded, see [`passthrough`].
+async fn forward_passthrough(
+    client: &'static Client<Connector, Body>,
+    config: &'static Config,
+    parts: http::request::Parts,
+    body: Incoming,
+) -> anyhow::Result<(http::response::Parts, Tee, Sent)> {
+    let length = parts.headers.get(CONTENT_LENGTH).cloned();
+    let (mut parts, exchange) = prepare(config, parts, Bytes::new()).await;
+    // The body is unchanged.
+    if let Some(it) = length {
+        parts.headers.insert(CONTENT_LENGTH, it);
+    }
+    let sent = Sent::default();
+    let pending = Arc::new(Mutex::new(HashMap::new()));
+    let body = Tee::new(body, config.max_record_bytes).on_end({
+        let (sent, pending) = (sent.clone(), pending.clone());
+        move |it| {
+            if let Some(it) = it {
+                pending.lock().unwrap().extend(record::calls(it));
+                *sent.lock().unwrap() = Some(it.clone())
+            }
+        }
+    });
+    let upstream = config.routes.read().unwrap().default().pick();
+    let outcome = request(client, parts, body.boxed_unsync(), upstream.uri()).await;
+    if let Err(Failure::Connect(_)) = &outcome {
+        upstream.eject()
+    }
+    let (parts, incoming) = outcome?.into_parts();
+    let tee = stream(
+        client,
+        config,
+        &parts,
+        incoming,
+        upstream,
+        sent.clone(),
+        pending,
+        exchange,
+    );
+    Ok((parts, tee, sent))
+}
+
+/// Stream the response from `upstream`, which is in flight until it has been streamed,
+/// recording the exchange with the request in `sent`.
+#[allow(clippy::too_many_arguments)]
+fn stream(
+    client: &'static Client<Connector, Body>,
+    config: &'static Config,
+    parts: &http::response::Parts,
+    incoming: Incoming,
+    upstream: Lease,
+    sent: Sent,
+    pending: Arc<Mutex<HashMap<Id, Pending>>>,
+    exchange: Exchange,
+) -> Tee {
+    if let Some(framing) = sse::Framing::of(&parts.headers) {
+        // Streams may never end, so messages are recorded as they arrive.
+        let (status, upstream_uri) = (
+            parts.status,
+            parts.extensions.get::<log::Upstream>().cloned(),
+        );
+        return Tee::new(incoming, 0)
+            .hold(upstream)
+            .messages(sse::Decoder::new(framing), move |it| {
+                record_message(config, &pending, None, &it, status, upstream_uri.as_ref())
+            });
+    }
+    Tee::new(incoming, config.max_record_bytes)
+        .hold(upstream)
+        .on_end({
+            let parts = parts.clone();
+            move |it| match (sent.lock().unwrap().clone(), it) {
+                (Some(body), Some(it)) => {
+                    Exchange { body, ..exchange }.finish(client, config, &parts, it)
+                }
+                (None, _) => tracing::warn!(
+                    "not recording a request which wasn't sent, or was bigger than {} bytes",
+                    config.max_record_bytes
+                ),
+                (_, None) => tracing::warn!(
+                    "not recording a response bigger than {} bytes",
+                    config.max_record_bytes
+                ),
+            }
+        })
+}
+
+/// [`send`] to an upstream from the pool for each route.
+///
+/// If a batch is split across routes, the response with the highest status is returned,
+/// with the joined bodies.
+async fn send_primary(
+    client: &Client<Connector, Body>,
+    config: &Config,
+    parts: http::request::Parts,
+    body: &Bytes,
+    timeout: Option<Duration>,
+) -> Result<(http::response::Parts, Bytes), Failure> {
+    let routes = config.routes.read().unwrap().clone();
+    let outcomes = futures::future::join_all(routes.split(body).into_iter().map(|(pool, body)| {
+        let parts = parts.clone();
+        async move {
+            let upstream = pool.pick();
+            let outcome = send(client, parts, &body, upstream.uri(), timeout).await;
+            if let Err(Failure::Connect(_)) = &outcome {
+                upstream.eject()
+            }
+            outcome
+        }
+    }))
+    .await;
+    let mut outcomes = outcomes.into_iter().collect::<Result<Vec<_>, _>>()?;
+    match outcomes.len() {
+        1 => Ok(outcomes.remove(0)),
+        _ => {
+            let ix = (0..outcomes.len())
+                .max_by_key(|ix| outcomes[*ix].0.status)
+                .expect("bodies are split into at least one part");
+            let mut parts = outcomes[ix].0.clone();
+            parts.headers.remove(CONTENT_LENGTH);
+            let upstreams = outcomes
+                .iter()
+                .filter_map(|(it, _)| it.extensions.get::<log::Upstream>())
+                .map(|it| &*it.0)
+                .collect::<Vec<_>>();
+            parts.extensions.insert(log::Upstream(upstreams.join(", ")));
+            Ok((parts, route::join(outcomes.into_iter().map(|(_, it)| it))))
+        }
+    }
+}
+
+/// Send `body` to `uri`, and collect the response.
+async fn send(
+    client: &Client<Connector, Body>,
+    parts: http::request::Parts,
+    body: &Bytes,
+    uri: &Uri,
+    timeout: Option<Duration>,
+) -> Result<(http::response::Parts, Bytes), Failure> {
+    let exchange = async {
+        let (parts, body) = request(client, parts, full(body.clone()), uri)
+            .await?
+            .into_parts();
+        let body = body
+            .collect()
+            .await
+            .map_err(|e| Failure::Other(e.into()))?
+            .to_bytes();
+        Ok((parts, body))
+    };
+    match timeout {
+        Some(it) => tokio::time::timeout(it, exchange)
+            .await
+            .unwrap_or(Err(Failure::Timeout(it))),
+        None => exchange.await,
+    }
+}
+
+/// Send `body` to `uri`, noting it in the response's extensions.
+async fn request(
+    client: &Client<Connector, Body>,
+    mut parts: http::request::Parts,
+    body: Body,
+    uri: &Uri,
+) -> Result<http::Response<Incoming>, Failure> {
+    parts.uri.clone_from(uri);
+    let mut response = client
+        .request(http::Request::from_parts(parts, body))
+        .await
+        .map_err(|e| match e.is_connect() {
+            true => Failure::Connect(e.into()),
+            false => Failure::Other(e.into()),
+        })?;
+    response
+        .extensions_mut()
+        .insert(log::Upstream(redact::uri(uri)));
+    Ok(response)
+}
+
+/// Answer `request` without forwarding it, if it's denied, an error is injected, or it's replayed.
+fn answer(
+    config: &Config,
+    request: &jsonrpcli::Request,
+) -> Option<Result<serde_json::Value, jsonrpcli::Error>> {
+    let method = &request.method;
+    let error = {
+        let settings = config.settings.read().unwrap();
+        policy::deny(method, &settings.deny_method)
+            .or_else(|| settings.chaos.then(|| config.chaos.error(method)).flatten())
+    };
+    if let Some(error) = error {
+        return Some(Err(error));
+    }
+    if let Some(contract) = &config.contract {
+        let violations = contract.params(request);
+        for it in &violations {
+            tracing::warn!(method, violation = %it, "call doesn't match the OpenRPC document")
+        }
+        if config.openrpc_reject && !violations.is_empty() {
+            let violations = violations
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            return Some(Err(jsonrpcli::Error::invalid_params(
+                violations.join(", "),
+                None,
+            )));
+        }
+    }
+    let replay = config.replay.as_ref()?;
+    match (replay.get(request), config.record_missing) {
+        (Some(it), _) => Some(it),
+        (None, true) => None,
+        (None, false) => Some(Err(jsonrpcli::Error::server_error(
+            format_args!("this call to `{}` wasn't recorded", method),
+            None,
+        ))),
+    }
+}
+
+/// Record each call in a (possibly batched) exchange.
+fn record(config: &Config, request: &[u8], response: &[u8], metadata: &Metadata) {
+    match request.trim_ascii_start().first() {
+        Some(b'[') => {
+            for (request, response) in record::pair_batch(request, response) {
+                record_call(
+                    config,
+                    request.get().as_bytes(),
+                    response.get().as_bytes(),
+                    metadata,
+                    None,
+                )
+            }
+        }
+        _ => record_call(config, request, response, metadata, None),
+    }
+}
+
+/// Record a call, with the notifications of the `subscription` it opened, if any.
+fn record_call(
+    config: &Config,
+    request: &[u8],
+    response: &[u8],
+    metadata: &Metadata,
+    subscription: Option<&Subscription>,
+) {
+    let bodies = (request, response);
+    let Some((mut request, mut result)) =
+        record::parse_call(request, response, config.record_errors)
+    else {
+        return;
+    };
+    if let (Some(contract), Ok(value)) = (&config.contract, &result) {
+        for it in contract.result(&request.method, value) {
+            tracing::warn!(method = request.method, violation = %it, "result doesn't match the OpenRPC document")
+        }
+    }
+    if let Some(replay) = &config.replay {
+        replay.insert(&request, result.clone())
+    }
+    if !included(config, &request.method) {
+        return;
+    }
+    config
+        .settings
+        .read()
+        .unwrap()
+        .redactions
+        .apply(&mut request, &mut result);
+    if let Some(dedup) = config.dedup {
+        let fingerprint = record::fingerprint(&request, &result, dedup);
+        if !config.seen.lock().unwrap().insert(fingerprint) {
+            return;
+        }
+    }
+    match (config.emit, &config.db) {
+        (Emit::Pairings, Some(db)) => {
+            let redactions = &config.settings.read().unwrap().redactions;
+            let bodies = (redactions.body(bodies.0), redactions.body(bodies.1));
+            db.insert(
+                &request,
+                Some(&result),
+                (&bodies.0, Some(&bodies.1)),
+                metadata,
+            );
+        }
+        (Emit::Pairings, None) => {
+            let mut pairing = record::pairing(request, result);
+            metadata.annotate(&mut pairing);
+            if let Some(it) = subscription {
+                it.annotate(&mut pairing)
+            }
+            if let Err(e) = config.sink.lock().unwrap().write_line(&pairing) {
+                tracing::warn!("couldn't write recording: {}", e)
+            }
+        }
+        (Emit::Openrpc, _) => {
+            let mut pairing = record::pairing(request.clone(), result.clone());
+            metadata.annotate(&mut pairing);
+            if let Some(it) = subscription {
+                it.annotate(&mut pairing)
+            }
+            config
+                .document
+                .lock()
+                .unwrap()
+                .observe(&request, &result, pairing)
+        }
+        // Exchanges are archived whole, by `forward`.
+        (Emit::Har, _) => {}
+    }
+}
+
+/// Record the responses and notifications in a message from `upstream`,
+/// pairing responses with their call in `pending`.
+///
+/// If `subscriptions` are tracked, subscribe calls and their notifications are
+/// held back, and recorded together when they're unsubscribed.
+fn record_message(
+    config: &Config,
+    pending: &Mutex<HashMap<Id, Pending>>,
+    subscriptions: Option<&Mutex<Subscriptions>>,
+    message: &[u8],
+    status: StatusCode,
+    upstream: Option<&log::Upstream>,
+) {
+    for member in Members::<Box<RawValue>>::new(message).flatten() {
+        match Envelope::of(&member) {
+            Some(Envelope {
+                id: Some(id),
+                method: None,
+            }) => {
+                let pending = pending.lock().unwrap().remove(&id);
+                if let Some(Pending {
+                    request,
+                    started,
+                    start,
+                }) = pending
+                {
+                    let metadata = Metadata {
+                        started,
+                        duration: Some(start.elapsed()),
+                        status,
+                        upstream: upstream.map(|it| it.0.clone()),
+                    };
+                    let (ended, opened) = match subscriptions {
+                        Some(it) => {
+                            let mut it = it.lock().unwrap();
+                            (
+                                it.close(&request, &member),
+                                it.open(&request, &member, &metadata),
+                            )
+                        }
+                        None => (None, false),
+                    };
+                    if let Some(it) = ended {
+                        record_subscription(config, &it)
+                    }
+                    if !opened {
+                        record_call(
+                            config,
+                            request.get().as_bytes(),
+                            member.get().as_bytes(),
+                            &metadata,
+                            None,
+                        )
+                    }
+                }
+            }
+            Some(Envelope {
+                id: None,
+                method: Some(_),
+            }) => {
+                if subscriptions.is_some_and(|it| it.lock().unwrap().notify(config, &member)) {
+                    continue;
+                }
+                let metadata = Metadata {
+                    started: SystemTime::now(),
+                    duration: None,
+                    status,
+                    upstream: upstream.map(|it| it.0.clone()),
+                };
+                record_notification(config, member.get().as_bytes(), &metadata)
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Record a subscribe call which has ended, with its notifications.
+fn record_subscription(config: &Config, subscription: &Subscription) {
+    record_call(
+        config,
+        subscription.request.get().as_bytes(),
+        subscription.response.get().as_bytes(),
+        &subscription.metadata,
+        Some(subscription),
+    )
+}
+
+/// Record a notification from the upstream, like a subscription update.
+///
+/// These only appear in [`Emit::Pairings`], and in the `--record-db` database.
+fn record_notification(config: &Config, body: &[u8], metadata: &Metadata) {
+    let Ok(mut notification) = serde_json::from_slice::<jsonrpcli::Request>(body) else {
+        return;
+    };
+    if !included(config, &notification.method) || config.emit != Emit::Pairings {
+        return;
+    }
+    config
+        .settings
+        .read()
+        .unwrap()
+        .redactions
+        .apply(&mut notification, &mut Ok(serde_json::Value::Null));
+    if let Some(db) = &config.db {
+        let body = config.settings.read().unwrap().redactions.body(body);
+        db.insert(&notification, None, (&body, None), metadata);
+        return;
+    }
+    let mut pairing = record::notification(notification);
+    metadata.annotate(&mut pairing);
+    if let Err(e) = config.sink.lock().unwrap().write_line(&pairing) {
+        tracing::warn!("couldn't write recording: {}", e)
+    }
+}
+
+/// Whether any of the calls in a (possibly batched) request should be recorded.
+fn any_included(config: &Config, request: &[u8]) -> bool {
+    #[derive(serde::Deserialize)]
+    struct Call {
+        method: String,
+    }
+    jsonrpcli::stream::Members::<Call>::new(request)
+        .flatten()
+        .any(|it| included(config, &it.method))
+}
+
+/// Whether calls to `method` should be recorded.
+fn included(config: &Config, method: &str) -> bool {
+    config.settings.read().unwrap().included(method)
+}
+
+/// Start using reloaded upstreams, rewrite rules and settings,
+/// checking the health of the upstreams every `interval`.
+#[cfg(unix)]
+fn install(
+    config: &Config,
+    client: &'static Client<Connector, Body>,
+    loaded: reload::Loaded,
+    interval: Duration,
+) {
+    let reload::Loaded {
+        routes,
+        rewrite,
+        settings,
+    } = loaded;
+    let routes = Arc::new(routes);
+    tokio::spawn(Routes::check(
+        Arc::downgrade(&routes),
+        client,
+        config.upstream_headers.clone(),
+        interval,
+    ));
+    *config.routes.write().unwrap() = routes;
+    *config.rewrite.write().unwrap() = rewrite.map(Arc::new);
+    if let Err(e) = config.settings.write().unwrap().apply(settings) {
+        tracing::warn!("couldn't apply settings: {}", e)
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    _main().await
+}
+
+async fn _main() -> anyhow::Result<()> {
+    let Args {
+        local,
+        remote,
+        remotes,
+        route,
+        balance,
+        health_interval,
+        fallback,
+        fallback_on,
+        fallback_timeout,
+        upstream_timeout,
+        cooldown,
+        upstream_header,
+        upstream_bearer,
+        shadow,
+        shadow_ignore,
+        max_record_bytes,
+        max_body_size,
+        record_errors,
+        emit,
+        record_dir,
+        record_db,
+        rotate_bytes,
+        rotate_secs,
+        gzip,
+        dedup,
+        redact,
+        record_only,
+        record_except,
+        deny_method,
+        inject_latency,
+        inject_error_rate,
+        inject_code,
+        replay,
+        record_missing,
+        openrpc,
+        openrpc_reject,
+        coalesce,
+        rewrite,
+        config: config_path,
+        admin,
+        dashboard,
+        cors_origin,
+        cors_method,
+        cors_header,
+        cors_max_age,
+        allow_cidr,
+        deny_cidr,
+        log_format,
+        tls_cert,
+        tls_key,
+    } = Args::parse();
+    log::init(log_format);
+    let tls = match (tls_cert, tls_key) {
+        (Some(cert), Some(key)) => Some(tls::acceptor(&cert, &key)?),
+        _ => None,
+    };
+    let sink = match (record_dir, &replay) {
+        (_, Some(path)) if record_missing => Sink::append(path)?,
+        (Some(dir), _) => Sink::Dir(Box::new(Rotating::new(
+            dir,
+            rotate_bytes,
+            rotate_secs.map(Duration::from_secs),
+            gzip,
+        )?)),
+        (None, _) => Sink::Stdout,
+    };
+    let db = record_db.as_deref().map(Db::open).transpose()?;
+    let replay = replay.as_deref().map(Replay::load).transpose()?;
+    let contract = openrpc
+        .map(|it| {
+            std::fs::read_to_string(&it)
+                .with_context(|| format!("couldn't read {}", it.display()))?
+                .parse::<Contract>()
+                .with_context(|| format!("invalid OpenRPC document {}", it.display()))
+        })
+        .transpose()?;
+    let client = &*Box::leak(Box::new(
+        Client::builder(hyper_util::rt::TokioExecutor::new()).build::<_, Body>(Connector::new()),
+    ));
+
+    let mut upstream_headers = upstream_header.into_iter().collect::<HeaderMap>();
+    if let Some(token) = upstream_bearer {
+        let mut value = HeaderValue::try_from(format!("Bearer {}", token))
+            .map_err(|_| anyhow::anyhow!("invalid bearer token"))?;
+        value.set_sensitive(true);
+        upstream_headers.insert(AUTHORIZATION, value);
+    }
+    let flags = Flags {
+        remotes: remote.into_iter().chain(remotes).collect(),
+        route,
+        balance,
+        rewrite,
+        redact,
+        record_only,
+        record_except,
+        deny_method,
+    };
+    let reload::Loaded {
+        routes,
+        rewrite,
+        settings: patch,
+    } = flags.load(config_path.as_deref())?;
+    let mut settings = Settings::default();
+    settings.apply(patch).map_err(anyhow::Error::msg)?;
+    let routes = Arc::new(routes);
+    let health_interval = Duration::from_secs(health_interval);
+    tokio::spawn(Routes::check(
+        Arc::downgrade(&routes),
+        client,
+        upstream_headers.clone(),
+        health_interval,
+    ));
+
+    let config = &*Box::leak(Box::new(Config {
+        routes: RwLock::new(routes),
+        record_errors,
+        emit,
+        document: Mutex::default(),
+        har: Mutex::default(),
+        sink: Mutex::new(sink),
+        db,
+        dedup,
+        seen: Mutex::default(),
+        settings: RwLock::new(settings),
+        chaos: Chaos {
+            latency: inject_latency,
+            error_rate: inject_error_rate,
+            code: inject_code,
+        },
+        replay,
+        record_missing,
+        contract,
+        openrpc_reject,
+        coalesce: coalesce.then(Coalescer::default),
+        rewrite: RwLock::new(rewrite.map(Arc::new)),
+        max_record_bytes,
+        max_body_size,
+        shadow: shadow.map(|uri| Shadow {
+            uri,
+            options: jsonrpcli::diff::Options {
+                ignore: shadow_ignore,
+                ..Default::default()
+            },
+        }),
+        failover: fallback.map(|it| Failover::new(it, fallback_on, fallback_timeout, cooldown)),
+        deadlines: Deadlines(upstream_timeout),
+        upstream_headers,
+        cors: (!cors_origin.is_empty()).then(|| Cors {
+            origins: cors_origin,
+            methods: cors_method,
+            headers: cors_header,
+            max_age: Duration::from_secs(cors_max_age),
+        }),
+        traffic: dashboard.map(|_| Traffic::default()),
+        connections: AtomicUsize::new(0),
+        drain: Notify::new(),
+    }));
+
+    #[cfg(unix)]
+    if let Some(path) = config_path {
+        tokio::spawn(reload::watch(path, flags, move |it| {
+            install(config, client, it, health_interval)
+        })?);
+    }
+
+    tokio::spawn(async move {
+        // Keep recordings if the process is killed, without flushing on every write.
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            interval.tick().await;
+            if let Err(e) = config.sink.lock().unwrap().tick() {
+                tracing::warn!("couldn't flush recordings: {}", e)
+            }
+        }
+    });
+
+    if let Some(addr) = admin {
+        tokio::spawn(admin::serve(TcpListener::bind(addr).await?, config));
+    }
+    if let Some(addr) = dashboard {
+        tokio::spawn(dashboard::serve(TcpListener::bind(addr).await?, config));
+    }
+
+    let access = Access {
+        allow: allow_cidr,
+        deny: deny_cidr,
+    };
+    let listener = Listener::bind(&local).await?;
+
+    let server = hyper_util::server::conn::auto::Builder::new(hyper_util::rt::TokioExecutor::new());
+    let graceful = hyper_util::server::graceful::GracefulShutdown::new();
+    let mut ctrl_c = pin!(tokio::signal::ctrl_c());
+
+    loop {
+        tokio::select! {
+            conn = listener.accept() => {
+                let (stream, peer) = match conn {
+                    Ok(conn) => conn,
+                    Err(e) => {
+                        tracing::warn!("accept error: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                if !access.accepts(peer) {
+                    tracing::warn!("refused connection from {}", peer);
+                    continue;
+                }
+                tracing::info!("incomming connection accepted: {}", peer);
+
+                let tls = tls.clone();
+                let server = server.clone();
+                let watcher = graceful.watcher();
+                config.connections.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move {
+                    let stream: Box<dyn Io> = match tls {
+                        Some(acceptor) => match acceptor.accept(stream).await {
+                            Ok(it) => Box::new(it),
+                            Err(e) => {
+                                tracing::warn!("tls error: {}", e);
+                                config.connections.fetch_sub(1, Ordering::Relaxed);
+                                return;
+                            }
+                        },
+                        None => Box::new(stream),
+                    };
+                    let stream = hyper_util::rt::TokioIo::new(Box::pin(stream));
+                    let conn = server.serve_connection_with_upgrades(stream, hyper::service::service_fn(move |it|proxy(it, client, config, peer)));
+                    if let Err(err) = watcher.watch(conn.into_owned()).await {
+                        tracing::warn!("connection error: {}", err);
+                    }
+                    config.connections.fetch_sub(1, Ordering::Relaxed);
+                    tracing::info!("connection dropped: {}", peer);
+                });
+            },
+
+            _ = config.drain.notified() => {
+                drop(listener);
+                tracing::info!("draining connections, starting shutdown");
+                break;
+            }
+
+            _ = ctrl_c.as_mut() => {
+                drop(listener);
+                tracing::info!("Ctrl-C received, starting shutdown");
+                    break;
+            }
+        }
+    }
+
+    tokio::select! {
+        _ = graceful.shutdown() => {
+            tracing::info!("Gracefully shutdown!");
+        },
+        _ = tokio::time::sleep(Duration::from_secs(10)) => {
+            tracing::warn!("Waited 10 seconds for graceful shutdown, aborting...");
+        }
+    }
+
+    if let Emit::Openrpc = config.emit {
+        let document = config.document.lock().unwrap().to_openrpc(format!(
+            "Recorded from {}",
+            redact::uri(config.routes.read().unwrap().default().primary())
+        ));
+        config
+            .sink
+            .lock()
+            .unwrap()
+            .write_document("openrpc", &document)?;
+    }
+    if let Emit::Har = config.emit {
+        let har = config.har.lock().unwrap();
+        config
+            .sink
+            .lock()
+            .unwrap()
+            .write_document("har", &har.to_har())?;
+    }
+    if let Some(db) = &config.db {
+        db.finish();
+    }
+    config.sink.lock().unwrap().finish()?;
+
+    Ok(())
+}
@@ -0,0 +1,289 @@
+//! Accumulate recorded calls into an OpenRPC document,
+//! inferring schemas from the values that were observed.
+
+use std::collections::BTreeMap;
+
+use jsonrpcli::{Request, RequestParameters};
+use openrpc_types::{
+    ContentDescriptor, ExamplePairing, Info, Method, OpenRPC, ParamStructure, ReferenceOr,
+    SpecificationExtensions, OPEN_RPC_SPECIFICATION_VERSION,
+};
+use serde_json::{json, Map, Value};
+
+/// The union of every value observed in a position.
+#[derive(Debug, Default)]
+struct Shape {
+    null: bool,
+    boolean: bool,
+    integer: bool,
+    number: bool,
+    string: bool,
+    /// The shape of all the elements.
+    array: Option<Box<Shape>>,
+    object: Option<ObjectShape>,
+}
+
+#[derive(Debug, Default)]
+struct ObjectShape {
+    observed: usize,
+    /// How many times each member was observed.
+    properties: BTreeMap<String, (usize, Shape)>,
+}
+
+impl Shape {
+    fn observe(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.null = true,
+            Value::Bool(_) => self.boolean = true,
+            Value::Number(it) if it.is_f64() => self.number = true,
+            Value::Number(_) => self.integer = true,
+            Value::String(_) => self.string = true,
+            Value::Array(it) => {
+                let items = self.array.get_or_insert_with(Box::default);
+                for it in it {
+                    items.observe(it)
+                }
+            }
+            Value::Object(it) => {
+                let object = self.object.get_or_insert_with(ObjectShape::default);
+                object.observed += 1;
+                for (k, v) in it {
+                    let (count, shape) = object.properties.entry(k.clone()).or_default();
+                    *count += 1;
+                    shape.observe(v)
+                }
+            }
+        }
+    }
+    /// A JSON Schema which accepts everything that was observed.
+    ///
+    /// If nothing was observed, anything is accepted.
+    fn to_schema(&self) -> Value {
+        let Self {
+            null,
+            boolean,
+            integer,
+            number,
+            string,
+            array,
+            object,
+        } = self;
+        let mut alternatives = [
+            (*null, "null"),
+            (*boolean, "boolean"),
+            // Integers are numbers too.
+            (*integer && !*number, "integer"),
+            (*number, "number"),
+            (*string, "string"),
+        ]
+        .into_iter()
+        .filter(|(observed, _)| *observed)
+        .map(|(_, ty)| json!({ "type": ty }))
+        .collect::<Vec<_>>();
+        if let Some(items) = array {
+            alternatives.push(match items.is_empty() {
+                true => json!({ "type": "array" }),
+                false => json!({ "type": "array", "items": items.to_schema() }),
+            })
+        }
+        if let Some(ObjectShape {
+            observed,
+            properties,
+        }) = object
+        {
+            let required = properties
+                .iter()
+                .filter(|(_, (count, _))| count == observed)
+                .map(|(k, _)| k.clone())
+                .collect::<Vec<_>>();
+            alternatives.push(json!({
+                "type": "object",
+                "properties": properties
+                    .iter()
+                    .map(|(k, (_, shape))| (k.clone(), shape.to_schema()))
+                    .collect::<Map<_, _>>(),
+                "required": required,
+            }))
+        }
+        match alternatives.len() {
+            0 => Value::Bool(true),
+            1 => alternatives.remove(0),
+            _ if alternatives
+                .iter()
+                .all(|it| it.as_object().is_some_and(|it| it.len() == 1)) =>
+            {
+                json!({ "type": alternatives.iter().map(|it| it["type"].clone()).collect::<Vec<_>>() })
+            }
+            _ => json!({ "anyOf": alternatives }),
+        }
+    }
+    fn is_empty(&self) -> bool {
+        let Self {
+            null,
+            boolean,
+            integer,
+            number,
+            string,
+            array,
+            object,
+        } = self;
+        !(*null || *boolean || *integer || *number || *string)
+            && array.is_none()
+            && object.is_none()
+    }
+}
+
+#[derive(Debug, Default)]
+struct MethodShape {
+    calls: usize,
+    by_name: usize,
+    by_position: usize,
+    /// In order of first appearance, with how many calls included each one.
+    params: Vec<(String, usize, Shape)>,
+    result: Option<Shape>,
+    errors: BTreeMap<i64, String>,
+    examples: Vec<ExamplePairing>,
+}
+
+/// Every call recorded so far, by method.
+#[derive(Debug, Default)]
+pub struct Document {
+    methods: BTreeMap<String, MethodShape>,
+}
+
+impl Document {
+    pub fn observe(
+        &mut self,
+        request: &Request,
+        result: &Result<Value, jsonrpcli::Error>,
+        pairing: ExamplePairing,
+    ) {
+        let method = self.methods.entry(request.method.clone()).or_default();
+        method.calls += 1;
+        let params = match &request.params {
+            Some(RequestParameters::ByName(it)) => {
+                method.by_name += 1;
+                it.iter().map(|(k, v)| (k.clone(), v)).collect()
+            }
+            Some(RequestParameters::ByPosition(it)) => {
+                method.by_position += 1;
+                it.iter()
+                    .enumerate()
+                    .map(|(ix, v)| (format!("param{}", ix), v))
+                    .collect()
+            }
+            None => vec![],
+        };
+        for (name, value) in params {
+            let ix = match method.params.iter().position(|(it, ..)| *it == name) {
+                Some(it) => it,
+                None => {
+                    method.params.push((name, 0, Shape::default()));
+                    method.params.len() - 1
+                }
+            };
+            let (_, count, shape) = &mut method.params[ix];
+            *count += 1;
+            shape.observe(value)
+        }
+        match result {
+            Ok(it) => method.result.get_or_insert_with(Shape::default).observe(it),
+            Err(e) => {
+                method
+                    .errors
+                    .entry(e.code)
+                    .or_insert_with(|| e.message.clone());
+            }
+        }
+        method.examples.push(pairing)
+    }
+    /// An OpenRPC document describing every method, titled `title`.
+    pub fn to_openrpc(&self, title: String) -> OpenRPC {
+        OpenRPC {
+            openrpc: OPEN_RPC_SPECIFICATION_VERSION,
+            info: Info {
+                title,
+                description: Some(String::from("Inferred from recorded traffic.")),
+                terms_of_service: None,
+                contact: None,
+                license: None,
+                version: String::from("0.0.0"),
+                extensions: SpecificationExtensions::default(),
+            },
+            servers: None,
+            methods: self
+                .methods
+                .iter()
+                .map(|(name, shape)| ReferenceOr::Item(method(name, shape)))
+                .collect(),
+            components: None,
+            external_docs: None,
+            extensions: SpecificationExtensions::default(),
+        }
+    }
+}
+
+fn method(name: &str, shape: &MethodShape) -> Method {
+    let MethodShape {
+        calls,
+        by_name,
+        by_position,
+        params,
+        result,
+        errors,
+        examples,
+    } = shape;
+    Method {
+        name: name.into(),
+        tags: None,
+        summary: None,
+        description: None,
+        external_docs: None,
+        params: params
+            .iter()
+            .map(|(name, count, shape)| {
+                ReferenceOr::Item(descriptor(name.clone(), count == calls, shape))
+            })
+            .collect(),
+        result: result
+            .as_ref()
+            .map(|it| ReferenceOr::Item(descriptor(String::from("result"), true, it))),
+        deprecated: None,
+        servers: None,
+        errors: match errors.is_empty() {
+            true => None,
+            false => Some(
+                errors
+                    .iter()
+                    .map(|(code, message)| {
+                        ReferenceOr::Item(openrpc_types::Error {
+                            code: *code,
+                            message: message.clone(),
+                            data: None,
+                        })
+                    })
+                    .collect(),
+            ),
+        },
+        param_structure: match (by_name, by_position) {
+            (0, 0) => None,
+            (_, 0) => Some(ParamStructure::ByName),
+            (0, _) => Some(ParamStructure::ByPosition),
+            _ => Some(ParamStructure::Either),
+        },
+        examples: Some(examples.iter().cloned().map(ReferenceOr::Item).collect()),
+        extensions: SpecificationExtensions::default(),
+    }
+}
+
+fn descriptor(name: String, required: bool, shape: &Shape) -> ContentDescriptor {
+    ContentDescriptor {
+        name,
+        summary: None,
+        description: None,
+        required: Some(required),
+        schema: serde_json::from_value(shape.to_schema()).expect("inferred schemas are valid"),
+        deprecated: None,
+        extensions: SpecificationExtensions::default(),
+    }
+}
@@ -0,0 +1,118 @@
+//! Deciding which calls are forwarded upstream.
+
+use hyper::body::Bytes;
+use jsonrpcli::{method::MethodGlob, Error, MaybeBatchedResponse, Request, Response, V2};
+use serde::Deserialize as _;
+use serde_json::{value::RawValue, Value};
+
+/// A request body, with calls which were answered locally removed.
+pub struct Filtered {
+    /// What to send upstream, if anything.
+    pub forward: Option<Bytes>,
+    /// Responses to the calls which were removed.
+    pub answered: Vec<Response>,
+    pub batch: bool,
+}
+
+impl Filtered {
+    /// Forward all of `body`.
+    pub fn unchanged(body: Bytes) -> Self {
+        Self {
+            forward: Some(body),
+            answered: vec![],
+            batch: false,
+        }
+    }
+    /// The responses to the calls which were answered, in a batch if the request was one.
+    ///
+    /// This is the whole response if nothing was forwarded.
+    pub fn response(self) -> Option<MaybeBatchedResponse> {
+        let Self {
+            answered, batch, ..
+        } = self;
+        match (batch, answered.len()) {
+            (_, 0) => None,
+            (false, _) => answered
+                .into_iter()
+                .next()
+                .map(MaybeBatchedResponse::Single),
+            (true, _) => Some(MaybeBatchedResponse::Batch(answered)),
+        }
+    }
+}
+
+/// Remove calls for which `answer` returns a result, responding to them with it.
+///
+/// Bodies which aren't requests are forwarded as-is, for the upstream to reject.
+pub fn filter(
+    body: Bytes,
+    mut answer: impl FnMut(&Request) -> Option<Result<Value, Error>>,
+) -> Filtered {
+    let Ok(value) = serde_json::from_slice::<Value>(&body) else {
+        return Filtered::unchanged(body);
+    };
+    let mut answer = |member: &Value| {
+        let request = Request::deserialize(member).ok()?;
+        let result = answer(&request)?;
+        Some(request.id.map(|id| Response {
+            jsonrpc: V2,
+            result,
+            id,
+        }))
+    };
+    match value {
+        Value::Array(members) => {
+            let (mut forward, mut answered) = (vec![], vec![]);
+            for member in members {
+                match answer(&member) {
+                    Some(response) => answered.extend(response),
+                    None => forward.push(member),
+                }
+            }
+            Filtered {
+                forward: match forward.is_empty() {
+                    true => None,
+                    false => Some(Bytes::from(
+                        serde_json::to_vec(&forward).expect("values always serialize"),
+                    )),
+                },
+                answered,
+                batch: true,
+            }
+        }
+        single => match answer(&single) {
+            Some(response) => Filtered {
+                forward: None,
+                answered: response.into_iter().collect(),
+                batch: false,
+            },
+            None => Filtered::unchanged(body),
+        },
+    }
+}
+
+/// The error for calls to `method`, if they are denied by any of `deny`.
+pub fn deny(method: &str, deny: &[MethodGlob]) -> Option<Error> {
+    deny.iter()
+        .any(|it| it.matches(method))
+        .then(|| Error::method_not_found(format_args!("method `{}` is not allowed", method), None))
+}
+
+/// Add `answered` to the upstream's response to the rest of the batch.
+///
+/// If the upstream didn't respond with a batch, its response is returned unchanged.
+pub fn merge(upstream: Bytes, answered: &[Response]) -> Bytes {
+    let mut members = match upstream.iter().all(u8::is_ascii_whitespace) {
+        true => vec![],
+        false => match serde_json::from_slice::<Vec<Box<RawValue>>>(&upstream) {
+            Ok(it) => it,
+            Err(_) => return upstream,
+        },
+    };
+    members.extend(
+        answered
+            .iter()
+            .map(|it| serde_json::value::to_raw_value(it).expect("responses always serialize")),
+    );
+    Bytes::from(serde_json::to_vec(&members).expect("raw values always serialize"))
+}
@@ -0,0 +1,325 @@
+//! Turning observed calls into [`ExamplePairing`]s.
+
+use std::{
+    collections::HashMap,
+    hash::{DefaultHasher, Hash as _, Hasher as _},
+    time::{Duration, Instant, SystemTime},
+};
+
+use clap::ValueEnum;
+use http::StatusCode;
+#[cfg(not(feature = "simd-json"))]
+use jsonrpcli::lazy::{LazyRequest, LazyResponse};
+use jsonrpcli::{stream::Members, Id, Request, RequestParameters};
+use openrpc_types::{Example, ExamplePairing, ExampleValue, ReferenceOr, SpecificationExtensions};
+use serde::{de::IgnoredAny, Deserialize};
+use serde_json::{value::RawValue, Value};
+
+/// The extension field which holds the error object of a failed call.
+///
+/// The pairing has no `result`.
+pub const ERROR_EXTENSION: &str = "x-jsonrpcli-error";
+
+/// The extension field which marks a notification sent by the upstream,
+/// like a subscription update.
+///
+/// The pairing has no `result`.
+pub const NOTIFICATION_EXTENSION: &str = "x-jsonrpcli-notification";
+
+/// The extension fields describing how the upstream handled a call, see [`Metadata`].
+pub const STARTED_EXTENSION: &str = "x-jsonrpcli-started";
+pub const DURATION_EXTENSION: &str = "x-jsonrpcli-duration-ms";
+pub const STATUS_EXTENSION: &str = "x-jsonrpcli-status";
+pub const UPSTREAM_EXTENSION: &str = "x-jsonrpcli-upstream";
+
+/// How the upstream handled a call.
+#[derive(Clone)]
+pub struct Metadata {
+    /// When the call was sent.
+    pub started: SystemTime,
+    /// How long the upstream took to respond, which notifications don't have.
+    pub duration: Option<Duration>,
+    pub status: StatusCode,
+    /// Redacted.
+    pub upstream: Option<String>,
+}
+
+impl Metadata {
+    /// Add the extension fields to `pairing`.
+    pub fn annotate(&self, pairing: &mut ExamplePairing) {
+        let Self {
+            started,
+            duration,
+            status,
+            upstream,
+        } = self;
+        let fields = &mut pairing.extensions.0;
+        fields.insert(
+            String::from(STARTED_EXTENSION),
+            Value::String(crate::har::timestamp(*started)),
+        );
+        if let Some(it) = duration {
+            fields.insert(
+                String::from(DURATION_EXTENSION),
+                Value::from(it.as_secs_f64() * 1000.0),
+            );
+        }
+        fields.insert(String::from(STATUS_EXTENSION), Value::from(status.as_u16()));
+        if let Some(it) = upstream {
+            fields.insert(String::from(UPSTREAM_EXTENSION), Value::String(it.clone()));
+        }
+    }
+}
+
+/// A call which hasn't been responded to yet, on a connection which carries several.
+pub struct Pending {
+    pub request: Box<RawValue>,
+    pub started: SystemTime,
+    pub start: Instant,
+}
+
+/// The parts of a message needed to correlate calls.
+#[derive(Deserialize)]
+pub struct Envelope {
+    #[serde(default)]
+    pub id: Option<Id>,
+    #[serde(default)]
+    pub method: Option<IgnoredAny>,
+}
+
+impl Envelope {
+    pub fn of(member: &RawValue) -> Option<Self> {
+        serde_json::from_str(member.get()).ok()
+    }
+}
+
+/// The calls in a (possibly batched) request, by id, for correlating responses.
+///
+/// They're timed from now.
+pub fn calls(request: &[u8]) -> impl Iterator<Item = (Id, Pending)> + '_ {
+    let (started, start) = (SystemTime::now(), Instant::now());
+    Members::<Box<RawValue>>::new(request)
+        .flatten()
+        .filter_map(move |member| match Envelope::of(&member)? {
+            Envelope {
+                id: Some(id),
+                method: Some(_),
+            } => Some((
+                id,
+                Pending {
+                    request: member,
+                    started,
+                    start,
+                },
+            )),
+            _ => None,
+        })
+}
+
+/// Match the members of a batched request with the members of its response, by id.
+///
+/// Notifications, and requests which weren't responded to, are skipped.
+pub fn pair_batch(request: &[u8], response: &[u8]) -> Vec<(Box<RawValue>, Box<RawValue>)> {
+    let mut responses = Members::<Box<RawValue>>::new(response)
+        .flatten()
+        .filter_map(|it| Some((Envelope::of(&it)?.id?, it)))
+        .collect::<HashMap<_, _>>();
+    Members::<Box<RawValue>>::new(request)
+        .flatten()
+        .filter_map(|it| {
+            let response = responses.remove(&Envelope::of(&it)?.id?)?;
+            Some((it, response))
+        })
+        .collect()
+}
+
+/// Parse the bodies of a call.
+///
+/// Errors are only returned if `errors` is set.
+/// Payloads are only parsed once the envelopes are known to be suitable.
+#[cfg(not(feature = "simd-json"))]
+pub fn parse_call(
+    request: &[u8],
+    response: &[u8],
+    errors: bool,
+) -> Option<(Request, Result<Value, jsonrpcli::Error>)> {
+    let request = serde_json::from_slice::<LazyRequest>(request).ok()?;
+    let result = match serde_json::from_slice::<LazyResponse>(response)
+        .ok()?
+        .result
+    {
+        Ok(it) => Ok(serde_json::from_str(it.get()).ok()?),
+        Err(e) if errors => Err(e),
+        Err(_) => return None,
+    };
+    Some((request.materialize().ok()?, result))
+}
+
+/// Parse the bodies of a call.
+///
+/// Errors are only returned if `errors` is set.
+/// `simd-json` can't parse lazily, but is fast enough to parse everything.
+#[cfg(feature = "simd-json")]
+pub fn parse_call(
+    request: &[u8],
+    response: &[u8],
+    errors: bool,
+) -> Option<(Request, Result<Value, jsonrpcli::Error>)> {
+    let request = jsonrpcli::parse::from_slice::<Request>(request).ok()?;
+    match jsonrpcli::parse::from_slice::<jsonrpcli::Response>(response)
+        .ok()?
+        .result
+    {
+        Err(_) if !errors => None,
+        result => Some((request, result)),
+    }
+}
+
+/// Record a call as an [`ExamplePairing`], named for its method.
+///
+/// Failed calls have the error object in [`ERROR_EXTENSION`].
+pub fn pairing(request: Request, result: Result<Value, jsonrpcli::Error>) -> ExamplePairing {
+    let Request { method, params, .. } = request;
+    let mut extensions = SpecificationExtensions::default();
+    let result = match result {
+        Ok(it) => Some(example(None, it)),
+        Err(e) => {
+            extensions.0.insert(
+                String::from(ERROR_EXTENSION),
+                serde_json::to_value(e).expect("errors always serialize"),
+            );
+            None
+        }
+    };
+    ExamplePairing {
+        name: method,
+        description: None,
+        summary: None,
+        params: match params {
+            Some(RequestParameters::ByPosition(it)) => {
+                it.into_iter().map(|it| example(None, it)).collect()
+            }
+            Some(RequestParameters::ByName(it)) => it
+                .into_iter()
+                .map(|(name, value)| example(Some(name), value))
+                .collect(),
+            None => vec![],
+        },
+        result,
+        extensions,
+    }
+}
+
+/// Record a notification from the upstream, marked with [`NOTIFICATION_EXTENSION`].
+pub fn notification(request: Request) -> ExamplePairing {
+    let mut pairing = pairing(request, Ok(Value::Null));
+    pairing.result = None;
+    pairing
+        .extensions
+        .0
+        .insert(String::from(NOTIFICATION_EXTENSION), Value::Bool(true));
+    pairing
+}
+
+/// The inverse of [`pairing`].
+///
+/// Returns [`None`] for notifications, and for pairings with neither a result nor an error.
+/// Omitted params come back as [`None`].
+pub fn unpair(pairing: ExamplePairing) -> Option<(Request, Result<Value, jsonrpcli::Error>)> {
+    let ExamplePairing {
+        name,
+        params,
+        result,
+        mut extensions,
+        ..
+    } = pairing;
+    if extensions.0.contains_key(NOTIFICATION_EXTENSION) {
+        return None;
+    }
+    let result = match (result, extensions.0.remove(ERROR_EXTENSION)) {
+        (Some(it), _) => Ok(unexample(it)?.1),
+        (None, Some(it)) => Err(serde_json::from_value(it).ok()?),
+        (None, None) => return None,
+    };
+    let params = params
+        .into_iter()
+        .map(unexample)
+        .collect::<Option<Vec<_>>>()?;
+    let params = match (
+        params.is_empty(),
+        params.iter().all(|(name, _)| name.is_some()),
+    ) {
+        (true, _) => None,
+        (false, true) => Some(RequestParameters::ByName(
+            params
+                .into_iter()
+                .map(|(name, value)| (name.unwrap_or_default(), value))
+                .collect(),
+        )),
+        (false, false) => Some(RequestParameters::ByPosition(
+            params.into_iter().map(|(_, value)| value).collect(),
+        )),
+    };
+    Some((
+        Request {
+            jsonrpc: jsonrpcli::V2,
+            method: name,
+            params,
+            id: None,
+        },
+        result,
+    ))
+}
+
+fn unexample(example: ReferenceOr<Example>) -> Option<(Option<String>, Value)> {
+    match example {
+        ReferenceOr::Item(Example {
+            name,
+            value: ExampleValue::Embedded(value),
+            ..
+        }) => Some((name, value)),
+        _ => None,
+    }
+}
+
+fn example(name: Option<String>, value: Value) -> ReferenceOr<Example> {
+    ReferenceOr::Item(Example {
+        name,
+        summary: None,
+        description: None,
+        value: ExampleValue::Embedded(value),
+        extensions: SpecificationExtensions::default(),
+    })
+}
+
+/// What makes two calls the same, for deduplication.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Dedup {
+    /// The same method and params.
+    Params,
+    /// The same method, params and result (or error).
+    Result,
+}
+
+/// Identify a call, according to `dedup`.
+///
+/// Values are compared canonically, so e.g the order of members doesn't matter.
+pub fn fingerprint(
+    request: &Request,
+    result: &Result<Value, jsonrpcli::Error>,
+    dedup: Dedup,
+) -> u64 {
+    let result = match dedup {
+        Dedup::Params => None,
+        Dedup::Result => Some(
+            result
+                .as_ref()
+                .map_err(|it| (it.code, &it.message, &it.data)),
+        ),
+    };
+    let key = jsonrpcli::canonical::to_vec(&(&request.method, &request.params, result))
+        .expect("calls always serialize");
+    let mut hasher = DefaultHasher::new();
+    key.hash(&mut hasher);
+    hasher.finish()
+}
@@ -0,0 +1,152 @@
+//! Loading upstreams, rewrite rules and settings from a config file,
+//! and reloading it on `SIGHUP`.
+//!
+//! The file is a JSON object with any of `remote` (a list of URIs), `route`, `balance`, `rewrite`,
+//! and the members of a [`Patch`], like `{"remote": ["http://node"], "deny_method": ["admin_*"]}`.
+//! These take the place of the flags of the same name, which apply when they're omitted.
+//! `recording` and `chaos` are unchanged when omitted.
+//!
+//! Established connections are kept through a reload.
+
+#[cfg(unix)]
+use std::future::Future;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context as _};
+use clap::ValueEnum as _;
+use http::Uri;
+use jsonrpcli::method::MethodGlob;
+use serde::Deserialize;
+
+use crate::{
+    rewrite::Rules,
+    route::{Route, Routes},
+    settings::Patch,
+    unix,
+    upstream::{Balance, Pool},
+};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct File {
+    remote: Option<Vec<String>>,
+    route: Option<Vec<String>>,
+    balance: Option<String>,
+    rewrite: Option<PathBuf>,
+    recording: Option<bool>,
+    chaos: Option<bool>,
+    redact: Option<Vec<String>>,
+    record_only: Option<Vec<String>>,
+    record_except: Option<Vec<String>>,
+    deny_method: Option<Vec<String>>,
+}
+
+/// What the flags say, for what the file doesn't.
+pub struct Flags {
+    pub remotes: Vec<Uri>,
+    pub route: Vec<Route>,
+    pub balance: Balance,
+    pub rewrite: Option<PathBuf>,
+    pub redact: Vec<String>,
+    pub record_only: Vec<MethodGlob>,
+    pub record_except: Vec<MethodGlob>,
+    pub deny_method: Vec<MethodGlob>,
+}
+
+pub struct Loaded {
+    pub routes: Routes,
+    pub rewrite: Option<Rules>,
+    pub settings: Patch,
+}
+
+impl Flags {
+    /// Load the flags, overridden by the file at `path`, if there is one.
+    pub fn load(&self, path: Option<&Path>) -> anyhow::Result<Loaded> {
+        let File {
+            remote,
+            route,
+            balance,
+            rewrite,
+            recording,
+            chaos,
+            redact,
+            record_only,
+            record_except,
+            deny_method,
+        } = match path {
+            Some(path) => {
+                let text = fs::read_to_string(path)
+                    .with_context(|| format!("couldn't read {}", path.display()))?;
+                serde_json::from_str(&text)
+                    .with_context(|| format!("invalid config in {}", path.display()))?
+            }
+            None => File::default(),
+        };
+        let remotes = match remote {
+            Some(it) => it
+                .iter()
+                .map(|it| unix::uri(it).map_err(anyhow::Error::msg))
+                .collect::<Result<_, _>>()?,
+            None => self.remotes.clone(),
+        };
+        if remotes.is_empty() {
+            bail!("no remote, give one on the command line or in the config file")
+        }
+        let route = match route {
+            Some(it) => it
+                .iter()
+                .map(|it| it.parse().map_err(anyhow::Error::msg))
+                .collect::<Result<_, _>>()?,
+            None => self.route.clone(),
+        };
+        let balance = match balance {
+            Some(it) => Balance::from_str(&it, false).map_err(anyhow::Error::msg)?,
+            None => self.balance,
+        };
+        let globs = |it: &[MethodGlob]| it.iter().map(|it| it.as_str().to_owned()).collect();
+        let settings = Patch {
+            recording,
+            chaos,
+            redact: Some(redact.unwrap_or_else(|| self.redact.clone())),
+            record_only: Some(record_only.unwrap_or_else(|| globs(&self.record_only))),
+            record_except: Some(record_except.unwrap_or_else(|| globs(&self.record_except))),
+            deny_method: Some(deny_method.unwrap_or_else(|| globs(&self.deny_method))),
+        };
+        settings.validate().map_err(anyhow::Error::msg)?;
+        Ok(Loaded {
+            routes: Routes::new(route, Pool::new(remotes, balance), balance),
+            rewrite: rewrite
+                .as_deref()
+                .or(self.rewrite.as_deref())
+                .map(Rules::load)
+                .transpose()?,
+            settings,
+        })
+    }
+}
+
+/// Reload the file at `path` on each `SIGHUP`, forever.
+///
+/// If the file is invalid, the previous config is kept.
+#[cfg(unix)]
+pub fn watch(
+    path: PathBuf,
+    flags: Flags,
+    mut install: impl FnMut(Loaded),
+) -> io::Result<impl Future<Output = ()>> {
+    let mut hangup = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup())?;
+    Ok(async move {
+        while hangup.recv().await.is_some() {
+            match flags.load(Some(&path)) {
+                Ok(it) => {
+                    install(it);
+                    tracing::info!("reloaded {}", path.display())
+                }
+                Err(e) => tracing::warn!("couldn't reload {}: {:#}", path.display(), e),
+            }
+        }
+    })
+}
@@ -0,0 +1,74 @@
+//! Answering calls from a recording, instead of the upstream.
+
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+    sync::Mutex,
+};
+
+use anyhow::Context as _;
+use flate2::read::MultiGzDecoder;
+use jsonrpcli::{Error, Request};
+use openrpc_types::ExamplePairing;
+use serde_json::Value;
+
+use crate::record::{self, Dedup};
+
+/// Recorded results, by method and params.
+pub struct Replay {
+    /// By [`key`].
+    calls: Mutex<HashMap<u64, Result<Value, Error>>>,
+}
+
+impl Replay {
+    /// Load the pairings in the file at `path`, which is gzipped if it ends in `.gz`.
+    ///
+    /// If a call was recorded more than once, the first recording is used.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let file = File::open(path).with_context(|| format!("couldn't open {}", path.display()))?;
+        let reader: Box<dyn BufRead> = match path.extension().is_some_and(|it| it == "gz") {
+            true => Box::new(BufReader::new(MultiGzDecoder::new(file))),
+            false => Box::new(BufReader::new(file)),
+        };
+        let mut calls = HashMap::new();
+        for (ix, line) in reader.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let pairing = serde_json::from_str::<ExamplePairing>(&line).with_context(|| {
+                format!("invalid pairing on line {} of {}", ix + 1, path.display())
+            })?;
+            if let Some((request, result)) = record::unpair(pairing) {
+                calls.entry(key(&request)).or_insert(result);
+            }
+        }
+        Ok(Self {
+            calls: Mutex::new(calls),
+        })
+    }
+    /// What was recorded for a call like `request`.
+    pub fn get(&self, request: &Request) -> Option<Result<Value, Error>> {
+        self.calls.lock().unwrap().get(&key(request)).cloned()
+    }
+    /// Add a call which wasn't in the recording.
+    pub fn insert(&self, request: &Request, result: Result<Value, Error>) {
+        self.calls
+            .lock()
+            .unwrap()
+            .entry(key(request))
+            .or_insert(result);
+    }
+}
+
+/// Pairings don't distinguish omitted and empty params, so neither does this.
+fn key(request: &Request) -> u64 {
+    let mut request = request.clone();
+    if request.params.as_ref().is_some_and(|it| it.is_empty()) {
+        request.params = None
+    }
+    request.id = None;
+    record::fingerprint(&request, &Ok(Value::Null), Dedup::Params)
+}
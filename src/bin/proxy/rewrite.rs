@@ -0,0 +1,265 @@
+//! Rewriting calls as they pass through, to adapt clients to a slightly different dialect.
+//!
+//! The rules file is a JSON list of rules like
+//! `{"method": "eth_*", "rename": "eth_other", "params": [...], "result": [...]}`,
+//! where `params` and `result` are JSON Patch (RFC 6902) operations, applied with [`json_patch`].
+//! Only `method` is required, and the first matching rule applies.
+//! Rules whose params can't be patched, like when a `test` fails, are skipped entirely.
+//!
+//! Omitted params are patched as an empty list.
+
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::Context as _;
+use hyper::body::Bytes;
+use json_patch::Patch;
+use jsonrpcli::{method::MethodGlob, Id};
+use serde::Deserialize;
+use serde_json::Value;
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Rule {
+    method: String,
+    #[serde(default)]
+    rename: Option<String>,
+    #[serde(default)]
+    params: Patch,
+    #[serde(default)]
+    result: Patch,
+}
+
+pub struct Rules(Vec<(MethodGlob, Rule)>);
+
+/// The rule applied to each call in a request, by id, for rewriting the results.
+#[derive(Default)]
+pub struct Applied(HashMap<Id, usize>);
+
+impl Applied {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+impl Rules {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("couldn't read {}", path.display()))?;
+        let rules = serde_json::from_str::<Vec<Rule>>(&text)
+            .with_context(|| format!("invalid rules in {}", path.display()))?;
+        rules
+            .into_iter()
+            .map(|it| {
+                let glob = MethodGlob::new(&it.method)
+                    .with_context(|| format!("invalid method glob `{}`", it.method))?;
+                Ok((glob, it))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+    /// Rename methods and patch params in a (possibly batched) request.
+    ///
+    /// Calls whose params can't be patched are forwarded unchanged.
+    pub fn request(&self, body: Bytes) -> (Bytes, Applied) {
+        let mut applied = Applied::default();
+        let Ok(mut value) = serde_json::from_slice::<Value>(&body) else {
+            return (body, applied);
+        };
+        let mut changed = false;
+        for member in members(&mut value) {
+            let Some(method) = member.get("method").and_then(Value::as_str) else {
+                continue;
+            };
+            let Some(ix) = self.0.iter().position(|(glob, _)| glob.matches(method)) else {
+                continue;
+            };
+            let (_, rule) = &self.0[ix];
+            if !rule.params.0.is_empty() {
+                let mut params = member
+                    .get("params")
+                    .cloned()
+                    .unwrap_or(Value::Array(vec![]));
+                if let Err(e) = json_patch::patch(&mut params, &rule.params) {
+                    tracing::warn!("not rewriting a call to `{}`: {}", method, e);
+                    continue;
+                }
+                member.insert(String::from("params"), params);
+            }
+            if let Some(rename) = &rule.rename {
+                member.insert(String::from("method"), Value::String(rename.clone()));
+            }
+            if let Some(id) = member
+                .get("id")
+                .and_then(|it| Id::deserialize(it).ok())
+                .filter(|_| !rule.result.0.is_empty())
+            {
+                applied.0.insert(id, ix);
+            }
+            changed = true;
+        }
+        match changed {
+            true => (
+                Bytes::from(serde_json::to_vec(&value).expect("values always serialize")),
+                applied,
+            ),
+            false => (body, applied),
+        }
+    }
+    /// Patch the results in a (possibly batched) response, according to `applied`.
+    pub fn response(&self, body: Bytes, applied: &Applied) -> Bytes {
+        if applied.0.is_empty() {
+            return body;
+        }
+        let Ok(mut value) = serde_json::from_slice::<Value>(&body) else {
+            return body;
+        };
+        for member in members(&mut value) {
+            let Some(ix) = member
+                .get("id")
+                .and_then(|it| Id::deserialize(it).ok())
+                .and_then(|it| applied.0.get(&it))
+            else {
+                continue;
+            };
+            let (glob, rule) = &self.0[*ix];
+            if let Some(result) = member.get_mut("result") {
+                if let Err(e) = json_patch::patch(result, &rule.result) {
+                    tracing::warn!("couldn't rewrite result of `{}`: {}", glob, e)
+                }
+            }
+        }
+        Bytes::from(serde_json::to_vec(&value).expect("values always serialize"))
+    }
+}
+
+/// The objects in a (possibly batched) message.
+fn members(value: &mut Value) -> Vec<&mut serde_json::Map<String, Value>> {
+    match value {
+        Value::Array(it) => it.iter_mut().filter_map(Value::as_object_mut).collect(),
+        Value::Object(it) => vec![it],
+        _ => vec![],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn rules(rules: Value) -> Rules {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), rules.to_string()).unwrap();
+        Rules::load(file.path()).unwrap()
+    }
+
+    fn body(value: Value) -> Bytes {
+        Bytes::from(value.to_string())
+    }
+
+    fn parse(body: &Bytes) -> Value {
+        serde_json::from_slice(body).unwrap()
+    }
+
+    #[test]
+    fn rewrite() {
+        let rules = rules(json!([
+            {
+                "method": "old_*",
+                "rename": "new",
+                "params": [{"op": "add", "path": "/-", "value": "latest"}],
+                "result": [{"op": "move", "from": "/a", "path": "/b"}]
+            },
+            {"method": "*", "params": [{"op": "remove", "path": "/drop"}]}
+        ]));
+        let (request, applied) = rules.request(body(json!([
+            {"jsonrpc": "2.0", "method": "old_one", "params": [1], "id": 1},
+            {"jsonrpc": "2.0", "method": "old_two", "id": 2},
+            {"jsonrpc": "2.0", "method": "other", "params": {"drop": 1, "keep": 2}, "id": 3}
+        ])));
+        assert_eq!(
+            parse(&request),
+            json!([
+                {"jsonrpc": "2.0", "method": "new", "params": [1, "latest"], "id": 1},
+                {"jsonrpc": "2.0", "method": "new", "params": ["latest"], "id": 2},
+                {"jsonrpc": "2.0", "method": "other", "params": {"keep": 2}, "id": 3}
+            ])
+        );
+        let response = rules.response(
+            body(json!([
+                {"jsonrpc": "2.0", "result": {"a": 1}, "id": 2},
+                {"jsonrpc": "2.0", "result": {"a": 3}, "id": 3},
+                {"jsonrpc": "2.0", "result": {"a": 1}, "id": 1}
+            ])),
+            &applied,
+        );
+        assert_eq!(
+            parse(&response),
+            json!([
+                {"jsonrpc": "2.0", "result": {"b": 1}, "id": 2},
+                {"jsonrpc": "2.0", "result": {"a": 3}, "id": 3},
+                {"jsonrpc": "2.0", "result": {"b": 1}, "id": 1}
+            ])
+        );
+    }
+
+    #[test]
+    fn failed_params_skip_the_rule() {
+        let rules = rules(json!([{
+            "method": "m",
+            "rename": "renamed",
+            "params": [
+                {"op": "add", "path": "/-", "value": 2},
+                {"op": "test", "path": "/0", "value": "expected"}
+            ],
+            "result": [{"op": "add", "path": "/extra", "value": true}]
+        }]));
+        let original = body(json!({"jsonrpc": "2.0", "method": "m", "params": [1], "id": 1}));
+        let (request, applied) = rules.request(original.clone());
+        assert_eq!(request, original);
+        assert!(applied.is_empty());
+    }
+
+    #[test]
+    fn failed_result_is_unchanged() {
+        let rules = rules(json!([{
+            "method": "m",
+            "result": [
+                {"op": "add", "path": "/extra", "value": true},
+                {"op": "remove", "path": "/missing"}
+            ]
+        }]));
+        let (_, applied) = rules.request(body(json!({"jsonrpc": "2.0", "method": "m", "id": 1})));
+        let response = rules.response(
+            body(json!({"jsonrpc": "2.0", "result": {"a": 1}, "id": 1})),
+            &applied,
+        );
+        assert_eq!(
+            parse(&response),
+            json!({"jsonrpc": "2.0", "result": {"a": 1}, "id": 1})
+        );
+    }
+
+    #[test]
+    fn unmatched() {
+        let rules = rules(json!([{"method": "m", "rename": "renamed"}]));
+        let original = body(json!({"jsonrpc": "2.0", "method": "other", "id": 1}));
+        let (request, applied) = rules.request(original.clone());
+        assert_eq!(request, original);
+        assert!(applied.is_empty());
+        let invalid = Bytes::from_static(b"not json");
+        assert_eq!(rules.request(invalid.clone()).0, invalid);
+    }
+
+    #[test]
+    fn invalid_glob() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        fs::write(file.path(), json!([{"method": "eth_["}]).to_string()).unwrap();
+        let error = Rules::load(file.path()).err().unwrap();
+        assert!(
+            error.to_string().starts_with("invalid method glob `eth_[`"),
+            "{}",
+            error
+        );
+    }
+}
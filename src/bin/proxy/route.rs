@@ -0,0 +1,187 @@
+//! Routing calls to different upstreams by method.
+//!
+//! Batches are split by route, and the responses are joined back together.
+
+use std::{str::FromStr, sync::Weak, time::Duration};
+
+use http::{HeaderMap, Uri};
+
+use hyper::body::Bytes;
+use hyper_util::client::legacy::Client;
+use jsonrpcli::{method::MethodGlob, stream::Members};
+use serde::Deserialize;
+use serde_json::value::RawValue;
+
+use crate::{
+    unix::{self, Connector},
+    upstream::{Balance, Pool},
+    Body,
+};
+
+/// `GLOB=URI`.
+#[derive(Debug, Clone)]
+pub struct Route {
+    glob: MethodGlob,
+    uri: Uri,
+}
+
+impl FromStr for Route {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (glob, uri) = s
+            .split_once('=')
+            .ok_or_else(|| format!("expected `GLOB=URI`, not `{}`", s))?;
+        Ok(Self {
+            glob: glob
+                .parse()
+                .map_err(|e| format!("invalid method glob `{}`: {}", glob, e))?,
+            uri: unix::uri(uri)?,
+        })
+    }
+}
+
+pub struct Routes {
+    routes: Vec<(MethodGlob, Pool)>,
+    /// For calls which don't match any route.
+    default: Pool,
+}
+
+impl Routes {
+    /// Routes with the same glob are balanced as a pool, ordered by their first appearance.
+    pub fn new(routes: Vec<Route>, default: Pool, balance: Balance) -> Self {
+        let mut grouped = Vec::<(MethodGlob, Vec<Uri>)>::new();
+        for Route { glob, uri } in routes {
+            match grouped.iter_mut().find(|(it, _)| *it == glob) {
+                Some((_, uris)) => uris.push(uri),
+                None => grouped.push((glob, vec![uri])),
+            }
+        }
+        Self {
+            routes: grouped
+                .into_iter()
+                .map(|(glob, uris)| (glob, Pool::new(uris, balance)))
+                .collect(),
+            default,
+        }
+    }
+    /// The pool for calls which don't match any route.
+    pub fn default(&self) -> &Pool {
+        &self.default
+    }
+    /// Whether there are no routes, so every call goes to the default pool.
+    pub fn is_empty(&self) -> bool {
+        self.routes.is_empty()
+    }
+    fn pools(&self) -> impl Iterator<Item = &Pool> {
+        [&self.default]
+            .into_iter()
+            .chain(self.routes.iter().map(|(_, it)| it))
+    }
+    fn route(&self, method: &str) -> Option<usize> {
+        self.routes
+            .iter()
+            .position(|(glob, _)| glob.matches(method))
+    }
+    /// Check the health of each pool of several upstreams every `interval`,
+    /// until `routes` are dropped.
+    ///
+    /// Probes carry `headers`, like calls, so upstreams which require credentials accept them.
+    pub async fn check(
+        routes: Weak<Self>,
+        client: &Client<Connector, Body>,
+        headers: HeaderMap,
+        interval: Duration,
+    ) {
+        loop {
+            tokio::time::sleep(interval).await;
+            let Some(routes) = routes.upgrade() else {
+                return;
+            };
+            for pool in routes.pools().filter(|it| it.len() > 1) {
+                pool.check(client, &headers, interval).await
+            }
+        }
+    }
+    /// The pool for all of `body`, if it isn't split.
+    pub fn pool(&self, body: &Bytes) -> Option<&Pool> {
+        match &*self.split(body) {
+            [(pool, _)] => Some(pool),
+            _ => None,
+        }
+    }
+    /// Split a (possibly batched) request into the parts for each pool.
+    ///
+    /// Bodies which aren't batches aren't split.
+    pub fn split(&self, body: &Bytes) -> Vec<(&Pool, Bytes)> {
+        #[derive(Deserialize)]
+        struct Call {
+            method: String,
+        }
+        let default = &self.default;
+        let pool = |ix: Option<usize>| match ix {
+            Some(ix) => &self.routes[ix].1,
+            None => default,
+        };
+        let method = |member: &RawValue| {
+            serde_json::from_str::<Call>(member.get())
+                .ok()
+                .and_then(|it| self.route(&it.method))
+        };
+        if self.routes.is_empty() {
+            return vec![(default, body.clone())];
+        }
+        let members = match body.trim_ascii_start().first() {
+            Some(b'[') => match serde_json::from_slice::<Vec<Box<RawValue>>>(body) {
+                Ok(it) => it,
+                Err(_) => return vec![(default, body.clone())],
+            },
+            _ => {
+                let ix = serde_json::from_slice::<&RawValue>(body)
+                    .ok()
+                    .and_then(method);
+                return vec![(pool(ix), body.clone())];
+            }
+        };
+        let mut groups = Vec::<(Option<usize>, Vec<Box<RawValue>>)>::new();
+        for member in members {
+            let ix = method(&member);
+            match groups.iter_mut().find(|(it, _)| *it == ix) {
+                Some((_, it)) => it.push(member),
+                None => groups.push((ix, vec![member])),
+            }
+        }
+        match &*groups {
+            [] => vec![(default, body.clone())],
+            [(ix, _)] => vec![(pool(*ix), body.clone())],
+            _ => groups
+                .into_iter()
+                .map(|(ix, members)| {
+                    (
+                        pool(ix),
+                        Bytes::from(
+                            serde_json::to_vec(&members).expect("raw values always serialize"),
+                        ),
+                    )
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Join the responses to the parts of a split batch.
+///
+/// Responses which aren't batches, like an error for a whole part, are added as members.
+pub fn join(bodies: impl IntoIterator<Item = Bytes>) -> Bytes {
+    let mut members = vec![];
+    for body in bodies {
+        match body.trim_ascii_start().first() {
+            Some(b'[') => members.extend(Members::<Box<RawValue>>::new(&body).flatten()),
+            Some(_) => members.extend(serde_json::from_slice::<Box<RawValue>>(&body)),
+            None => {}
+        }
+    }
+    match members.is_empty() {
+        true => Bytes::new(),
+        false => Bytes::from(serde_json::to_vec(&members).expect("raw values always serialize")),
+    }
+}
@@ -0,0 +1,135 @@
+//! Settings which can be changed while the proxy is running.
+
+use jsonrpcli::method::MethodGlob;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use crate::redact::Redactions;
+
+pub struct Settings {
+    /// Whether calls are recorded at all.
+    pub recording: bool,
+    /// Whether latency and errors are injected, see [`crate::chaos`].
+    pub chaos: bool,
+    /// The JSON Pointers for `redactions`.
+    redact: Vec<String>,
+    pub redactions: Redactions,
+    pub record_only: Vec<MethodGlob>,
+    pub record_except: Vec<MethodGlob>,
+    pub deny_method: Vec<MethodGlob>,
+}
+
+/// Changes to [`Settings`], where omitted settings are unchanged.
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Patch {
+    pub recording: Option<bool>,
+    pub chaos: Option<bool>,
+    pub redact: Option<Vec<String>>,
+    pub record_only: Option<Vec<String>>,
+    pub record_except: Option<Vec<String>>,
+    pub deny_method: Option<Vec<String>>,
+}
+
+/// Recording, with chaos, and without filters or redactions.
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            recording: true,
+            chaos: true,
+            redact: vec![],
+            redactions: Redactions::default(),
+            record_only: vec![],
+            record_except: vec![],
+            deny_method: vec![],
+        }
+    }
+}
+
+impl Patch {
+    pub fn validate(&self) -> Result<(), String> {
+        match self.redact.iter().flatten().find(|it| !it.starts_with('/')) {
+            Some(it) => Err(format!("`{}` isn't a JSON Pointer", it)),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Settings {
+    /// Apply all of `patch`, or none of it if any of it is invalid.
+    pub fn apply(&mut self, patch: Patch) -> Result<(), String> {
+        patch.validate()?;
+        let Patch {
+            recording,
+            chaos,
+            redact,
+            record_only,
+            record_except,
+            deny_method,
+        } = patch;
+        let globs = |it: Option<Vec<String>>| {
+            it.map(|it| {
+                it.iter()
+                    .map(|it| {
+                        MethodGlob::new(it)
+                            .map_err(|e| format!("invalid method glob `{}`: {}", it, e))
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()
+        };
+        let (record_only, record_except, deny_method) = (
+            globs(record_only)?,
+            globs(record_except)?,
+            globs(deny_method)?,
+        );
+        if let Some(it) = recording {
+            self.recording = it
+        }
+        if let Some(it) = chaos {
+            self.chaos = it
+        }
+        if let Some(it) = redact {
+            self.redactions = Redactions::new(&it);
+            self.redact = it
+        }
+        if let Some(it) = record_only {
+            self.record_only = it
+        }
+        if let Some(it) = record_except {
+            self.record_except = it
+        }
+        if let Some(it) = deny_method {
+            self.deny_method = it
+        }
+        Ok(())
+    }
+    /// Whether calls to `method` should be recorded.
+    pub fn included(&self, method: &str) -> bool {
+        let only =
+            self.record_only.is_empty() || self.record_only.iter().any(|it| it.matches(method));
+        self.recording && only && !self.record_except.iter().any(|it| it.matches(method))
+    }
+    pub fn to_json(&self) -> Value {
+        let Self {
+            recording,
+            chaos,
+            redact,
+            redactions: _,
+            record_only,
+            record_except,
+            deny_method,
+        } = self;
+        fn globs(it: &[MethodGlob]) -> Vec<&str> {
+            it.iter().map(MethodGlob::as_str).collect()
+        }
+        json!({
+            "recording": recording,
+            "chaos": chaos,
+            "redact": redact,
+            "record_only": globs(record_only),
+            "record_except": globs(record_except),
+            "deny_method": globs(deny_method),
+        })
+    }
+}
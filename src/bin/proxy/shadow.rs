@@ -0,0 +1,58 @@
+//! Mirroring calls to a second upstream, and comparing its responses.
+
+use std::collections::HashMap;
+
+use http::Uri;
+use jsonrpcli::{diff, stream::Members, Id};
+use serde::Deserialize;
+use serde_json::Value;
+
+pub struct Shadow {
+    pub uri: Uri,
+    /// Responses are compared without their `jsonrpc` and `id` members,
+    /// so pointers are like `/result/...` or `/error/...`.
+    pub options: diff::Options,
+}
+
+impl Shadow {
+    /// Log the differences between `primary` and `shadow`, the responses to `request`.
+    pub fn compare(&self, request: &[u8], primary: &[u8], shadow: &[u8]) {
+        #[derive(Deserialize)]
+        struct Call {
+            method: String,
+            id: Option<Id>,
+        }
+        let primary = responses(primary);
+        let mut shadow = responses(shadow);
+        for Call { method, id } in Members::<Call>::new(request).flatten() {
+            let Some((left, right)) = id
+                .as_ref()
+                .and_then(|id| Some((primary.get(id)?, shadow.remove(id))))
+            else {
+                continue;
+            };
+            let id = serde_json::to_string(&id).expect("ids always serialize");
+            match right {
+                None => tracing::warn!(method, id, "shadow didn't respond"),
+                Some(right) => {
+                    for difference in diff::diff(left, &right, &self.options) {
+                        tracing::warn!(method, id, %difference, "shadow mismatch")
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The members of a (possibly batched) response, by id.
+fn responses(body: &[u8]) -> HashMap<Id, Value> {
+    Members::<Value>::new(body)
+        .flatten()
+        .filter_map(|mut it| {
+            let members = it.as_object_mut()?;
+            members.remove("jsonrpc");
+            let id = Id::deserialize(members.remove("id")?).ok()?;
+            Some((id, it))
+        })
+        .collect()
+}
@@ -0,0 +1,313 @@
+//! Where recordings are written.
+
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write as _},
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{write::GzEncoder, Compression};
+use serde::Serialize;
+
+pub enum Sink {
+    Stdout,
+    Dir(Box<Rotating>),
+    Append(Appending),
+}
+
+impl Sink {
+    /// Append to the file at `path`, which is gzipped if it ends in `.gz`.
+    pub fn append(path: &Path) -> io::Result<Self> {
+        Ok(Self::Append(Appending {
+            file: File::options().append(true).open(path)?,
+            gzip: path.extension().is_some_and(|it| it == "gz"),
+            current: None,
+        }))
+    }
+    /// Write `record` as a line of JSON.
+    pub fn write_line(&mut self, record: &impl Serialize) -> io::Result<()> {
+        match self {
+            Sink::Stdout => {
+                let mut stdout = io::stdout().lock();
+                serde_json::to_writer(&mut stdout, record)?;
+                writeln!(stdout)
+            }
+            Sink::Dir(it) => it.write_line(record),
+            Sink::Append(it) => {
+                let writer = it.writer()?;
+                serde_json::to_writer(&mut *writer, record)?;
+                writeln!(writer)
+            }
+        }
+    }
+    /// Write `document` on its own, named for `name`.
+    pub fn write_document(&mut self, name: &str, document: &impl Serialize) -> io::Result<()> {
+        match self {
+            Sink::Stdout => {
+                let mut stdout = io::stdout().lock();
+                serde_json::to_writer_pretty(&mut stdout, document)?;
+                writeln!(stdout)
+            }
+            Sink::Dir(it) => it.write_document(name, document),
+            Sink::Append(it) => {
+                let writer = it.writer()?;
+                serde_json::to_writer_pretty(&mut *writer, document)?;
+                writeln!(writer)
+            }
+        }
+    }
+    /// Called periodically, to flush buffered output and start new files when the current one is too old.
+    pub fn tick(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Stdout => Ok(()),
+            Sink::Dir(it) => it.tick(),
+            Sink::Append(it) => it.tick(),
+        }
+    }
+    /// Flush any buffered output, completing compressed files.
+    pub fn finish(&mut self) -> io::Result<()> {
+        match self {
+            Sink::Stdout => io::stdout().flush(),
+            Sink::Dir(it) => it.finish(),
+            Sink::Append(it) => it.finish(),
+        }
+    }
+}
+
+/// Appending to an existing file.
+///
+/// Gzipped files are appended to in complete gzip members,
+/// one for each [`tick`](Self::tick) with new writes, which are read back as one stream.
+pub struct Appending {
+    file: File,
+    gzip: bool,
+    current: Option<Writer>,
+}
+
+impl Appending {
+    fn writer(&mut self) -> io::Result<&mut dyn io::Write> {
+        if self.current.is_none() {
+            let file = BufWriter::new(self.file.try_clone()?);
+            self.current = Some(match self.gzip {
+                true => Writer::Gzip(GzEncoder::new(file, Compression::default())),
+                false => Writer::Plain(file),
+            })
+        }
+        Ok(self.current.as_mut().expect("just created").get())
+    }
+    fn tick(&mut self) -> io::Result<()> {
+        match &mut self.current {
+            Some(Writer::Plain(it)) => it.flush(),
+            Some(Writer::Gzip(_)) => self.finish(),
+            None => Ok(()),
+        }
+    }
+    fn finish(&mut self) -> io::Result<()> {
+        match self.current.take() {
+            Some(it) => it.finish(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Newline-delimited files in a directory,
+/// starting a new one when the current one is too big or too old.
+pub struct Rotating {
+    dir: PathBuf,
+    max_bytes: Option<u64>,
+    max_age: Option<Duration>,
+    gzip: bool,
+    current: Option<Current>,
+    /// Disambiguates files created in the same millisecond.
+    created: u64,
+}
+
+struct Current {
+    writer: Writer,
+    /// Uncompressed.
+    written: u64,
+    opened: Instant,
+}
+
+enum Writer {
+    Plain(BufWriter<File>),
+    Gzip(GzEncoder<BufWriter<File>>),
+}
+
+impl Writer {
+    fn get(&mut self) -> &mut dyn io::Write {
+        match self {
+            Writer::Plain(it) => it,
+            Writer::Gzip(it) => it,
+        }
+    }
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Writer::Plain(mut it) => it.flush(),
+            Writer::Gzip(it) => it.finish()?.flush(),
+        }
+    }
+}
+
+impl Rotating {
+    pub fn new(
+        dir: PathBuf,
+        max_bytes: Option<u64>,
+        max_age: Option<Duration>,
+        gzip: bool,
+    ) -> io::Result<Self> {
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self {
+            dir,
+            max_bytes,
+            max_age,
+            gzip,
+            current: None,
+            created: 0,
+        })
+    }
+    fn write_line(&mut self, record: &impl Serialize) -> io::Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        if self.expired() {
+            self.finish()?
+        }
+        if self.current.is_none() {
+            self.current = Some(Current {
+                writer: self.create("recording", "ndjson")?,
+                written: 0,
+                opened: Instant::now(),
+            })
+        }
+        let current = self.current.as_mut().expect("just created");
+        current.writer.get().write_all(&line)?;
+        current.written += line.len() as u64;
+        Ok(())
+    }
+    fn expired(&self) -> bool {
+        self.current.as_ref().is_some_and(|it| {
+            self.max_bytes.is_some_and(|max| it.written >= max)
+                || self.max_age.is_some_and(|max| it.opened.elapsed() >= max)
+        })
+    }
+    fn tick(&mut self) -> io::Result<()> {
+        match self.expired() {
+            true => self.finish(),
+            false => match &mut self.current {
+                Some(it) => it.writer.get().flush(),
+                None => Ok(()),
+            },
+        }
+    }
+    fn write_document(&mut self, name: &str, document: &impl Serialize) -> io::Result<()> {
+        let mut writer = self.create(name, "json")?;
+        serde_json::to_writer_pretty(writer.get(), document)?;
+        writer.finish()
+    }
+    fn finish(&mut self) -> io::Result<()> {
+        match self.current.take() {
+            Some(it) => it.writer.finish(),
+            None => Ok(()),
+        }
+    }
+    /// A new file, named for the current time.
+    fn create(&mut self, stem: &str, extension: &str) -> io::Result<Writer> {
+        let millis = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis();
+        let mut name = format!("{}-{}-{}.{}", stem, millis, self.created, extension);
+        self.created += 1;
+        if self.gzip {
+            name.push_str(".gz")
+        }
+        let file = BufWriter::new(File::create_new(self.dir.join(name))?);
+        Ok(match self.gzip {
+            true => Writer::Gzip(GzEncoder::new(file, Compression::default())),
+            false => Writer::Plain(file),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read as _;
+
+    use flate2::read::MultiGzDecoder;
+    use serde_json::json;
+
+    use super::*;
+
+    fn contents(dir: &Path) -> Vec<String> {
+        let mut paths = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|it| it.unwrap().path())
+            .collect::<Vec<_>>();
+        paths.sort();
+        paths
+            .into_iter()
+            .map(|path| {
+                let mut s = String::new();
+                let file = File::open(&path).unwrap();
+                match path.extension().is_some_and(|it| it == "gz") {
+                    true => MultiGzDecoder::new(file).read_to_string(&mut s),
+                    false => io::BufReader::new(file).read_to_string(&mut s),
+                }
+                .unwrap();
+                s
+            })
+            .collect()
+    }
+
+    #[test]
+    fn append_gzip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording.ndjson.gz");
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        writeln!(encoder, "0").unwrap();
+        encoder.finish().unwrap();
+
+        let mut sink = Sink::append(&path).unwrap();
+        sink.write_line(&json!(1)).unwrap();
+        sink.tick().unwrap();
+        sink.tick().unwrap();
+        sink.write_line(&json!(2)).unwrap();
+        sink.finish().unwrap();
+        assert_eq!(contents(dir.path()), ["0\n1\n2\n"]);
+    }
+
+    #[test]
+    fn rotate_by_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = Rotating::new(dir.path().into(), Some(8), None, false).unwrap();
+        for i in 0..3 {
+            sink.write_line(&json!({ "i": i })).unwrap()
+        }
+        sink.finish().unwrap();
+        assert_eq!(
+            contents(dir.path()),
+            ["{\"i\":0}\n", "{\"i\":1}\n", "{\"i\":2}\n"]
+        );
+    }
+
+    #[test]
+    fn rotate_by_age_on_tick() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = Rotating::new(dir.path().into(), None, Some(Duration::ZERO), true).unwrap();
+        sink.write_line(&json!(1)).unwrap();
+        sink.tick().unwrap();
+        assert!(sink.current.is_none());
+        assert_eq!(contents(dir.path()), ["1\n"]);
+    }
+
+    #[test]
+    fn tick_flushes() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut sink = Rotating::new(dir.path().into(), None, None, false).unwrap();
+        sink.write_line(&json!(1)).unwrap();
+        assert_eq!(contents(dir.path()), [""]);
+        sink.tick().unwrap();
+        assert_eq!(contents(dir.path()), ["1\n"]);
+    }
+}
@@ -0,0 +1,248 @@
+//! Splitting streamed responses into JSON-RPC messages, as they arrive.
+//!
+//! Server-sent events (`text/event-stream`) carry a message in the `data` of each event,
+//! and newline-delimited JSON (`application/x-ndjson` or `application/jsonl`) carries one per line.
+//!
+//! Responses are streamed as they arrive, unless `--rewrite`, `--coalesce` or `--fallback`
+//! apply to the call. Then they're buffered, and changed one message at a time, see [`map`],
+//! so those streams must end.
+
+use http::{header::CONTENT_TYPE, HeaderMap};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    Events,
+    Lines,
+}
+
+impl Framing {
+    /// The framing of a response, if it's streamed.
+    pub fn of(headers: &HeaderMap) -> Option<Self> {
+        let content_type = headers.get(CONTENT_TYPE)?.to_str().ok()?;
+        media_types(content_type).find_map(framing)
+    }
+}
+
+/// The media types in a header like `Accept` or `Content-Type`, without parameters.
+fn media_types(header: &str) -> impl Iterator<Item = &str> {
+    header
+        .split(',')
+        .map(|it| it.split(';').next().unwrap_or_default().trim())
+}
+
+fn framing(media_type: &str) -> Option<Framing> {
+    match media_type.to_ascii_lowercase().as_str() {
+        "text/event-stream" => Some(Framing::Events),
+        "application/x-ndjson" | "application/jsonl" => Some(Framing::Lines),
+        _ => None,
+    }
+}
+
+pub struct Decoder {
+    framing: Framing,
+    /// Bytes of an incomplete line.
+    buf: Vec<u8>,
+    /// The data of the current event so far.
+    data: Vec<u8>,
+}
+
+impl Decoder {
+    pub fn new(framing: Framing) -> Self {
+        Self {
+            framing,
+            buf: vec![],
+            data: vec![],
+        }
+    }
+    /// Add `bytes` from the stream, returning any messages they complete.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Vec<u8>> {
+        self.buf.extend_from_slice(bytes);
+        let mut messages = vec![];
+        while let Some(ix) = self.buf.iter().position(|it| *it == b'\n') {
+            let mut line = self.buf.drain(..=ix).collect::<Vec<_>>();
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            match (self.framing, line.is_empty()) {
+                (Framing::Lines, _) if line.trim_ascii().is_empty() => {}
+                (Framing::Lines, _) => messages.push(line),
+                // A blank line ends an event.
+                (Framing::Events, true) if !self.data.is_empty() => {
+                    messages.push(std::mem::take(&mut self.data))
+                }
+                (Framing::Events, true) => {}
+                (Framing::Events, false) => {
+                    if let Some(value) = data(&line) {
+                        if !self.data.is_empty() {
+                            self.data.push(b'\n')
+                        }
+                        self.data.extend_from_slice(value)
+                    }
+                }
+            }
+        }
+        messages
+    }
+}
+
+/// Replace each message in a whole streamed `body` with `f` of it,
+/// keeping the rest of the stream, like the other fields of events.
+///
+/// Incomplete messages at the end are kept as they are.
+pub fn map(framing: Framing, body: &[u8], mut f: impl FnMut(&[u8]) -> Vec<u8>) -> Vec<u8> {
+    let mut mapped = vec![];
+    // The lines of the current event so far.
+    let mut event = vec![];
+    for line in body.split_inclusive(|it| *it == b'\n') {
+        let content = trim_end(line);
+        let complete = line.ends_with(b"\n");
+        match framing {
+            Framing::Lines if !complete || content.trim_ascii().is_empty() => mapped.extend(line),
+            Framing::Lines => {
+                mapped.extend(f(content));
+                mapped.extend(&line[content.len()..])
+            }
+            Framing::Events if complete && content.is_empty() => {
+                map_event(&event, &mut mapped, &mut f);
+                mapped.extend(line);
+                event.clear()
+            }
+            Framing::Events => event.push(line),
+        }
+    }
+    mapped.extend(event.concat());
+    mapped
+}
+
+/// Like [`map`], for the `lines` of one event.
+fn map_event(lines: &[&[u8]], mapped: &mut Vec<u8>, f: &mut impl FnMut(&[u8]) -> Vec<u8>) {
+    let values = lines
+        .iter()
+        .filter_map(|it| data(trim_end(it)))
+        .collect::<Vec<_>>();
+    if values.is_empty() {
+        mapped.extend(lines.concat());
+        return;
+    }
+    let mut values = Some(values.join(&b'\n'));
+    for line in lines {
+        match (data(trim_end(line)), values.take()) {
+            (None, it) => {
+                values = it;
+                mapped.extend(*line)
+            }
+            // The first data field is replaced with the new message, the rest are dropped.
+            (Some(_), Some(it)) => data_fields(&f(&it), mapped),
+            (Some(_), None) => {}
+        }
+    }
+}
+
+/// `message` as the `data` fields of an event.
+fn data_fields(message: &[u8], event: &mut Vec<u8>) {
+    for line in message.split(|it| *it == b'\n') {
+        event.extend(b"data: ");
+        event.extend(line);
+        event.push(b'\n')
+    }
+}
+
+/// `line` without its line ending.
+fn trim_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+/// The value of a `data` field, if `line` is one.
+fn data(line: &[u8]) -> Option<&[u8]> {
+    let rest = line.strip_prefix(b"data")?;
+    match rest.strip_prefix(b":") {
+        Some(value) => Some(value.strip_prefix(b" ").unwrap_or(value)),
+        None => rest.is_empty().then_some(rest),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::HeaderValue;
+
+    use super::*;
+
+    fn decode(framing: Framing, chunks: &[&str]) -> Vec<String> {
+        let mut decoder = Decoder::new(framing);
+        chunks
+            .iter()
+            .flat_map(|it| decoder.push(it.as_bytes()))
+            .map(|it| String::from_utf8(it).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn events() {
+        assert_eq!(
+            decode(
+                Framing::Events,
+                &[
+                    ": comment\nevent: message\nid: 1\ndata: {\"a\"",
+                    ":\r\n",
+                    "data:1}\r\n\r\n\n",
+                    "retry: 10\ndata: 2\n\n",
+                    "data: incomplete\n"
+                ]
+            ),
+            ["{\"a\":\n1}", "2"]
+        );
+    }
+
+    #[test]
+    fn lines() {
+        assert_eq!(
+            decode(Framing::Lines, &["{\"a\":", " 1}\n\n  \r\n2\r\n", "3"]),
+            ["{\"a\": 1}", "2"]
+        );
+    }
+
+    #[test]
+    fn headers() {
+        let headers = |name, value| {
+            let mut headers = HeaderMap::new();
+            headers.insert(name, HeaderValue::from_static(value));
+            headers
+        };
+        assert_eq!(
+            Framing::of(&headers(CONTENT_TYPE, "text/event-stream; charset=utf-8")),
+            Some(Framing::Events)
+        );
+        assert_eq!(
+            Framing::of(&headers(CONTENT_TYPE, "Application/JSONL")),
+            Some(Framing::Lines)
+        );
+        assert_eq!(
+            Framing::of(&headers(CONTENT_TYPE, "application/json")),
+            None
+        );
+    }
+
+    #[test]
+    fn map() {
+        let upper = |it: &[u8]| it.to_ascii_uppercase();
+        assert_eq!(
+            super::map(Framing::Lines, b"one\r\n\n two\nthree", upper),
+            b"ONE\r\n\n TWO\nthree"
+        );
+        assert_eq!(
+            String::from_utf8(super::map(
+                Framing::Events,
+                b": comment\nid: 1\ndata: a\r\ndata: b\nretry: 10\n\nevent: ping\n\ndata: incomplete\n",
+                upper
+            ))
+            .unwrap(),
+            ": comment\nid: 1\ndata: A\ndata: B\nretry: 10\n\nevent: ping\n\ndata: incomplete\n"
+        );
+        assert_eq!(
+            super::map(Framing::Events, b"data: a\n\n", |_| b"{}".to_vec()),
+            b"data: {}\n\n"
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Grouping WebSocket subscriptions with their notifications.
+//!
+//! A successful call to a `*_subscribe` method opens a subscription, named by its result.
+//! Notifications naming it in `params.subscription` are collected,
+//! until a `*_unsubscribe` call naming it succeeds, or the connection closes.
+//! Then the subscribe call is recorded, with the notifications in [`SUBSCRIPTION_EXTENSION`].
+
+use std::{collections::HashMap, time::SystemTime};
+
+use jsonrpcli::{Request, RequestParameters};
+use openrpc_types::ExamplePairing;
+use serde::Deserialize;
+use serde_json::{json, value::RawValue, Value};
+
+use crate::{record::Metadata, Config};
+
+/// The extension field which holds the notifications of a subscription, like
+/// `{"id": "0x1", "notifications": [{"received": "...", "method": "eth_subscription", "params": {...}}], "dropped": 0, "unsubscribed": "..."}`.
+///
+/// `unsubscribed` is `null` if the connection closed first.
+/// Notifications beyond `--max-record-bytes` are counted in `dropped`.
+pub const SUBSCRIPTION_EXTENSION: &str = "x-jsonrpcli-subscription";
+
+/// The open subscriptions on a connection, by id.
+#[derive(Default)]
+pub struct Subscriptions(HashMap<String, Subscription>);
+
+pub struct Subscription {
+    pub request: Box<RawValue>,
+    pub response: Box<RawValue>,
+    pub metadata: Metadata,
+    id: Value,
+    notifications: Vec<Value>,
+    bytes: usize,
+    dropped: usize,
+    unsubscribed: Option<SystemTime>,
+}
+
+#[derive(Deserialize)]
+struct Call {
+    method: String,
+    #[serde(default)]
+    params: Option<RequestParameters>,
+}
+
+#[derive(Deserialize)]
+struct Success {
+    result: Value,
+}
+
+impl Subscriptions {
+    /// Open a subscription, if `request` is a subscribe call which succeeded.
+    pub fn open(&mut self, request: &RawValue, response: &RawValue, metadata: &Metadata) -> bool {
+        let (Ok(call), Ok(Success { result: id })) = (
+            serde_json::from_str::<Call>(request.get()),
+            serde_json::from_str::<Success>(response.get()),
+        ) else {
+            return false;
+        };
+        if !call.method.ends_with("_subscribe") || id.is_null() {
+            return false;
+        }
+        self.0.insert(
+            id.to_string(),
+            Subscription {
+                request: request.to_owned(),
+                response: response.to_owned(),
+                metadata: metadata.clone(),
+                id,
+                notifications: vec![],
+                bytes: 0,
+                dropped: 0,
+                unsubscribed: None,
+            },
+        );
+        true
+    }
+    /// Close the subscription named by `request`, if it's an unsubscribe call which succeeded.
+    pub fn close(&mut self, request: &RawValue, response: &RawValue) -> Option<Subscription> {
+        let call = serde_json::from_str::<Call>(request.get()).ok()?;
+        serde_json::from_str::<Success>(response.get()).ok()?;
+        if !call.method.ends_with("_unsubscribe") {
+            return None;
+        }
+        let id = match call.params? {
+            RequestParameters::ByPosition(it) => it.into_iter().next()?,
+            RequestParameters::ByName(_) => return None,
+        };
+        let mut subscription = self.0.remove(&id.to_string())?;
+        subscription.unsubscribed = Some(SystemTime::now());
+        Some(subscription)
+    }
+    /// Collect `notification`, if it's for an open subscription.
+    pub fn notify(&mut self, config: &Config, notification: &RawValue) -> bool {
+        let Ok(mut notification) = serde_json::from_str::<Request>(notification.get()) else {
+            return false;
+        };
+        let Some(RequestParameters::ByName(params)) = &notification.params else {
+            return false;
+        };
+        let Some(subscription) = params
+            .get("subscription")
+            .and_then(|it| self.0.get_mut(&it.to_string()))
+        else {
+            return false;
+        };
+        config
+            .settings
+            .read()
+            .unwrap()
+            .redactions
+            .apply(&mut notification, &mut Ok(Value::Null));
+        let Request { method, params, .. } = notification;
+        let entry = json!({
+            "received": crate::har::timestamp(SystemTime::now()),
+            "method": method,
+            "params": params,
+        });
+        let len = entry.to_string().len();
+        match subscription.bytes + len > config.max_record_bytes {
+            true => subscription.dropped += 1,
+            false => {
+                subscription.bytes += len;
+                subscription.notifications.push(entry)
+            }
+        }
+        true
+    }
+    /// The subscriptions still open, when the connection closes.
+    pub fn drain(&mut self) -> impl Iterator<Item = Subscription> + '_ {
+        self.0.drain().map(|(_, it)| it)
+    }
+}
+
+impl Subscription {
+    /// Add [`SUBSCRIPTION_EXTENSION`] to the pairing for the subscribe call.
+    pub fn annotate(&self, pairing: &mut ExamplePairing) {
+        let Self {
+            id,
+            notifications,
+            dropped,
+            unsubscribed,
+            ..
+        } = self;
+        pairing.extensions.0.insert(
+            String::from(SUBSCRIPTION_EXTENSION),
+            json!({
+                "id": id,
+                "notifications": notifications,
+                "dropped": dropped,
+                "unsubscribed": unsubscribed.map(crate::har::timestamp),
+            }),
+        );
+    }
+}
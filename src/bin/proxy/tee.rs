@@ -0,0 +1,111 @@
+//! Streaming a body, while keeping a copy for recording.
+
+use std::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use hyper::body::{Body, Bytes, Frame, Incoming, SizeHint};
+
+use crate::sse::Decoder;
+
+type OnEnd = Box<dyn FnOnce(Option<&Bytes>) + Send>;
+type OnMessage = Box<dyn FnMut(Vec<u8>) + Send>;
+
+pub struct Tee {
+    inner: Incoming,
+    /// [`None`] once the body is bigger than `limit`.
+    copy: Option<Vec<u8>>,
+    limit: usize,
+    on_end: Vec<OnEnd>,
+    messages: Option<(Decoder, OnMessage)>,
+    /// Dropped with the body.
+    held: Vec<Box<dyn Send>>,
+}
+
+impl Tee {
+    /// Keep a copy of up to `limit` bytes of `inner`.
+    pub fn new(inner: Incoming, limit: usize) -> Self {
+        Self {
+            inner,
+            copy: Some(vec![]),
+            limit,
+            on_end: vec![],
+            messages: None,
+            held: vec![],
+        }
+    }
+    /// Call `f` with each message in the stream as it arrives, instead of keeping a copy.
+    pub fn messages(mut self, decoder: Decoder, f: impl FnMut(Vec<u8>) + Send + 'static) -> Self {
+        self.copy = None;
+        self.messages = Some((decoder, Box::new(f)));
+        self
+    }
+    /// Call `f` with the whole body once it has been streamed,
+    /// or with [`None`] if it was too big to keep.
+    ///
+    /// `f` isn't called if the stream fails, or the client goes away.
+    pub fn on_end(mut self, f: impl FnOnce(Option<&Bytes>) + Send + 'static) -> Self {
+        self.on_end.push(Box::new(f));
+        self
+    }
+    /// Keep `it` until the body is dropped, once it has been streamed or the client goes away.
+    pub fn hold(mut self, it: impl Send + 'static) -> Self {
+        self.held.push(Box::new(it));
+        self
+    }
+}
+
+impl Tee {
+    fn end(&mut self) {
+        let copy = self.copy.take().map(Bytes::from);
+        for f in self.on_end.drain(..) {
+            f(copy.as_ref())
+        }
+    }
+}
+
+impl Body for Tee {
+    type Data = Bytes;
+    type Error = hyper::Error;
+    fn poll_frame(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        let this = self.get_mut();
+        match ready!(Pin::new(&mut this.inner).poll_frame(cx)) {
+            Some(Ok(frame)) => {
+                if let (Some(data), Some((decoder, f))) = (frame.data_ref(), &mut this.messages) {
+                    decoder.push(data).into_iter().for_each(f)
+                }
+                if let Some(data) = frame.data_ref() {
+                    this.copy = this
+                        .copy
+                        .take()
+                        .filter(|it| it.len() + data.len() <= this.limit)
+                        .map(|mut it| {
+                            it.extend_from_slice(data);
+                            it
+                        });
+                }
+                // Bodies with a known length may not be polled again once it's reached.
+                if this.inner.is_end_stream() {
+                    this.end()
+                }
+                Poll::Ready(Some(Ok(frame)))
+            }
+            Some(Err(e)) => {
+                this.on_end.clear();
+                Poll::Ready(Some(Err(e)))
+            }
+            None => {
+                this.end();
+                Poll::Ready(None)
+            }
+        }
+    }
+    // Not `is_end_stream`, so that the end is always polled for.
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,60 @@
+//! Serving the proxy over TLS.
+
+use std::{fs::File, io::BufReader, path::Path, sync::Arc};
+
+use anyhow::{bail, Context as _};
+use tokio_rustls::{rustls::ServerConfig, TlsAcceptor};
+
+/// Load a certificate chain and private key, both PEM-encoded.
+pub fn acceptor(cert: &Path, key: &Path) -> anyhow::Result<TlsAcceptor> {
+    let chain = rustls_pemfile::certs(&mut open(cert)?)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("invalid certificate in {}", cert.display()))?;
+    if chain.is_empty() {
+        bail!("no certificates in {}", cert.display())
+    }
+    let key = rustls_pemfile::private_key(&mut open(key)?)
+        .with_context(|| format!("invalid private key in {}", key.display()))?
+        .with_context(|| format!("no private key in {}", key.display()))?;
+    let mut config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(chain, key)?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn open(path: &Path) -> anyhow::Result<BufReader<File>> {
+    let file = File::open(path).with_context(|| format!("couldn't read {}", path.display()))?;
+    Ok(BufReader::new(file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load() {
+        let dir = tempfile::tempdir().unwrap();
+        let (cert, key) = (dir.path().join("cert.pem"), dir.path().join("key.pem"));
+        let rcgen::CertifiedKey { cert: c, key_pair } =
+            rcgen::generate_simple_self_signed(["localhost".into()]).unwrap();
+        std::fs::write(&cert, c.pem()).unwrap();
+        std::fs::write(&key, key_pair.serialize_pem()).unwrap();
+        acceptor(&cert, &key).unwrap();
+
+        let Err(e) = acceptor(&key, &key) else {
+            panic!()
+        };
+        assert_eq!(
+            e.to_string(),
+            format!("no certificates in {}", key.display())
+        );
+        let Err(e) = acceptor(&cert, &cert) else {
+            panic!()
+        };
+        assert_eq!(
+            e.to_string(),
+            format!("no private key in {}", cert.display())
+        );
+    }
+}
@@ -0,0 +1,233 @@
+//! Listening on, and connecting to, Unix sockets as well as TCP.
+//!
+//! Upstreams like `unix:/path.sock` are carried through the proxy as URIs like `unix://<hex path>/`,
+//! so they can be balanced, routed and failed over to like any other.
+
+use std::{
+    fmt,
+    future::Future,
+    io,
+    net::SocketAddr,
+    path::PathBuf,
+    pin::Pin,
+    str::FromStr,
+    task::{Context, Poll},
+};
+
+use http::Uri;
+use hyper::rt::{Read, ReadBufCursor, Write};
+use hyper_util::{
+    client::legacy::connect::{Connected, HttpConnector},
+    rt::TokioIo,
+};
+use tokio::net::{TcpListener, TcpStream};
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+use tower_service::Service;
+
+use crate::Io;
+
+/// Where to listen for clients.
+#[derive(Debug, Clone)]
+pub enum Local {
+    Tcp(SocketAddr),
+    #[cfg(unix)]
+    Unix(PathBuf),
+}
+
+impl FromStr for Local {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        #[cfg(unix)]
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(Self::Unix(PathBuf::from(path)));
+        }
+        s.parse().map(Self::Tcp).map_err(|e| {
+            format!(
+                "expected an address like `127.0.0.1:8080` or `unix:/path.sock`, not `{}`: {}",
+                s, e
+            )
+        })
+    }
+}
+
+/// Who a connection is from.
+#[derive(Debug, Clone, Copy)]
+pub enum Peer {
+    Tcp(SocketAddr),
+    Unix,
+}
+
+impl fmt::Display for Peer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Peer::Tcp(it) => it.fmt(f),
+            Peer::Unix => f.write_str("unix"),
+        }
+    }
+}
+
+pub enum Listener {
+    Tcp(TcpListener),
+    /// The socket file is removed when the listener is dropped.
+    #[cfg(unix)]
+    Unix(UnixListener, PathBuf),
+}
+
+impl Listener {
+    pub async fn bind(local: &Local) -> io::Result<Self> {
+        match local {
+            Local::Tcp(it) => TcpListener::bind(it).await.map(Self::Tcp),
+            #[cfg(unix)]
+            Local::Unix(path) => UnixListener::bind(path).map(|it| Self::Unix(it, path.clone())),
+        }
+    }
+    pub async fn accept(&self) -> io::Result<(Box<dyn Io>, Peer)> {
+        match self {
+            Listener::Tcp(it) => {
+                let (stream, peer) = it.accept().await?;
+                Ok((Box::new(stream), Peer::Tcp(peer)))
+            }
+            #[cfg(unix)]
+            Listener::Unix(it, _) => {
+                let (stream, _) = it.accept().await?;
+                Ok((Box::new(stream), Peer::Unix))
+            }
+        }
+    }
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        #[cfg(unix)]
+        if let Listener::Unix(_, path) = self {
+            if let Err(e) = std::fs::remove_file(&*path) {
+                tracing::warn!("couldn't remove {}: {}", path.display(), e)
+            }
+        }
+    }
+}
+
+/// Parse an upstream, like `http://node:8545` or `unix:/path.sock`.
+pub fn uri(s: &str) -> Result<Uri, String> {
+    let parsed = match s.strip_prefix("unix:") {
+        Some(path) => {
+            let hex = path
+                .bytes()
+                .map(|it| format!("{:02x}", it))
+                .collect::<String>();
+            format!("unix://{}/", hex).parse()
+        }
+        None => s.parse(),
+    };
+    parsed.map_err(|e| format!("invalid uri `{}`: {}", s, e))
+}
+
+/// The path of the socket for a `unix:` upstream, as parsed by [`uri`].
+pub fn path(uri: &Uri) -> Option<PathBuf> {
+    if uri.scheme_str() != Some("unix") {
+        return None;
+    }
+    let hex = uri.host()?.as_bytes();
+    let bytes = hex
+        .chunks(2)
+        .map(|it| u8::from_str_radix(std::str::from_utf8(it).ok()?, 16).ok())
+        .collect::<Option<Vec<_>>>()?;
+    Some(PathBuf::from(String::from_utf8(bytes).ok()?))
+}
+
+/// Connects to `unix:` upstreams over Unix sockets, and to others over TCP.
+#[derive(Clone)]
+pub struct Connector(HttpConnector);
+
+impl Connector {
+    pub fn new() -> Self {
+        Self(HttpConnector::new())
+    }
+}
+
+impl Service<Uri> for Connector {
+    type Response = Connection;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Future = Pin<Box<dyn Future<Output = Result<Connection, Self::Error>> + Send>>;
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.0.poll_ready(cx).map_err(Into::into)
+    }
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        match path(&uri) {
+            #[cfg(unix)]
+            Some(path) => Box::pin(async move {
+                let stream = UnixStream::connect(&path)
+                    .await
+                    .map_err(|e| io::Error::new(e.kind(), format!("{}: {}", path.display(), e)))?;
+                Ok(Connection::Unix(TokioIo::new(stream)))
+            }),
+            #[cfg(not(unix))]
+            Some(_) => {
+                Box::pin(async { Err("unix sockets aren't supported on this platform".into()) })
+            }
+            None => {
+                let connecting = self.0.call(uri);
+                Box::pin(async move { Ok(Connection::Tcp(connecting.await?)) })
+            }
+        }
+    }
+}
+
+pub enum Connection {
+    Tcp(TokioIo<TcpStream>),
+    #[cfg(unix)]
+    Unix(TokioIo<UnixStream>),
+}
+
+impl hyper_util::client::legacy::connect::Connection for Connection {
+    fn connected(&self) -> Connected {
+        match self {
+            Connection::Tcp(it) => it.connected(),
+            #[cfg(unix)]
+            Connection::Unix(_) => Connected::new(),
+        }
+    }
+}
+
+impl Read for Connection {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: ReadBufCursor<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Tcp(it) => Pin::new(it).poll_read(cx, buf),
+            #[cfg(unix)]
+            Connection::Unix(it) => Pin::new(it).poll_read(cx, buf),
+        }
+    }
+}
+
+impl Write for Connection {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Connection::Tcp(it) => Pin::new(it).poll_write(cx, buf),
+            #[cfg(unix)]
+            Connection::Unix(it) => Pin::new(it).poll_write(cx, buf),
+        }
+    }
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Tcp(it) => Pin::new(it).poll_flush(cx),
+            #[cfg(unix)]
+            Connection::Unix(it) => Pin::new(it).poll_flush(cx),
+        }
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Connection::Tcp(it) => Pin::new(it).poll_shutdown(cx),
+            #[cfg(unix)]
+            Connection::Unix(it) => Pin::new(it).poll_shutdown(cx),
+        }
+    }
+}
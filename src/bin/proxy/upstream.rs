@@ -0,0 +1,216 @@
+//! Balancing calls across a pool of upstreams.
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use clap::ValueEnum;
+use http::{HeaderMap, Uri};
+
+use hyper::body::Bytes;
+use hyper_util::client::legacy::Client;
+use jsonrpcli::{Id, Request, V2};
+
+use crate::{redact, unix::Connector, Body};
+
+/// How to pick an upstream for each call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Balance {
+    /// Each upstream in turn.
+    RoundRobin,
+    /// The upstream with the fewest calls in flight.
+    LeastOutstanding,
+}
+
+pub struct Upstream {
+    uri: Uri,
+    /// Calls in flight.
+    outstanding: AtomicUsize,
+    healthy: AtomicBool,
+}
+
+pub struct Pool {
+    upstreams: Vec<Arc<Upstream>>,
+    balance: Balance,
+    /// For [`Balance::RoundRobin`].
+    next: AtomicUsize,
+}
+
+impl Pool {
+    /// # Panics
+    /// - If `uris` is empty.
+    pub fn new(uris: Vec<Uri>, balance: Balance) -> Self {
+        assert!(!uris.is_empty(), "a pool must have an upstream");
+        Self {
+            upstreams: uris
+                .into_iter()
+                .map(|uri| {
+                    Arc::new(Upstream {
+                        uri,
+                        outstanding: AtomicUsize::new(0),
+                        healthy: AtomicBool::new(true),
+                    })
+                })
+                .collect(),
+            balance,
+            next: AtomicUsize::new(0),
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.upstreams.len()
+    }
+    /// The first upstream, for describing the pool.
+    pub fn primary(&self) -> &Uri {
+        &self.upstreams[0].uri
+    }
+    /// Choose an upstream for a call, which is in flight until the [`Lease`] is dropped.
+    ///
+    /// Unhealthy upstreams are only chosen if there are no healthy ones.
+    pub fn pick(&self) -> Lease {
+        let healthy = self
+            .upstreams
+            .iter()
+            .filter(|it| it.healthy.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+        let candidates = match healthy.is_empty() {
+            true => self.upstreams.iter().collect(),
+            false => healthy,
+        };
+        let chosen = match self.balance {
+            Balance::RoundRobin => {
+                candidates[self.next.fetch_add(1, Ordering::Relaxed) % candidates.len()]
+            }
+            Balance::LeastOutstanding => candidates
+                .into_iter()
+                .min_by_key(|it| it.outstanding.load(Ordering::Relaxed))
+                .expect("pools aren't empty"),
+        };
+        chosen.outstanding.fetch_add(1, Ordering::Relaxed);
+        Lease(chosen.clone())
+    }
+    /// Probe each upstream, marking them healthy if they respond within `timeout` without a server error.
+    pub async fn check(
+        &self,
+        client: &Client<Connector, Body>,
+        headers: &HeaderMap,
+        timeout: Duration,
+    ) {
+        for upstream in &self.upstreams {
+            let healthy = probe(client, &upstream.uri, headers, timeout).await;
+            upstream.set_healthy(healthy)
+        }
+    }
+}
+
+impl Upstream {
+    fn set_healthy(&self, healthy: bool) {
+        if self.healthy.swap(healthy, Ordering::Relaxed) != healthy {
+            tracing::info!(
+                "upstream {} is {}",
+                redact::uri(&self.uri),
+                match healthy {
+                    true => "healthy",
+                    false => "unhealthy",
+                }
+            )
+        }
+    }
+}
+
+/// Any response is fine, even an error, as long as it isn't a server error.
+async fn probe(
+    client: &Client<Connector, Body>,
+    uri: &Uri,
+    headers: &HeaderMap,
+    timeout: Duration,
+) -> bool {
+    let mut request = Request {
+        jsonrpc: V2,
+        method: String::from("jsonrpcli_health"),
+        params: None,
+        id: Some(Id::from_u64(0)),
+    }
+    .into_http(uri.clone())
+    .map(|it| crate::full(Bytes::from(it)));
+    request.headers_mut().extend(headers.clone());
+    match tokio::time::timeout(timeout, client.request(request)).await {
+        Ok(Ok(it)) => !it.status().is_server_error(),
+        _ => false,
+    }
+}
+
+/// An upstream chosen for a call.
+pub struct Lease(Arc<Upstream>);
+
+impl Lease {
+    pub fn uri(&self) -> &Uri {
+        &self.0.uri
+    }
+    /// Stop choosing this upstream until it passes a health check.
+    pub fn eject(&self) {
+        self.0.set_healthy(false)
+    }
+}
+
+impl Drop for Lease {
+    fn drop(&mut self) {
+        self.0.outstanding.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use http::{header::AUTHORIZATION, HeaderValue};
+    use tokio::{
+        io::{AsyncReadExt as _, AsyncWriteExt as _},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// An upstream which fails unless it's called with `authorization: Bearer token`.
+    async fn upstream() -> Uri {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut request = vec![];
+                while !request.windows(4).any(|it| it == b"\r\n\r\n") {
+                    let mut buf = [0; 1024];
+                    let n = stream.read(&mut buf).await.unwrap();
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let request = String::from_utf8_lossy(&request).to_ascii_lowercase();
+                let status = match request.contains("\r\nauthorization: bearer token\r\n") {
+                    true => "200 OK",
+                    false => "503 Service Unavailable",
+                };
+                let response = format!("HTTP/1.1 {}\r\ncontent-length: 0\r\n\r\n", status);
+                stream.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        uri.parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn probes_carry_headers() {
+        let client = Client::builder(hyper_util::rt::TokioExecutor::new())
+            .build::<_, Body>(Connector::new());
+        let pool = Pool::new(vec![upstream().await], Balance::RoundRobin);
+        let healthy = || pool.upstreams[0].healthy.load(Ordering::Relaxed);
+        let timeout = Duration::from_secs(5);
+
+        pool.check(&client, &HeaderMap::new(), timeout).await;
+        assert!(!healthy());
+
+        let headers =
+            HeaderMap::from_iter([(AUTHORIZATION, HeaderValue::from_static("Bearer token"))]);
+        pool.check(&client, &headers, timeout).await;
+        assert!(healthy());
+    }
+}
@@ -0,0 +1,220 @@
+//! Relaying WebSocket connections, recording the messages as they pass.
+//!
+//! Messages are read with [`tokio_tungstenite`], and calls in them are answered locally
+//! like those over HTTP, see [`crate::answer`]. The rest are forwarded,
+//! and the responses to those which were answered are sent to the client in a message of their own.
+//! Calls can't be routed by method over one connection, so upgrades are refused while there are routes.
+//!
+//! Compression is disabled by not forwarding the client's `Sec-WebSocket-Extensions`.
+//! Subscriptions are recorded with their notifications, see [`crate::subscription`].
+
+use std::{collections::HashMap, sync::Mutex, time::Duration};
+
+use futures::{SinkExt as _, StreamExt as _};
+
+use http::{
+    header::{SEC_WEBSOCKET_EXTENSIONS, UPGRADE},
+    HeaderMap, StatusCode, Version,
+};
+use http_body_util::{BodyExt as _, Full};
+use hyper::{
+    body::{Bytes, Incoming},
+    upgrade::Upgraded,
+};
+use hyper_util::{client::legacy::Client, rt::TokioIo};
+use jsonrpcli::Id;
+use tokio_tungstenite::{
+    tungstenite::{self, protocol::Role, Message, Utf8Bytes},
+    WebSocketStream,
+};
+
+use crate::{
+    log,
+    record::{self, Pending},
+    redact,
+    subscription::Subscriptions,
+    unix::Connector,
+    Body, Config,
+};
+
+/// Whether `headers` ask to switch to the WebSocket protocol.
+pub fn is_upgrade(headers: &HeaderMap) -> bool {
+    headers
+        .get(UPGRADE)
+        .and_then(|it| it.to_str().ok())
+        .is_some_and(|it| it.eq_ignore_ascii_case("websocket"))
+}
+
+/// Perform the handshake with the upstream, and relay the connection in the background.
+///
+/// If the upstream refuses, its response is returned to the client.
+pub async fn upgrade(
+    mut request: http::Request<Incoming>,
+    client: &Client<Connector, Body>,
+    config: &'static Config,
+) -> anyhow::Result<http::Response<Full<Bytes>>> {
+    if !config.routes.read().unwrap().is_empty() {
+        let mut response = http::Response::new(Full::new(Bytes::from_static(
+            b"WebSocket connections can't be routed by method\n",
+        )));
+        *response.status_mut() = StatusCode::NOT_IMPLEMENTED;
+        return Ok(response);
+    }
+    let from_client = hyper::upgrade::on(&mut request);
+    let (mut parts, _) = request.into_parts();
+    parts
+        .uri
+        .clone_from(config.routes.read().unwrap().default().pick().uri());
+    parts.version = Version::HTTP_11;
+    parts.headers.remove(SEC_WEBSOCKET_EXTENSIONS);
+    parts.headers.extend(config.upstream_headers.clone());
+
+    let uri = log::Upstream(redact::uri(&parts.uri));
+    let mut response = client
+        .request(http::Request::from_parts(parts, crate::full(Bytes::new())))
+        .await?;
+    if response.status() != StatusCode::SWITCHING_PROTOCOLS {
+        let (parts, body) = response.into_parts();
+        let body = body.collect().await?.to_bytes();
+        return Ok(http::Response::from_parts(parts, Full::new(body)));
+    }
+
+    let from_upstream = hyper::upgrade::on(&mut response);
+    tokio::spawn(async move {
+        match tokio::try_join!(from_client, from_upstream) {
+            Ok((client, upstream)) => {
+                if let Err(e) = relay(client, upstream, config, &uri).await {
+                    tracing::warn!("websocket error: {}", e)
+                }
+            }
+            Err(e) => tracing::warn!("websocket upgrade error: {}", e),
+        }
+    });
+    Ok(response.map(|_| Full::default()))
+}
+
+type Socket = WebSocketStream<TokioIo<Upgraded>>;
+
+/// Relay messages between the `client` and `upstream` until either closes the connection.
+async fn relay(
+    client: Upgraded,
+    upstream: Upgraded,
+    config: &Config,
+    uri: &log::Upstream,
+) -> tungstenite::Result<()> {
+    let mut client =
+        WebSocketStream::from_raw_socket(TokioIo::new(client), Role::Server, None).await;
+    let mut upstream =
+        WebSocketStream::from_raw_socket(TokioIo::new(upstream), Role::Client, None).await;
+    // Requests which haven't been responded to yet.
+    let pending = Mutex::new(HashMap::<Id, Pending>::new());
+    let subscriptions = Mutex::new(Subscriptions::default());
+
+    let relayed = async {
+        loop {
+            tokio::select! {
+                message = client.next() => match message.transpose()? {
+                    Some(Message::Close(it)) => {
+                        upstream.close(it).await?;
+                        return client.flush().await;
+                    }
+                    // Each side of the proxy answers pings itself.
+                    Some(Message::Ping(_) | Message::Pong(_)) => {}
+                    Some(message) => {
+                        request(config, &pending, message, &mut client, &mut upstream).await?
+                    }
+                    None => return upstream.close(None).await,
+                },
+                message = upstream.next() => match message.transpose()? {
+                    Some(Message::Close(it)) => {
+                        client.close(it).await?;
+                        return upstream.flush().await;
+                    }
+                    Some(Message::Ping(_) | Message::Pong(_)) => {}
+                    Some(message) => {
+                        if let Some(data) = data(&message).filter(|it| recordable(config, it)) {
+                            crate::record_message(
+                                config,
+                                &pending,
+                                Some(&subscriptions),
+                                data,
+                                StatusCode::SWITCHING_PROTOCOLS,
+                                Some(uri),
+                            )
+                        }
+                        client.send(message).await?
+                    }
+                    None => return client.close(None).await,
+                },
+            }
+        }
+    }
+    .await;
+    for it in subscriptions.into_inner().unwrap().drain() {
+        crate::record_subscription(config, &it)
+    }
+    match relayed {
+        Err(tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed) => Ok(()),
+        it => it,
+    }
+}
+
+/// Answer the calls in a `message` from the client which can be, and forward the rest.
+async fn request(
+    config: &Config,
+    pending: &Mutex<HashMap<Id, Pending>>,
+    message: Message,
+    client: &mut Socket,
+    upstream: &mut Socket,
+) -> tungstenite::Result<()> {
+    let text = matches!(message, Message::Text(_));
+    let Some(body) = data(&message) else {
+        return upstream.send(message).await;
+    };
+    let filtered = crate::filter(config, Bytes::copy_from_slice(body));
+    if let Some(forward) = filtered.forward.clone() {
+        if recordable(config, &forward) {
+            pending.lock().unwrap().extend(record::calls(&forward))
+        }
+        let delay = match config.settings.read().unwrap().chaos {
+            true => config.chaos.delay(&forward),
+            false => Duration::ZERO,
+        };
+        tokio::time::sleep(delay).await;
+        upstream.send(like(text, forward)).await?
+    }
+    if let Some(response) = filtered.response() {
+        let response = serde_json::to_vec(&response).expect("responses always serialize");
+        client.send(like(text, Bytes::from(response))).await?
+    }
+    Ok(())
+}
+
+/// The payload of a data message.
+fn data(message: &Message) -> Option<&[u8]> {
+    match message {
+        Message::Text(it) => Some(it.as_bytes()),
+        Message::Binary(it) => Some(it),
+        _ => None,
+    }
+}
+
+/// A text message if `text`, else a binary one.
+fn like(text: bool, data: Bytes) -> Message {
+    match text {
+        true => Message::Text(Utf8Bytes::try_from(data).expect("text and JSON are UTF-8")),
+        false => Message::Binary(data),
+    }
+}
+
+/// Whether a message is small enough to record.
+fn recordable(config: &Config, data: &[u8]) -> bool {
+    let fits = data.len() <= config.max_record_bytes;
+    if !fits {
+        tracing::warn!(
+            "not recording a websocket message bigger than {} bytes",
+            config.max_record_bytes
+        );
+    }
+    fits
+}
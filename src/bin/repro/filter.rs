@@ -0,0 +1,145 @@
+//! jq filters, for normalizing results before they're compared,
+//! like `--compare-with 'del(.[].timestamp) | sort_by(.hash)'`.
+//!
+//! Filters are run with [`jaq_core`], and its standard library.
+//! A filter which outputs several values, or none, outputs them as an array.
+
+use std::{str::FromStr, sync::Arc};
+
+use jaq_core::{
+    compile,
+    data::JustLut,
+    load::{self, Arena, File, Loader},
+    unwrap_valr, Compiler, Ctx, Native, Vars,
+};
+use jaq_json::Val;
+use serde_json::Value;
+
+#[derive(Clone)]
+pub struct Filter(Arc<compile::Filter<Native<JustLut<Val>>>>);
+
+impl FromStr for Filter {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let defs = jaq_core::defs()
+            .chain(jaq_std::defs())
+            .chain(jaq_json::defs());
+        let funs = jaq_core::funs()
+            .chain(jaq_std::funs())
+            .chain(jaq_json::funs());
+        let arena = Arena::default();
+        let modules = Loader::new(defs)
+            .load(&arena, File { code: s, path: () })
+            .map_err(|errors| {
+                let (_, error) = errors.into_iter().next().expect("failures have errors");
+                match error {
+                    load::Error::Io(it) => it.into_iter().map(|(_, it)| it).collect(),
+                    load::Error::Lex(it) => it
+                        .iter()
+                        .map(|(expect, at)| unexpected(expect.as_str(), at))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    load::Error::Parse(it) => it
+                        .iter()
+                        .map(|(expect, at)| unexpected(expect.as_str(), at))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                }
+            })?;
+        let filter = Compiler::default()
+            .with_funs(funs)
+            .compile(modules)
+            .map_err(|errors| {
+                let (_, undefined) = errors.into_iter().next().expect("failures have errors");
+                undefined
+                    .iter()
+                    .map(|(name, it)| match it {
+                        compile::Undefined::Filter(arity) => {
+                            format!("undefined filter `{}/{}`", name, arity)
+                        }
+                        _ => format!("undefined `{}`", name),
+                    })
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            })?;
+        Ok(Self(Arc::new(filter)))
+    }
+}
+
+fn unexpected(expected: &str, at: &str) -> String {
+    match at.is_empty() {
+        true => format!("expected {} at the end", expected),
+        false => format!("expected {} at `{}`", expected, at),
+    }
+}
+
+impl Filter {
+    /// Run the filter on `input`, collecting several outputs, or none, into an array.
+    pub fn apply(&self, input: &Value) -> Result<Value, String> {
+        let input = jaq_json::read::parse_single(input.to_string().as_bytes())
+            .map_err(|e| e.to_string())?;
+        let ctx = Ctx::<JustLut<Val>>::new(&self.0.lut, Vars::new([]));
+        let mut outputs = self
+            .0
+            .id
+            .run((ctx, input))
+            .map(|it| {
+                let it = unwrap_valr(it).map_err(|e| e.to_string())?;
+                serde_json::from_str(&it.to_string()).map_err(|_| format!("{} isn't JSON", it))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        match outputs.len() {
+            1 => Ok(outputs.remove(0)),
+            _ => Ok(Value::Array(outputs)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[track_caller]
+    fn check(filter: &str, input: Value, expected: Value) {
+        let filter = filter.parse::<Filter>().unwrap();
+        assert_eq!(filter.apply(&input).unwrap(), expected)
+    }
+
+    #[test]
+    fn apply() {
+        check(".a[1]", json!({"a": [1, 2]}), json!(2));
+        check(".[]", json!([1, 2]), json!([1, 2]));
+        check("empty", json!(1), json!([]));
+        check(
+            "del(.[].timestamp) | sort_by(.hash)",
+            json!([{"hash": 2, "timestamp": 0}, {"hash": 1, "timestamp": 0}]),
+            json!([{"hash": 1}, {"hash": 2}]),
+        );
+        check(
+            ".a |= . + 1 | .b = \"set\"",
+            json!({"a": 1}),
+            json!({"a": 2, "b": "set"}),
+        );
+        check(
+            "walk(if type == \"number\" then round else . end)",
+            json!({"a": [1.4, "x"], "b": 2.6}),
+            json!({"a": [1, "x"], "b": 3}),
+        );
+        check(
+            "{id: .a, n: (.b | length)}",
+            json!({"a": 1, "b": "four"}),
+            json!({"id": 1, "n": 4}),
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let error = |it: &str| it.parse::<Filter>().err().unwrap();
+        assert!(error(".a |").starts_with("expected"), "{}", error(".a |"));
+        assert_eq!(error("nope"), "undefined filter `nope/0`");
+        let filter = ".a".parse::<Filter>().unwrap();
+        assert!(filter.apply(&json!([1])).is_err());
+    }
+}
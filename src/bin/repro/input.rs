@@ -0,0 +1,241 @@
+//! Reading pairings from recordings, or stdin.
+
+use std::{
+    fmt,
+    fs::{self, File},
+    io::{self, BufWriter, Read as _, Write as _},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context as _};
+use flate2::{read::MultiGzDecoder, write::GzEncoder, Compression};
+use openrpc_types::resolved::ExamplePairing;
+
+/// A recording, read whole.
+pub struct Input {
+    /// [`None`] for stdin.
+    pub path: Option<PathBuf>,
+    gzip: bool,
+    data: Vec<u8>,
+}
+
+/// Read the recordings at `paths`, or stdin if there are none.
+///
+/// Paths may be globs, like `recordings/*.ndjson`.
+/// Directories are read file by file, in order of name,
+/// skipping files which aren't `.ndjson`, `.jsonl` or `.gz`.
+/// Recordings ending in `.gz` are gzipped.
+pub fn read(paths: &[PathBuf]) -> anyhow::Result<Vec<Input>> {
+    if paths.is_empty() {
+        let mut data = vec![];
+        io::stdin()
+            .read_to_end(&mut data)
+            .context("couldn't read stdin")?;
+        return Ok(vec![Input {
+            path: None,
+            gzip: false,
+            data,
+        }]);
+    }
+    let mut out = vec![];
+    for path in paths {
+        for path in expand(path)? {
+            match path.is_dir() {
+                true => {
+                    let mut files = fs::read_dir(&path)
+                        .with_context(|| format!("couldn't read {}", path.display()))?
+                        .map(|it| Ok(it?.path()))
+                        .collect::<io::Result<Vec<_>>>()
+                        .with_context(|| format!("couldn't read {}", path.display()))?;
+                    files.retain(|it| {
+                        it.is_file()
+                            && it.extension().is_some_and(|it| {
+                                ["ndjson", "jsonl", "gz"].iter().any(|e| it == *e)
+                            })
+                    });
+                    files.sort();
+                    for it in files {
+                        out.push(Input::file(it)?)
+                    }
+                }
+                false => out.push(Input::file(path)?),
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// The paths matching `path`, in order, if it's a glob.
+fn expand(path: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let Some(pattern) = path.to_str().filter(|it| it.contains(['*', '?', '['])) else {
+        return Ok(vec![path.into()]);
+    };
+    let paths = glob::glob(pattern)
+        .with_context(|| format!("invalid glob {}", pattern))?
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("couldn't read {}", pattern))?;
+    match paths.is_empty() {
+        true => bail!("no recordings match {}", pattern),
+        false => Ok(paths),
+    }
+}
+
+impl Input {
+    fn file(path: PathBuf) -> anyhow::Result<Self> {
+        let context = || format!("couldn't read {}", path.display());
+        let data = fs::read(&path).with_context(context)?;
+        let gzip = path.extension().is_some_and(|it| it == "gz");
+        let data = match gzip {
+            true => {
+                let mut decoded = vec![];
+                MultiGzDecoder::new(&*data)
+                    .read_to_end(&mut decoded)
+                    .with_context(context)?;
+                decoded
+            }
+            false => data,
+        };
+        Ok(Self {
+            path: Some(path),
+            gzip,
+            data,
+        })
+    }
+    /// The pairings in the recording, with the line each starts on.
+    pub fn pairings(
+        &self,
+    ) -> impl Iterator<Item = (usize, serde_json::Result<ExamplePairing>)> + '_ {
+        let mut stream = serde_json::Deserializer::from_slice(&self.data).into_iter();
+        // Lines are counted up to `counted`.
+        let (mut line, mut counted) = (1, 0);
+        std::iter::from_fn(move || {
+            let start = self.data[stream.byte_offset()..]
+                .iter()
+                .position(|it| !it.is_ascii_whitespace())
+                .map_or(self.data.len(), |it| stream.byte_offset() + it);
+            line += self.data[counted..start]
+                .iter()
+                .filter(|it| **it == b'\n')
+                .count();
+            counted = start;
+            Some((line, stream.next()?))
+        })
+    }
+    /// Replace the recording with `pairings`, one per line, or write them to stdout.
+    pub fn write(&self, pairings: &[ExamplePairing]) -> anyhow::Result<()> {
+        let mut lines = vec![];
+        for it in pairings {
+            serde_json::to_writer(&mut lines, it)?;
+            lines.push(b'\n')
+        }
+        let Some(path) = &self.path else {
+            let mut stdout = io::stdout().lock();
+            stdout.write_all(&lines)?;
+            return Ok(stdout.flush()?);
+        };
+        let context = || format!("couldn't write {}", path.display());
+        let mut file = BufWriter::new(File::create(path).with_context(context)?);
+        match self.gzip {
+            true => {
+                let mut encoder = GzEncoder::new(file, Compression::default());
+                encoder.write_all(&lines).with_context(context)?;
+                file = encoder.finish().with_context(context)?
+            }
+            false => file.write_all(&lines).with_context(context)?,
+        }
+        file.flush().with_context(context)
+    }
+    pub fn name(&self) -> &Path {
+        self.path.as_deref().unwrap_or(Path::new("stdin"))
+    }
+}
+
+/// Where a pairing came from, like `recording.ndjson:12`.
+pub struct Source<'a> {
+    pub input: &'a Input,
+    pub line: usize,
+}
+
+impl fmt::Display for Source<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.input.name().display(), self.line)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAIRING: &str =
+        r#"{"name": "m", "params": [], "result": {"name": "result", "value": 1}}"#;
+
+    fn lines(input: &Input) -> Vec<usize> {
+        input
+            .pairings()
+            .map(|(line, it)| {
+                it.unwrap();
+                line
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pairings() {
+        let input = Input {
+            path: None,
+            gzip: false,
+            data: format!("{PAIRING}\n\n  {PAIRING}\n{{\n}}\n").into_bytes(),
+        };
+        let found = input
+            .pairings()
+            .map(|(line, it)| (line, it.is_ok()))
+            .collect::<Vec<_>>();
+        assert_eq!(found, [(1, true), (3, true), (4, false)]);
+    }
+
+    #[test]
+    fn directories_and_globs() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |it| dir.path().join(it);
+        fs::write(path("b.ndjson"), format!("{PAIRING}\n")).unwrap();
+        fs::write(path("a.jsonl"), format!("{PAIRING}\n{PAIRING}\n")).unwrap();
+        fs::write(path("notes.txt"), "not a recording").unwrap();
+        let mut encoder = GzEncoder::new(
+            File::create(path("c.ndjson.gz")).unwrap(),
+            Compression::default(),
+        );
+        write!(encoder, "{PAIRING}\n{PAIRING}\n{PAIRING}\n").unwrap();
+        encoder.finish().unwrap();
+
+        let inputs = read(&[dir.path().into()]).unwrap();
+        let names = inputs
+            .iter()
+            .map(|it| it.name().file_name().unwrap().to_str().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["a.jsonl", "b.ndjson", "c.ndjson.gz"]);
+        assert_eq!(
+            inputs.iter().map(lines).collect::<Vec<_>>(),
+            [vec![1, 2], vec![1], vec![1, 2, 3]]
+        );
+
+        let inputs = read(&[dir.path().join("*.*json*")]).unwrap();
+        assert_eq!(inputs.len(), 3);
+        let Err(e) = read(&[dir.path().join("*.har")]) else {
+            panic!()
+        };
+        assert!(e.to_string().starts_with("no recordings match"));
+    }
+
+    #[test]
+    fn write_gzip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("recording.ndjson.gz");
+        let mut encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::default());
+        writeln!(encoder, "{PAIRING}").unwrap();
+        encoder.finish().unwrap();
+        let input = Input::file(path.clone()).unwrap();
+        let pairing = input.pairings().next().unwrap().1.unwrap();
+        input.write(&[pairing.clone(), pairing]).unwrap();
+        assert_eq!(lines(&Input::file(path).unwrap()), [1, 2]);
+    }
+}
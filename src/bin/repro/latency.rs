@@ -0,0 +1,55 @@
+//! How long the server took to respond, by method.
+
+use std::{collections::BTreeMap, fmt, time::Duration};
+
+#[derive(Default)]
+pub struct Latencies(BTreeMap<String, Vec<Duration>>);
+
+impl Latencies {
+    pub fn add(&mut self, method: &str, duration: Duration) {
+        self.0
+            .entry(String::from(method))
+            .or_default()
+            .push(duration)
+    }
+}
+
+/// The `q`th quantile of `sorted`, by nearest rank.
+pub fn quantile(sorted: &[Duration], q: f64) -> Duration {
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Formats as a table, with a row for each method and times in milliseconds.
+impl fmt::Display for Latencies {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .0
+            .keys()
+            .map(String::len)
+            .chain(["method".len()])
+            .max()
+            .unwrap_or_default();
+        writeln!(
+            f,
+            "{:width$} {:>7} {:>9} {:>9} {:>9} {:>9}",
+            "method", "calls", "min ms", "median ms", "p95 ms", "max ms"
+        )?;
+        for (method, durations) in &self.0 {
+            let mut sorted = durations.clone();
+            sorted.sort();
+            let ms = |it: Duration| it.as_secs_f64() * 1000.0;
+            writeln!(
+                f,
+                "{:width$} {:>7} {:>9.1} {:>9.1} {:>9.1} {:>9.1}",
+                method,
+                sorted.len(),
+                ms(sorted[0]),
+                ms(quantile(&sorted, 0.5)),
+                ms(quantile(&sorted, 0.95)),
+                ms(sorted[sorted.len() - 1]),
+            )?
+        }
+        Ok(())
+    }
+}
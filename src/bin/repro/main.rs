@@ -0,0 +1,723 @@
+use std::{
+    borrow::Cow,
+    collections::HashSet,
+    fmt,
+    io::{self, IsTerminal as _},
+    num::NonZeroUsize,
+    path::PathBuf,
+    pin::pin,
+    process::ExitCode,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context as _};
+use clap::{ColorChoice, Parser, ValueEnum};
+use futures::{StreamExt as _, TryStreamExt as _};
+use jsonrpcli::{
+    client::ClientError,
+    contract::{Contract, Violation},
+    diff::{self, DifferenceKind},
+    method::MethodGlob,
+    Client, RequestParameters,
+};
+use openrpc_types::{resolved::ExamplePairing, Example, ExampleValue};
+use serde_json::Value;
+
+use crate::{
+    filter::Filter,
+    input::Source,
+    latency::Latencies,
+    pace::Pace,
+    report::{Case, Report},
+    sample::Sample,
+};
+
+mod filter;
+mod input;
+mod latency;
+mod pace;
+mod report;
+mod sample;
+
+#[derive(Parser)]
+struct Args {
+    url: String,
+    /// Also repeat each pairing against this server, reporting where the servers disagree.
+    ///
+    /// May be given more than once.
+    /// Only the first server's results are used for `--update`.
+    #[arg(long, value_name = "URL")]
+    also: Vec<String>,
+    /// Recordings of pairings to repeat, directories of them, or globs like
+    /// `recordings/*.ndjson.gz`, instead of stdin.
+    /// Recordings ending in `.gz` are gzipped.
+    inputs: Vec<PathBuf>,
+    /// How many calls to have in flight at once.
+    ///
+    /// Results are still reported in the order of the input.
+    #[arg(short = 'j', long, value_name = "N", default_value = "1")]
+    concurrency: NonZeroUsize,
+    /// Whether to color the differences in mismatches.
+    #[arg(long, value_name = "WHEN", default_value = "auto")]
+    color: ColorChoice,
+    /// Also write the outcome of each pairing as `junit:PATH`, `tap` (to stdout), `tap:PATH`,
+    /// or `markdown:PATH`.
+    ///
+    /// `tap` can't be written to stdout with `--update` when reading from stdin.
+    #[arg(long, value_name = "FORMAT")]
+    report: Vec<Report>,
+    /// Only repeat pairings for methods matching this glob, e.g `Filecoin.StateGetActor`.
+    #[arg(long, value_name = "GLOB")]
+    only: Vec<MethodGlob>,
+    /// Don't repeat pairings for methods matching this glob.
+    ///
+    /// Takes priority over `--only`.
+    #[arg(long, value_name = "GLOB")]
+    skip: Vec<MethodGlob>,
+    /// Don't compare the value at this JSON Pointer into the results, e.g `/timestamp`.
+    ///
+    /// A `*` segment matches any member or element, e.g `/*/gasUsed`.
+    #[arg(long, value_name = "POINTER", value_parser = pointer)]
+    ignore: Vec<String>,
+    /// Check results against the result schemas of the methods in this OpenRPC document,
+    /// instead of comparing them with the recorded results.
+    ///
+    /// Calls to methods without a result schema pass unless they return an error.
+    #[arg(long, value_name = "PATH")]
+    validate_schema: Option<PathBuf>,
+    /// Rewrite the recordings, or write the pairings to stdout if they came from stdin,
+    /// with the results replaced by the ones just observed.
+    ///
+    /// Everything else about the pairings is preserved,
+    /// and pairings which couldn't be checked, or weren't selected, are written unchanged.
+    #[arg(long)]
+    update: bool,
+    /// How to send the params of each pairing.
+    ///
+    /// A pairing's `x-jsonrpcli-param-structure` field, with one of the same values,
+    /// takes priority.
+    #[arg(long, value_name = "STRUCTURE", default_value = "by-position")]
+    params: ParamStructure,
+    /// Retry calls which fail to reach the server up to this many times,
+    /// waiting after each failure, starting at 200ms and doubling each time.
+    ///
+    /// Calls which still fail are errors, and responses from the server are never retried.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    retries: u32,
+    /// Start at most this many calls per second.
+    #[arg(long, value_name = "N", value_parser = pace::rate, conflicts_with = "delay")]
+    rps: Option<f64>,
+    /// Wait this long between starting calls, e.g `250ms`.
+    #[arg(long, value_name = "DURATION", value_parser = pace::duration)]
+    delay: Option<Duration>,
+    /// Print how long the server took to respond to each method, at the end.
+    ///
+    /// Only calls which returned a result are counted.
+    #[arg(long)]
+    latency: bool,
+    /// Exit successfully if at most this many pairings mismatched or couldn't be checked.
+    ///
+    /// Otherwise, the exit status is 4 if any couldn't be checked, else 3.
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    max_failures: usize,
+    /// Only repeat this many of the selected pairings, like `100`, or this percentage, like `10%`,
+    /// chosen at random.
+    #[arg(long, value_name = "N|PERCENT")]
+    sample: Option<Sample>,
+    /// Choose the same pairings for `--sample` as a previous run with this seed.
+    ///
+    /// Otherwise, the seed is printed at the start.
+    #[arg(long, value_name = "SEED", requires = "sample")]
+    seed: Option<u64>,
+    /// Stop after this many pairings mismatched or couldn't be checked, 1 if not given,
+    /// still reporting the ones which were.
+    #[arg(
+        long,
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "1",
+        conflicts_with = "update"
+    )]
+    fail_fast: Option<NonZeroUsize>,
+    /// Normalize the recorded and observed results with this filter before comparing them,
+    /// e.g `'del(.timestamp) | .items |= sort_by(.hash)'`.
+    ///
+    /// The filter is jq, as implemented by jaq.
+    #[arg(long, value_name = "FILTER", conflicts_with = "validate_schema")]
+    compare_with: Option<Filter>,
+}
+
+/// The exit status if some pairings mismatched.
+const MISMATCHED: u8 = 3;
+/// The exit status if some pairings couldn't be checked, like when the server is unreachable.
+const ERRORED: u8 = 4;
+
+/// The extension field which overrides `--params` for a pairing.
+const PARAM_STRUCTURE_EXTENSION: &str = "x-jsonrpcli-param-structure";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ParamStructure {
+    /// As an array, in order.
+    ByPosition,
+    /// As an object, using the names of the examples, which are required.
+    ByName,
+    /// By name if every example has a name, like pairings recorded by the proxy from calls by name,
+    /// else by position.
+    Auto,
+}
+
+fn pointer(s: &str) -> Result<String, String> {
+    match s.starts_with('/') {
+        true => Ok(String::from(s)),
+        false => Err(String::from("JSON Pointers must start with `/`")),
+    }
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<ExitCode> {
+    let Args {
+        url,
+        also,
+        inputs,
+        concurrency,
+        color,
+        report,
+        only,
+        skip,
+        ignore,
+        validate_schema,
+        update,
+        params,
+        retries,
+        rps,
+        delay,
+        latency,
+        max_failures,
+        sample,
+        seed,
+        fail_fast,
+        compare_with,
+    } = Args::parse();
+    if update && inputs.is_empty() && report.iter().any(|it| matches!(it, Report::Tap(None))) {
+        bail!("--update writes the pairings from stdin to stdout, so give `--report tap` a path, like `tap:results.tap`")
+    }
+    let color = match color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => io::stderr().is_terminal() && std::env::var_os("NO_COLOR").is_none(),
+    };
+    // Keep a connection open for each call in flight, rather than reconnecting for all but one.
+    let agent = ureq::AgentBuilder::new()
+        .max_idle_connections_per_host(concurrency.get())
+        .max_idle_connections(concurrency.get() * (1 + also.len()))
+        .build();
+    let checker = Arc::new(Checker {
+        endpoints: [url]
+            .into_iter()
+            .chain(also)
+            .map(|url| Endpoint {
+                client: Client::with_agent(url.clone(), agent.clone()),
+                url,
+            })
+            .collect(),
+        options: diff::Options {
+            ignore,
+            ..Default::default()
+        },
+        compare_with,
+        contract: validate_schema
+            .map(|it| {
+                std::fs::read_to_string(&it)
+                    .with_context(|| format!("couldn't read {}", it.display()))?
+                    .parse::<Contract>()
+                    .with_context(|| format!("invalid OpenRPC document {}", it.display()))
+            })
+            .transpose()?,
+        update,
+        params,
+        retries,
+    });
+    let wanted = |method: &str| {
+        (only.is_empty() || only.iter().any(|it| it.matches(method)))
+            && !skip.iter().any(|it| it.matches(method))
+    };
+    let pace = rps
+        .map(|it| Duration::from_secs_f64(1.0 / it))
+        .or(delay)
+        .map(Pace::new);
+    let pace = &pace;
+    let inputs = input::read(&inputs)?;
+    let pairings = || {
+        inputs.iter().enumerate().flat_map(|(ix, input)| {
+            input
+                .pairings()
+                .map(move |(line, it)| (ix, Source { input, line }, it))
+        })
+    };
+    // The positions of the sampled pairings, out of all of them.
+    let sampled = sample.map(|sample| {
+        let seed = seed.unwrap_or_else(|| {
+            let seed = fastrand::u64(..);
+            eprintln!("sampling with --seed {}", seed);
+            seed
+        });
+        let candidates = pairings()
+            .enumerate()
+            .filter(|(_, (_, _, it))| it.as_ref().is_ok_and(|it| wanted(&it.name)))
+            .map(|(position, _)| position)
+            .collect::<Vec<_>>();
+        sample
+            .pick(candidates.len(), seed)
+            .into_iter()
+            .map(|it| candidates[it])
+            .collect::<HashSet<_>>()
+    });
+    let wanted = |position: usize, method: &str| {
+        wanted(method) && sampled.as_ref().is_none_or(|it| it.contains(&position))
+    };
+    // Pairings which weren't selected have no outcome, but are still passed through by `--update`.
+    let mut outcomes = pin!(futures::stream::iter(pairings().enumerate())
+        .map(|(position, (ix, source, it))| {
+            let checker = checker.clone();
+            let pairing = it.map(|it| (wanted(position, &it.name), it));
+            async move {
+                let (wanted, mut pairing) =
+                    pairing.with_context(|| format!("invalid pairing at {}", source))?;
+                if !wanted {
+                    return anyhow::Ok((ix, source, pairing, None));
+                }
+                if let Some(it) = pace {
+                    it.wait().await
+                }
+                let (pairing, checked) = tokio::task::spawn_blocking(move || {
+                    let checked = checker.check(&mut pairing);
+                    (pairing, checked)
+                })
+                .await?;
+                Ok((ix, source, pairing, Some(checked)))
+            }
+        })
+        .buffered(concurrency.get()));
+    let mut summary = Summary::default();
+    let mut cases = vec![];
+    let mut updated = inputs.iter().map(|_| vec![]).collect::<Vec<_>>();
+    let mut latencies = Latencies::default();
+    while let Some((ix, source, pairing, checked)) = outcomes.try_next().await? {
+        let method = pairing.name.clone();
+        if update {
+            updated[ix].push(pairing)
+        }
+        let Some(Checked {
+            outcomes,
+            baseline,
+            divergences,
+        }) = checked
+        else {
+            continue;
+        };
+        let several = checker.endpoints.len() > 1;
+        for (endpoint, (outcome, duration)) in checker.endpoints.iter().zip(outcomes) {
+            // Only name the server if there's a choice.
+            let (source, key) = match several {
+                true => (
+                    format!("{} on {}", source, endpoint.url),
+                    format!("{} on {}", method, endpoint.url),
+                ),
+                false => (source.to_string(), method.clone()),
+            };
+            if let Some(it) = duration {
+                latencies.add(&key, it)
+            }
+            match &outcome {
+                Outcome::Mismatched(differences) => {
+                    eprintln!("mismatch for {} at {} (expected -> actual)", method, source);
+                    for it in differences {
+                        eprintln!("  {}", Colored(it, color))
+                    }
+                }
+                Outcome::Invalid(violations) => {
+                    eprintln!("invalid result for {} at {}", method, source);
+                    for it in violations {
+                        eprintln!("  {}", it)
+                    }
+                }
+                Outcome::Errored(e) => eprintln!("error for {} at {}: {}", method, source, e),
+                Outcome::Passed | Outcome::Skipped => {}
+            }
+            summary.add(&outcome);
+            if !report.is_empty() {
+                cases.push(Case {
+                    method: method.clone(),
+                    endpoint: several.then(|| endpoint.url.clone()),
+                    source,
+                    outcome,
+                    duration,
+                })
+            }
+        }
+        if !divergences.is_empty() {
+            summary.diverged += 1
+        }
+        for (ix, differences) in divergences {
+            let (first, other) = (&checker.endpoints[baseline].url, &checker.endpoints[ix].url);
+            eprintln!(
+                "{} and {} disagree on {} at {} ({} -> {})",
+                first, other, method, source, first, other
+            );
+            for it in &differences {
+                eprintln!("  {}", Colored(it, color))
+            }
+        }
+        if let Some(it) = fail_fast {
+            if summary.mismatched + summary.errored >= it.get() {
+                eprintln!("stopping after {} failures", it);
+                break;
+            }
+        }
+    }
+    if update {
+        for (input, pairings) in inputs.iter().zip(updated) {
+            input.write(&pairings)?
+        }
+    }
+    eprintln!("{}", summary);
+    if checker.endpoints.len() > 1 {
+        eprintln!("servers disagreed on {} pairings", summary.diverged)
+    }
+    if latency {
+        eprint!("{}", latencies)
+    }
+    for it in &report {
+        it.write(&cases)?
+    }
+    let failures = summary.mismatched + summary.errored;
+    if failures > 0 && failures <= max_failures {
+        eprintln!("{} failures are within --max-failures", failures)
+    }
+    Ok(ExitCode::from(
+        match (failures <= max_failures, summary.errored) {
+            (true, _) => 0,
+            (false, 0) => MISMATCHED,
+            (false, _) => ERRORED,
+        },
+    ))
+}
+
+/// What happened when a pairing was repeated.
+enum Outcome {
+    Passed,
+    /// The differences from the expected result.
+    Mismatched(Vec<diff::Difference>),
+    /// How the result doesn't match its schema, for `--validate-schema`.
+    Invalid(Vec<Violation>),
+    /// The call failed, or the server returned an error.
+    Errored(anyhow::Error),
+    /// The pairing has no result to compare with, like a notification.
+    Skipped,
+}
+
+#[derive(Default)]
+struct Summary {
+    passed: usize,
+    mismatched: usize,
+    errored: usize,
+    skipped: usize,
+    /// Pairings whose results differed between servers.
+    diverged: usize,
+}
+
+impl Summary {
+    fn add(&mut self, outcome: &Outcome) {
+        *match outcome {
+            Outcome::Passed => &mut self.passed,
+            Outcome::Mismatched(_) | Outcome::Invalid(_) => &mut self.mismatched,
+            Outcome::Errored(_) => &mut self.errored,
+            Outcome::Skipped => &mut self.skipped,
+        } += 1
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self {
+            passed,
+            mismatched,
+            errored,
+            skipped,
+            diverged: _,
+        } = self;
+        write!(
+            f,
+            "{} passed, {} mismatched, {} errored, {} skipped",
+            passed, mismatched, errored, skipped
+        )
+    }
+}
+
+/// Formats a [`diff::Difference`] as a line like `~ /path: left -> right`,
+/// marking removed leaves with `-` and added ones with `+`,
+/// in red, green and yellow if `.1` is set.
+struct Colored<'a>(&'a diff::Difference, bool);
+
+impl fmt::Display for Colored<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self(diff::Difference { path, kind }, color) = *self;
+        let path = match path.is_empty() {
+            true => "(root)",
+            false => path,
+        };
+        let (sign, code) = match kind {
+            DifferenceKind::Removed(_) => ('-', "31"),
+            DifferenceKind::Added(_) => ('+', "32"),
+            DifferenceKind::Changed { .. } => ('~', "33"),
+        };
+        if color {
+            write!(f, "\x1b[{}m", code)?
+        }
+        match kind {
+            DifferenceKind::Removed(it) | DifferenceKind::Added(it) => {
+                write!(f, "{} {}: {}", sign, path, it)?
+            }
+            DifferenceKind::Changed { left, right } => {
+                write!(f, "{} {}: {} -> {}", sign, path, left, right)?
+            }
+        }
+        if color {
+            f.write_str("\x1b[0m")?
+        }
+        Ok(())
+    }
+}
+
+/// A server to repeat pairings against.
+struct Endpoint {
+    url: String,
+    client: Client,
+}
+
+/// What each pairing is checked with.
+struct Checker {
+    /// Each pairing is repeated against every endpoint, in order.
+    endpoints: Vec<Endpoint>,
+    options: diff::Options,
+    compare_with: Option<Filter>,
+    contract: Option<Contract>,
+    /// Replace the results of pairings with the ones observed.
+    update: bool,
+    params: ParamStructure,
+    retries: u32,
+}
+
+/// The outcome of repeating a pairing against each endpoint, in order,
+/// and how long each took, if it returned a result.
+struct Checked {
+    outcomes: Vec<(Outcome, Option<Duration>)>,
+    /// The index of the first endpoint which returned a result.
+    baseline: usize,
+    /// The differences from the baseline's result, by the index of each endpoint which
+    /// returned a different one.
+    divergences: Vec<(usize, Vec<diff::Difference>)>,
+}
+
+/// The first of `results` which is present, and how each other present result differs from it.
+fn diverge(
+    results: &[Option<Cow<'_, Value>>],
+    options: &diff::Options,
+) -> (usize, Vec<(usize, Vec<diff::Difference>)>) {
+    let Some((baseline, Some(first))) = results.iter().enumerate().find(|(_, it)| it.is_some())
+    else {
+        return (0, vec![]);
+    };
+    let divergences = results
+        .iter()
+        .enumerate()
+        .skip(baseline + 1)
+        .filter_map(|(ix, it)| {
+            let differences = diff::diff(first, it.as_ref()?, options);
+            match differences.is_empty() {
+                true => None,
+                false => Some((ix, differences)),
+            }
+        })
+        .collect();
+    (baseline, divergences)
+}
+
+/// How long to wait after the first failure to reach the server.
+const BACKOFF: Duration = Duration::from_millis(200);
+
+impl Checker {
+    /// Call the method of `pairing` on each endpoint, and compare the results with the recorded one,
+    /// and with each other.
+    fn check(&self, pairing: &mut ExamplePairing) -> Checked {
+        let every = |f: &dyn Fn() -> Outcome| Checked {
+            outcomes: self.endpoints.iter().map(|_| (f(), None)).collect(),
+            baseline: 0,
+            divergences: vec![],
+        };
+        let ExamplePairing {
+            name: method_name,
+            params,
+            result:
+                Some(Example {
+                    value: ExampleValue::Embedded(expected_result),
+                    ..
+                }),
+            extensions,
+            ..
+        } = pairing
+        else {
+            return every(&|| Outcome::Skipped);
+        };
+        let structure = match extensions.0.get(PARAM_STRUCTURE_EXTENSION) {
+            Some(it) => match it
+                .as_str()
+                .and_then(|it| ParamStructure::from_str(it, false).ok())
+            {
+                Some(it) => it,
+                None => {
+                    return every(&|| {
+                        Outcome::Errored(anyhow!("invalid {}: {}", PARAM_STRUCTURE_EXTENSION, it))
+                    })
+                }
+            },
+            None => self.params,
+        };
+        let mut outcomes = vec![];
+        let mut results = vec![];
+        for Endpoint { client, .. } in &self.endpoints {
+            let (actual_result, duration) =
+                match self.call(client, method_name.clone(), params, structure) {
+                    Ok(it) => it,
+                    Err(e) => {
+                        outcomes.push((Outcome::Errored(e), None));
+                        results.push(None);
+                        continue;
+                    }
+                };
+            let outcome = match &self.contract {
+                Some(contract) => match contract.result(method_name, &actual_result) {
+                    violations if violations.is_empty() => Outcome::Passed,
+                    violations => Outcome::Invalid(violations),
+                },
+                None => match self
+                    .normalize(expected_result)
+                    .and_then(|expected| Ok((expected, self.normalize(&actual_result)?)))
+                {
+                    Ok((expected, actual)) => match diff::diff(&expected, &actual, &self.options) {
+                        differences if differences.is_empty() => Outcome::Passed,
+                        differences => Outcome::Mismatched(differences),
+                    },
+                    Err(e) => Outcome::Errored(e),
+                },
+            };
+            outcomes.push((outcome, Some(duration)));
+            results.push(Some(actual_result));
+        }
+        // Servers which couldn't be compared are already errors.
+        let normalized = results
+            .iter()
+            .map(|it| self.normalize(it.as_ref()?).ok())
+            .collect::<Vec<_>>();
+        let (baseline, divergences) = diverge(&normalized, &self.options);
+        if let (true, Some(Some(it))) = (self.update, results.into_iter().nth(baseline)) {
+            *expected_result = it
+        }
+        Checked {
+            outcomes,
+            baseline,
+            divergences,
+        }
+    }
+    /// `value`, normalized with the `--compare-with` filter.
+    fn normalize<'a>(&self, value: &'a Value) -> anyhow::Result<Cow<'a, Value>> {
+        match &self.compare_with {
+            Some(filter) => match filter.apply(value) {
+                Ok(it) => Ok(Cow::Owned(it)),
+                Err(e) => bail!("couldn't apply --compare-with: {}", e),
+            },
+            None => Ok(Cow::Borrowed(value)),
+        }
+    }
+    /// The result of calling `method_name`, and how long the last attempt took.
+    fn call(
+        &self,
+        client: &Client,
+        method_name: String,
+        params: &[Example],
+        structure: ParamStructure,
+    ) -> anyhow::Result<(Value, Duration)> {
+        let values = params
+            .iter()
+            .map(|example| match &example.value {
+                ExampleValue::External(_) => {
+                    bail!("unexpected external example value")
+                }
+                ExampleValue::Embedded(it) => Ok(it.clone()),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let named = match structure {
+            // Methods without params are more likely to accept an empty array.
+            ParamStructure::Auto => !params.is_empty() && params.iter().all(|it| it.name.is_some()),
+            _ => params.iter().all(|it| it.name.is_some()),
+        };
+        let params = match (structure, named) {
+            (ParamStructure::ByPosition, _) | (ParamStructure::Auto, false) => {
+                RequestParameters::ByPosition(values)
+            }
+            (ParamStructure::ByName, false) => {
+                bail!("every param needs a name to send them by name")
+            }
+            (ParamStructure::ByName | ParamStructure::Auto, true) => RequestParameters::ByName(
+                params
+                    .iter()
+                    .map(|it| it.name.clone().unwrap_or_default())
+                    .zip(values)
+                    .collect(),
+            ),
+        };
+        let mut attempt = 0;
+        let (response, duration) = loop {
+            let start = Instant::now();
+            match client.call(method_name.clone(), params.clone()) {
+                Err(ClientError::Transport(_)) if attempt < self.retries => {
+                    std::thread::sleep(BACKOFF * 2u32.saturating_pow(attempt));
+                    attempt += 1
+                }
+                Err(e @ ClientError::Transport(_)) if attempt > 0 => {
+                    bail!("{} (after {} retries)", e, attempt)
+                }
+                it => break (it?, start.elapsed()),
+            }
+        };
+        let result = response.result.map_err(|e| anyhow!(e))?;
+        Ok((result, duration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn diverge_from_first_result() {
+        let (one, two) = (json!(1), json!(2));
+        let results = [
+            None,
+            Some(Cow::Borrowed(&one)),
+            None,
+            Some(Cow::Borrowed(&one)),
+            Some(Cow::Borrowed(&two)),
+        ];
+        let (baseline, divergences) = diverge(&results, &diff::Options::default());
+        assert_eq!(baseline, 1);
+        assert_eq!(
+            divergences.iter().map(|(ix, _)| *ix).collect::<Vec<_>>(),
+            [4]
+        );
+        assert_eq!(diverge(&[None, None], &diff::Options::default()).1, []);
+    }
+}
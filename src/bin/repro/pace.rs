@@ -0,0 +1,52 @@
+//! Spacing out calls, for servers which limit their rate.
+
+use std::{sync::Mutex, time::Duration};
+
+use tokio::time::Instant;
+
+/// Lets calls start at most once every `interval`, however many are in flight.
+pub struct Pace {
+    interval: Duration,
+    /// When the next call may start.
+    next: Mutex<Instant>,
+}
+
+impl Pace {
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            next: Mutex::new(Instant::now()),
+        }
+    }
+    /// Wait until it's this call's turn.
+    pub async fn wait(&self) {
+        let at = {
+            let mut next = self.next.lock().unwrap();
+            let at = next.max(Instant::now());
+            *next = at + self.interval;
+            at
+        };
+        tokio::time::sleep_until(at).await
+    }
+}
+
+/// Parse a duration like `100ms` or `2s`.
+pub fn duration(s: &str) -> Result<Duration, String> {
+    let parsed = match (s.strip_suffix("ms"), s.strip_suffix('s')) {
+        (Some(millis), _) => millis.parse().ok().map(Duration::from_millis),
+        (None, Some(secs)) => secs
+            .parse()
+            .ok()
+            .and_then(|it| Duration::try_from_secs_f64(it).ok()),
+        (None, None) => None,
+    };
+    parsed.ok_or_else(|| format!("expected a duration like `100ms` or `2s`, not `{}`", s))
+}
+
+/// Parse a positive rate, in calls per second.
+pub fn rate(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(it) if it > 0.0 && it.is_finite() => Ok(it),
+        _ => Err(format!("expected a positive number, not `{}`", s)),
+    }
+}
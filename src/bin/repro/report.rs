@@ -0,0 +1,352 @@
+//! Writing the outcome of each pairing for CI, like `--report junit:results.xml`.
+
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::PathBuf,
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::{latency::quantile, Colored, Outcome};
+
+#[derive(Debug, Clone)]
+pub enum Report {
+    /// A JUnit XML file.
+    Junit(PathBuf),
+    /// Test Anything Protocol, to the file, or stdout.
+    Tap(Option<PathBuf>),
+    /// A Markdown summary for humans, with the pass rate and timings of each method
+    /// (on each server, if there are several), and every failure.
+    Markdown(PathBuf),
+}
+
+impl FromStr for Report {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (format, path) = match s.split_once(':') {
+            Some((format, path)) => (format, Some(PathBuf::from(path))),
+            None => (s, None),
+        };
+        match (format, path) {
+            ("junit", Some(path)) => Ok(Self::Junit(path)),
+            ("junit", None) => Err(String::from("expected a path, like `junit:results.xml`")),
+            ("tap", path) => Ok(Self::Tap(path)),
+            ("markdown", Some(path)) => Ok(Self::Markdown(path)),
+            ("markdown", None) => Err(String::from("expected a path, like `markdown:results.md`")),
+            _ => Err(format!(
+                "unknown report `{}`, expected `junit:PATH`, `tap`, `tap:PATH` or `markdown:PATH`",
+                s
+            )),
+        }
+    }
+}
+
+/// The outcome of one pairing.
+pub struct Case {
+    pub method: String,
+    /// The URL of the server, if there were several.
+    pub endpoint: Option<String>,
+    /// Where the pairing came from, like `recording.ndjson:12`.
+    pub source: String,
+    pub outcome: Outcome,
+    /// How long the server took, if it returned a result.
+    pub duration: Option<Duration>,
+}
+
+impl Report {
+    pub fn write(&self, cases: &[Case]) -> io::Result<()> {
+        match self {
+            Report::Junit(path) => junit(&mut BufWriter::new(File::create(path)?), cases),
+            Report::Tap(Some(path)) => tap(&mut BufWriter::new(File::create(path)?), cases),
+            Report::Tap(None) => tap(&mut io::stdout().lock(), cases),
+            Report::Markdown(path) => markdown(&mut BufWriter::new(File::create(path)?), cases),
+        }
+    }
+}
+
+/// The JUnit element for a failure, and the lines describing it.
+fn details(outcome: &Outcome) -> Option<(&'static str, Vec<String>)> {
+    match outcome {
+        Outcome::Mismatched(differences) => Some((
+            "failure",
+            differences.iter().map(ToString::to_string).collect(),
+        )),
+        Outcome::Invalid(violations) => Some((
+            "failure",
+            violations.iter().map(ToString::to_string).collect(),
+        )),
+        Outcome::Errored(e) => Some(("error", vec![e.to_string()])),
+        Outcome::Passed | Outcome::Skipped => None,
+    }
+}
+
+fn junit(out: &mut impl Write, cases: &[Case]) -> io::Result<()> {
+    let count = |f: fn(&Outcome) -> bool| cases.iter().filter(|it| f(&it.outcome)).count();
+    writeln!(out, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(out, "<testsuites>")?;
+    writeln!(
+        out,
+        r#"  <testsuite name="repro" tests="{}" failures="{}" errors="{}" skipped="{}">"#,
+        cases.len(),
+        count(|it| matches!(it, Outcome::Mismatched(_) | Outcome::Invalid(_))),
+        count(|it| matches!(it, Outcome::Errored(_))),
+        count(|it| matches!(it, Outcome::Skipped)),
+    )?;
+    for Case {
+        method,
+        endpoint: _,
+        source,
+        outcome,
+        duration,
+    } in cases
+    {
+        write!(
+            out,
+            r#"    <testcase name="{} at {}" classname="{}" time="{:.3}""#,
+            escape(method),
+            escape(source),
+            escape(method),
+            duration.unwrap_or_default().as_secs_f64()
+        )?;
+        match (outcome, details(outcome)) {
+            (_, Some((tag, lines))) => {
+                writeln!(out, ">")?;
+                writeln!(
+                    out,
+                    r#"      <{} message="{}">{}</{}>"#,
+                    tag,
+                    escape(lines.first().map(String::as_str).unwrap_or_default()),
+                    escape(&lines.join("\n")),
+                    tag
+                )?;
+                writeln!(out, "    </testcase>")?
+            }
+            (Outcome::Skipped, None) => {
+                writeln!(out, ">")?;
+                writeln!(out, "      <skipped/>")?;
+                writeln!(out, "    </testcase>")?
+            }
+            (_, None) => writeln!(out, "/>")?,
+        }
+    }
+    writeln!(out, "  </testsuite>")?;
+    writeln!(out, "</testsuites>")?;
+    out.flush()
+}
+
+fn tap(out: &mut impl Write, cases: &[Case]) -> io::Result<()> {
+    writeln!(out, "TAP version 13")?;
+    writeln!(out, "1..{}", cases.len())?;
+    for (
+        ix,
+        Case {
+            method,
+            source,
+            outcome,
+            ..
+        },
+    ) in cases.iter().enumerate()
+    {
+        match (outcome, details(outcome)) {
+            (_, Some((_, lines))) => {
+                writeln!(out, "not ok {} - {} at {}", ix + 1, method, source)?;
+                for it in lines {
+                    writeln!(out, "# {}", it)?
+                }
+            }
+            (Outcome::Skipped, None) => writeln!(
+                out,
+                "ok {} - {} at {} # SKIP no result to compare",
+                ix + 1,
+                method,
+                source
+            )?,
+            (_, None) => writeln!(out, "ok {} - {} at {}", ix + 1, method, source)?,
+        }
+    }
+    out.flush()
+}
+
+fn markdown(out: &mut impl Write, cases: &[Case]) -> io::Result<()> {
+    let mut methods = BTreeMap::<_, Vec<_>>::new();
+    for it in cases {
+        methods
+            .entry((it.endpoint.as_deref(), it.method.as_str()))
+            .or_default()
+            .push(it)
+    }
+    let several = cases.iter().any(|it| it.endpoint.is_some());
+    let count =
+        |cases: &[&Case], f: fn(&Outcome) -> bool| cases.iter().filter(|it| f(&it.outcome)).count();
+    let all = cases.iter().collect::<Vec<_>>();
+    let failed = |it: &Outcome| matches!(it, Outcome::Mismatched(_) | Outcome::Invalid(_));
+    let errored = |it: &Outcome| matches!(it, Outcome::Errored(_));
+    writeln!(out, "# repro results")?;
+    writeln!(out)?;
+    writeln!(
+        out,
+        "{} pairings: {} passed, {} mismatched, {} errored, {} skipped.",
+        cases.len(),
+        count(&all, |it| matches!(it, Outcome::Passed)),
+        count(&all, failed),
+        count(&all, errored),
+        count(&all, |it| matches!(it, Outcome::Skipped)),
+    )?;
+    writeln!(out)?;
+    writeln!(out, "## Methods")?;
+    writeln!(out)?;
+    if several {
+        write!(out, "| endpoint ")?
+    }
+    writeln!(
+        out,
+        "| method | passed | mismatched | errored | skipped | pass rate | median ms | p95 ms | max ms |"
+    )?;
+    if several {
+        write!(out, "|---")?
+    }
+    writeln!(out, "|---|--:|--:|--:|--:|--:|--:|--:|--:|")?;
+    for ((endpoint, method), cases) in &methods {
+        let passed = count(cases, |it| matches!(it, Outcome::Passed));
+        let skipped = count(cases, |it| matches!(it, Outcome::Skipped));
+        let rate = match cases.len() - skipped {
+            0 => String::from("-"),
+            checked => format!("{:.1}%", 100.0 * passed as f64 / checked as f64),
+        };
+        let mut durations = cases
+            .iter()
+            .filter_map(|it| it.duration)
+            .collect::<Vec<_>>();
+        durations.sort();
+        let ms = |it: Duration| format!("{:.1}", it.as_secs_f64() * 1000.0);
+        let (median, p95, max) = match durations.last() {
+            Some(max) => (
+                ms(quantile(&durations, 0.5)),
+                ms(quantile(&durations, 0.95)),
+                ms(*max),
+            ),
+            None => (String::from("-"), String::from("-"), String::from("-")),
+        };
+        if several {
+            write!(out, "| {} ", cell(endpoint.unwrap_or_default()))?
+        }
+        writeln!(
+            out,
+            "| `{}` | {} | {} | {} | {} | {} | {} | {} | {} |",
+            cell(method),
+            passed,
+            count(cases, failed),
+            count(cases, errored),
+            skipped,
+            rate,
+            median,
+            p95,
+            max
+        )?
+    }
+    let failures = cases
+        .iter()
+        .filter(|it| details(&it.outcome).is_some())
+        .collect::<Vec<_>>();
+    if failures.is_empty() {
+        return out.flush();
+    }
+    writeln!(out)?;
+    writeln!(out, "## Failures")?;
+    for Case {
+        method,
+        source,
+        outcome,
+        ..
+    } in failures
+    {
+        let (heading, fence, lines) = match outcome {
+            Outcome::Mismatched(differences) => (
+                "Mismatched (expected -> actual):",
+                "```diff",
+                differences
+                    .iter()
+                    .map(|it| Colored(it, false).to_string())
+                    .collect(),
+            ),
+            Outcome::Invalid(violations) => (
+                "Invalid result:",
+                "```",
+                violations.iter().map(ToString::to_string).collect(),
+            ),
+            Outcome::Errored(e) => ("Errored:", "```", vec![e.to_string()]),
+            Outcome::Passed | Outcome::Skipped => continue,
+        };
+        writeln!(out)?;
+        writeln!(out, "### `{}` at `{}`", method, source)?;
+        writeln!(out)?;
+        writeln!(out, "{}", heading)?;
+        writeln!(out)?;
+        writeln!(out, "{}", fence)?;
+        for it in lines {
+            writeln!(out, "{}", it)?
+        }
+        writeln!(out, "```")?
+    }
+    out.flush()
+}
+
+/// Escape `s` for a cell of a Markdown table.
+fn cell(s: &str) -> String {
+    s.replace('|', "\\|")
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn markdown_by_endpoint() {
+        let case = |endpoint: &str, outcome| Case {
+            method: String::from("m"),
+            endpoint: Some(String::from(endpoint)),
+            source: format!("recording.ndjson:1 on {}", endpoint),
+            outcome,
+            duration: Some(Duration::from_millis(2)),
+        };
+        let cases = [
+            case("http://a", Outcome::Passed),
+            case("http://b", Outcome::Errored(anyhow::anyhow!("refused"))),
+        ];
+        let mut out = vec![];
+        markdown(&mut out, &cases).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(
+            out.contains("| http://a | `m` | 1 | 0 | 0 | 0 | 100.0% |"),
+            "{}",
+            out
+        );
+        assert!(
+            out.contains("| http://b | `m` | 0 | 0 | 1 | 0 | 0.0% |"),
+            "{}",
+            out
+        );
+        assert!(
+            out.contains("### `m` at `recording.ndjson:1 on http://b`"),
+            "{}",
+            out
+        );
+    }
+}
@@ -0,0 +1,43 @@
+//! Repeating a random subset of the pairings, like `--sample 10%`.
+
+use std::{collections::HashSet, str::FromStr};
+
+#[derive(Debug, Clone, Copy)]
+pub enum Sample {
+    Count(usize),
+    Percent(f64),
+}
+
+impl FromStr for Sample {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = match s.strip_suffix('%') {
+            Some(it) => it
+                .parse()
+                .ok()
+                .filter(|it| (0.0..=100.0).contains(it))
+                .map(Self::Percent),
+            None => s.parse().ok().map(Self::Count),
+        };
+        parsed.ok_or_else(|| {
+            format!(
+                "expected a count like `100`, or a percentage like `10%`, not `{}`",
+                s
+            )
+        })
+    }
+}
+
+impl Sample {
+    /// Which of `len` pairings to repeat, the same each time for a given `seed`.
+    pub fn pick(self, len: usize, seed: u64) -> HashSet<usize> {
+        let count = match self {
+            Self::Count(it) => it.min(len),
+            Self::Percent(it) => (len as f64 * it / 100.0).round() as usize,
+        };
+        fastrand::Rng::with_seed(seed)
+            .choose_multiple(0..len, count)
+            .into_iter()
+            .collect()
+    }
+}
@@ -0,0 +1,289 @@
+//! Zero-copy variants of the `JSON-RPC 2.0` types.
+//!
+//! Strings are borrowed from the input where possible,
+//! and structured values are left unparsed as [`RawValue`]s.
+//! Use `into_owned` to convert to the types at the [crate root](crate).
+
+use alloc::borrow::Cow;
+
+use serde::{de::Error as _, Deserialize, Serialize};
+use serde_json::{value::RawValue, Number};
+
+use crate::{deserialize_some, V2};
+
+/// A borrowed [`crate::Request`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Request<'a> {
+    pub jsonrpc: V2,
+    #[serde(borrow)]
+    pub method: Cow<'a, str>,
+    /// Be lenient in what we accept: a `null` is treated as absent.
+    #[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+    pub params: Option<&'a RawValue>,
+    /// [`None`] if this is a notification.
+    #[serde(
+        borrow,
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub id: Option<Id<'a>>,
+}
+
+impl<'a> Request<'a> {
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+    /// See [`crate::Request::deserialize_params`].
+    pub fn deserialize_params<T>(&self) -> serde_json::Result<T>
+    where
+        T: Deserialize<'a>,
+    {
+        serde_json::from_str(self.params.map(RawValue::get).unwrap_or("null"))
+    }
+    /// Parse the parameters, and take ownership of all data.
+    pub fn into_owned(self) -> serde_json::Result<crate::Request> {
+        let Self {
+            jsonrpc,
+            method,
+            params,
+            id,
+        } = self;
+        Ok(crate::Request {
+            jsonrpc,
+            method: method.into_owned(),
+            params: params
+                .map(|it| serde_json::from_str(it.get()))
+                .transpose()?,
+            id: id.map(Id::into_owned),
+        })
+    }
+}
+
+/// A borrowed [`crate::Id`].
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Hash, Default)]
+#[serde(untagged, expecting = "a string, a number, or null")]
+pub enum Id<'a> {
+    String(#[serde(borrow)] Cow<'a, str>),
+    Number(Number),
+    #[default]
+    Null,
+}
+
+impl Id<'_> {
+    pub fn into_owned(self) -> crate::Id {
+        match self {
+            Id::String(it) => crate::Id::String(it.into_owned()),
+            Id::Number(it) => crate::Id::Number(it),
+            Id::Null => crate::Id::Null,
+        }
+    }
+}
+
+/// A borrowed [`crate::Response`].
+#[derive(Debug, Clone)]
+pub struct Response<'a> {
+    pub jsonrpc: V2,
+    pub result: Result<&'a RawValue, Error<'a>>,
+    pub id: Id<'a>,
+}
+
+impl Response<'_> {
+    /// Parse the result, and take ownership of all data.
+    pub fn into_owned(self) -> serde_json::Result<crate::Response> {
+        let Self {
+            jsonrpc,
+            result,
+            id,
+        } = self;
+        Ok(crate::Response {
+            jsonrpc,
+            result: match result {
+                Ok(it) => Ok(serde_json::from_str(it.get())?),
+                Err(it) => Err(it.into_owned()?),
+            },
+            id: id.into_owned(),
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawResponseDeSer<'a> {
+    jsonrpc: V2,
+    #[serde(
+        borrow,
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    result: Option<&'a RawValue>,
+    #[serde(borrow, default, skip_serializing_if = "Option::is_none")]
+    error: Option<Error<'a>>,
+    #[serde(borrow)]
+    id: Id<'a>,
+}
+
+impl Serialize for Response<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let Self {
+            jsonrpc,
+            result,
+            id,
+        } = self.clone();
+        let (result, error) = match result {
+            Ok(it) => (Some(it), None),
+            Err(it) => (None, Some(it)),
+        };
+        RawResponseDeSer {
+            jsonrpc,
+            result,
+            error,
+            id,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for Response<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let RawResponseDeSer {
+            jsonrpc,
+            result,
+            error,
+            id,
+        } = RawResponseDeSer::deserialize(deserializer)?;
+        let result = match (result, error) {
+            (Some(ok), None) => Ok(ok),
+            (None, Some(err)) => Err(err),
+            (Some(_), Some(_)) => {
+                return Err(D::Error::custom(
+                    "only ONE of `error` and `result` may be present",
+                ))
+            }
+            (None, None) => {
+                return Err(D::Error::custom("must have an `error` or `result` member"))
+            }
+        };
+        Ok(Self {
+            jsonrpc,
+            result,
+            id,
+        })
+    }
+}
+
+/// A borrowed [`crate::Error`].
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Error<'a> {
+    pub code: i64,
+    #[serde(borrow)]
+    pub message: Cow<'a, str>,
+    #[serde(
+        borrow,
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub data: Option<&'a RawValue>,
+}
+
+impl Error<'_> {
+    /// Parse the data, and take ownership of all data.
+    pub fn into_owned(self) -> serde_json::Result<crate::Error> {
+        let Self {
+            code,
+            message,
+            data,
+        } = self;
+        Ok(crate::Error {
+            code,
+            message: message.into_owned(),
+            data: data.map(|it| serde_json::from_str(it.get())).transpose()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    #[track_caller]
+    fn round_trip<'a, T>(text: &'a str) -> T
+    where
+        T: Deserialize<'a> + Serialize,
+    {
+        let it = serde_json::from_str::<T>(text).unwrap();
+        assert_eq!(serde_json::to_string(&it).unwrap(), text);
+        it
+    }
+
+    #[test]
+    fn request() {
+        let text = r#"{"jsonrpc":"2.0","method":"a","params":[1, {"b" : 2}],"id":"x"}"#;
+        let request = round_trip::<Request>(text);
+        assert!(matches!(request.method, Cow::Borrowed("a")));
+        assert!(matches!(request.id, Some(Id::String(Cow::Borrowed("x")))));
+        // The raw parameters keep their formatting.
+        assert_eq!(request.params.unwrap().get(), r#"[1, {"b" : 2}]"#);
+        assert_eq!(
+            request.deserialize_params::<(u8, Value)>().unwrap(),
+            (1, json!({"b": 2}))
+        );
+        assert_eq!(
+            request.into_owned().unwrap(),
+            serde_json::from_str::<crate::Request>(text).unwrap()
+        );
+
+        let notification = round_trip::<Request>(r#"{"jsonrpc":"2.0","method":"a"}"#);
+        assert!(notification.is_notification());
+        let null =
+            serde_json::from_str::<Request>(r#"{"jsonrpc":"2.0","method":"a","id":null}"#).unwrap();
+        assert_eq!(null.id, Some(Id::Null));
+        // Escaped strings can't be borrowed.
+        let escaped =
+            serde_json::from_str::<Request>(r#"{"jsonrpc":"2.0","method":"\u0061"}"#).unwrap();
+        assert!(matches!(escaped.method, Cow::Owned(it) if it == "a"));
+    }
+
+    #[test]
+    fn response() {
+        let text = r#"{"jsonrpc":"2.0","result":{"a" : [1]},"id":1}"#;
+        let response = round_trip::<Response>(text);
+        assert_eq!(response.result.as_ref().unwrap().get(), r#"{"a" : [1]}"#);
+        assert_eq!(
+            response.into_owned().unwrap(),
+            serde_json::from_str::<crate::Response>(text).unwrap()
+        );
+
+        let null = round_trip::<Response>(r#"{"jsonrpc":"2.0","result":null,"id":null}"#);
+        assert_eq!(null.result.unwrap().get(), "null");
+
+        let text =
+            r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"no","data":[ 1 ]},"id":"x"}"#;
+        let error = round_trip::<Response>(text).result.unwrap_err();
+        assert_eq!(error.data.unwrap().get(), "[ 1 ]");
+        assert_eq!(
+            error.into_owned().unwrap(),
+            crate::Error {
+                code: -32000,
+                message: "no".into(),
+                data: Some(json!([1]))
+            }
+        );
+
+        for it in [
+            r#"{"jsonrpc":"2.0","id":1}"#,
+            r#"{"jsonrpc":"2.0","result":1,"error":{"code":1,"message":""},"id":1}"#,
+        ] {
+            assert!(serde_json::from_str::<Response>(it).is_err(), "{}", it);
+        }
+    }
+}
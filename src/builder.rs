@@ -0,0 +1,162 @@
+//! Checked construction of [`Request`]s.
+
+use alloc::string::String;
+use core::fmt;
+
+use serde_json::{Map, Value};
+
+use crate::{method::MethodNameError, Id, Request, RequestParameters, V2};
+
+/// Incrementally build a [`Request`].
+///
+/// See [`Request::builder`].
+#[derive(Debug, Clone, Default)]
+#[must_use]
+pub struct RequestBuilder {
+    method: Option<String>,
+    params: Option<RequestParameters>,
+    /// [`None`] until the caller has chosen between a call and a notification.
+    id: Option<Option<Id>>,
+}
+
+impl RequestBuilder {
+    /// Set the name of the method to be invoked.
+    pub fn method(mut self, method: impl Into<String>) -> Self {
+        self.method = Some(method.into());
+        self
+    }
+    /// Pass `params` by-position, replacing any previously set parameters.
+    pub fn positional<T: Into<Value>>(mut self, params: impl IntoIterator<Item = T>) -> Self {
+        self.params = Some(RequestParameters::ByPosition(
+            params.into_iter().map(Into::into).collect(),
+        ));
+        self
+    }
+    /// Pass `params` by-name, replacing any previously set parameters.
+    pub fn named<K: Into<String>, V: Into<Value>>(
+        mut self,
+        params: impl IntoIterator<Item = (K, V)>,
+    ) -> Self {
+        self.params = Some(RequestParameters::ByName(
+            params
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect::<Map<_, _>>(),
+        ));
+        self
+    }
+    /// Set the parameters directly, replacing any previously set parameters.
+    pub fn params(mut self, params: impl Into<Option<RequestParameters>>) -> Self {
+        self.params = params.into();
+        self
+    }
+    /// Make this request a call, which the server will respond to.
+    pub fn id(mut self, id: impl Into<Id>) -> Self {
+        self.id = Some(Some(id.into()));
+        self
+    }
+    /// Make this request a notification, which the server will not respond to.
+    pub fn notification(mut self) -> Self {
+        self.id = Some(None);
+        self
+    }
+    /// Check that the request is complete.
+    ///
+    /// A valid application method must have been given (see [`Request::validate_method`]),
+    /// and exactly one of [`Self::id`] or [`Self::notification`] chosen,
+    /// so that notifications are never sent by accident.
+    pub fn build(self) -> Result<Request, BuildError> {
+        let Self { method, params, id } = self;
+        let request = Request {
+            jsonrpc: V2,
+            method: method.ok_or(BuildError::MissingMethod)?,
+            params,
+            id: id.ok_or(BuildError::MissingId)?,
+        };
+        request.validate_method().map_err(BuildError::Method)?;
+        Ok(request)
+    }
+}
+
+/// An incomplete [`RequestBuilder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuildError {
+    /// [`RequestBuilder::method`] was not called.
+    MissingMethod,
+    /// Neither [`RequestBuilder::id`] nor [`RequestBuilder::notification`] was called.
+    MissingId,
+    /// The method name was empty, or reserved for extensions.
+    Method(MethodNameError),
+}
+
+impl fmt::Display for BuildError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BuildError::MissingMethod => f.write_str("no method was given"),
+            BuildError::MissingId => {
+                f.write_str("no id was given, and the request was not marked as a notification")
+            }
+            BuildError::Method(it) => write!(f, "invalid method: {}", it),
+        }
+    }
+}
+
+impl core::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            BuildError::Method(it) => Some(it),
+            BuildError::MissingMethod | BuildError::MissingId => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn build() {
+        let request = Request::builder()
+            .method("eth_call")
+            .positional([1, 2])
+            .id(1u64)
+            .build()
+            .unwrap();
+        assert_eq!(
+            serde_json::to_value(request).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "eth_call", "params": [1, 2], "id": 1})
+        );
+        let notification = Request::builder()
+            .method("m")
+            .named([("a", 1)])
+            .notification()
+            .build()
+            .unwrap();
+        assert!(notification.is_notification());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            Request::builder().id(1u64).build(),
+            Err(BuildError::MissingMethod)
+        );
+        assert_eq!(
+            Request::builder().method("m").build(),
+            Err(BuildError::MissingId)
+        );
+        assert_eq!(
+            Request::builder().method("").id(1u64).build(),
+            Err(BuildError::Method(MethodNameError::Empty))
+        );
+        assert_eq!(
+            Request::builder()
+                .method("rpc.discover")
+                .notification()
+                .build(),
+            Err(BuildError::Method(MethodNameError::Reserved))
+        );
+    }
+}
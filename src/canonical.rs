@@ -0,0 +1,130 @@
+//! Deterministic serialization, for hashing, signing and diffing.
+//!
+//! Output is compact JSON where:
+//! - Object members are sorted by key, by codepoint.
+//! - Numbers without a fractional part are written as integers (so `1.0` and `1` are identical).
+//!   Other numbers are written in their shortest round-tripping form.
+//!
+//! The same value always produces the same bytes, regardless of platform,
+//! or the order in which members were inserted.
+
+use alloc::{string::String, vec::Vec};
+
+use serde::{ser::SerializeMap as _, ser::SerializeSeq as _, Serialize, Serializer};
+use serde_json::{Number, Value};
+
+/// Serialize `value` canonically, see [the module documentation](self).
+pub fn to_vec<T>(value: &T) -> serde_json::Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    serde_json::to_vec(&Canonical(&serde_json::to_value(value)?))
+}
+
+/// Serialize `value` canonically, see [the module documentation](self).
+pub fn to_string<T>(value: &T) -> serde_json::Result<String>
+where
+    T: Serialize + ?Sized,
+{
+    serde_json::to_string(&Canonical(&serde_json::to_value(value)?))
+}
+
+/// Serialize `value` canonically, see [the module documentation](self).
+#[cfg(feature = "std")]
+pub fn to_writer<W, T>(writer: W, value: &T) -> serde_json::Result<()>
+where
+    W: std::io::Write,
+    T: Serialize + ?Sized,
+{
+    serde_json::to_writer(writer, &Canonical(&serde_json::to_value(value)?))
+}
+
+struct Canonical<'a>(&'a Value);
+
+impl Serialize for Canonical<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self.0 {
+            Value::Object(it) => {
+                let mut members = it.iter().collect::<Vec<_>>();
+                members.sort_unstable_by_key(|(k, _)| *k);
+                let mut map = serializer.serialize_map(Some(members.len()))?;
+                for (k, v) in members {
+                    map.serialize_entry(k, &Canonical(v))?
+                }
+                map.end()
+            }
+            Value::Array(it) => {
+                let mut seq = serializer.serialize_seq(Some(it.len()))?;
+                for v in it {
+                    seq.serialize_element(&Canonical(v))?
+                }
+                seq.end()
+            }
+            Value::Number(it) => serialize_number(it, serializer),
+            other => other.serialize(serializer),
+        }
+    }
+}
+
+/// The largest integer such that it and all smaller integers are exactly representable as an [`f64`].
+const MAX_SAFE_INTEGER: f64 = 9007199254740991.0;
+
+fn serialize_number<S: Serializer>(number: &Number, serializer: S) -> Result<S::Ok, S::Error> {
+    match number.as_f64() {
+        Some(f)
+            if number.is_f64()
+                && (-MAX_SAFE_INTEGER..=MAX_SAFE_INTEGER).contains(&f)
+                && f == (f as i64) as f64 =>
+        {
+            // This also normalizes `-0.0`.
+            serializer.serialize_i64(f as i64)
+        }
+        _ => number.serialize(serializer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Map};
+
+    use super::*;
+
+    #[track_caller]
+    fn check(value: Value, expected: &str) {
+        assert_eq!(to_string(&value).unwrap(), expected);
+        assert_eq!(to_vec(&value).unwrap(), expected.as_bytes());
+    }
+
+    #[test]
+    fn sorted() {
+        check(
+            json!({"b": 1, "a": {"z": [{"y": 0, "x": 0}], "B": null, "é": true}}),
+            r#"{"a":{"B":null,"z":[{"x":0,"y":0}],"é":true},"b":1}"#,
+        );
+        let mut insertion = Map::new();
+        insertion.insert("b".into(), json!(1));
+        insertion.insert("a".into(), json!(2));
+        check(Value::Object(insertion), r#"{"a":2,"b":1}"#);
+    }
+
+    #[test]
+    fn numbers() {
+        check(json!([1.0, -1.0, 0.0, -0.0, 1, -1]), "[1,-1,0,0,1,-1]");
+        check(json!([1.5, -0.25, 1e-7]), "[1.5,-0.25,1e-7]");
+        check(json!(u64::MAX), "18446744073709551615");
+        check(json!(i64::MIN), "-9223372036854775808");
+    }
+
+    #[test]
+    fn safe_integer_boundary() {
+        check(json!(MAX_SAFE_INTEGER), "9007199254740991");
+        check(json!(-MAX_SAFE_INTEGER), "-9007199254740991");
+        // Beyond this, a float may be a rounded integer, so it stays a float.
+        check(json!(9007199254740992.0), "9007199254740992.0");
+        check(json!(-9007199254740992.0), "-9007199254740992.0");
+        check(json!(1e300), "1e+300");
+    }
+}
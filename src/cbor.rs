@@ -0,0 +1,114 @@
+//! Encode and decode messages as [CBOR](https://cbor.io), instead of JSON.
+//!
+//! The raw JSON in [`lazy`](crate::lazy) types is encoded as the value it holds,
+//! but types which borrow raw JSON (like those in [`borrowed`](crate::borrowed)) can't be encoded this way.
+
+use std::io;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+pub type EncodeError = ciborium::ser::Error<io::Error>;
+pub type DecodeError = ciborium::de::Error<io::Error>;
+
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, EncodeError>
+where
+    T: Serialize + ?Sized,
+{
+    let mut buf = vec![];
+    ciborium::into_writer(value, &mut buf)?;
+    Ok(buf)
+}
+
+pub fn from_slice<T>(slice: &[u8]) -> Result<T, DecodeError>
+where
+    T: DeserializeOwned,
+{
+    ciborium::from_reader(slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        lazy::{LazyRequest, LazyResponse},
+        Id, MaybeBatchedRequest, MaybeBatchedResponse, Request, Response,
+    };
+
+    fn round_trip<T>(value: T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        assert_eq!(from_slice::<T>(&to_vec(&value).unwrap()).unwrap(), value)
+    }
+
+    /// Round trip `json` as a `T`, which isn't comparable, returning what it's decoded as.
+    fn round_trip_json<T>(json: Value) -> Value
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let value = serde_json::from_value::<T>(json.clone()).unwrap();
+        let bytes = to_vec(&value).unwrap();
+        let back = from_slice::<T>(&bytes).unwrap();
+        assert_eq!(serde_json::to_value(back).unwrap(), json);
+        from_slice::<Value>(&bytes).unwrap()
+    }
+
+    fn request() -> Value {
+        json!({"jsonrpc": "2.0", "method": "m", "params": [1, "two", {"three": [3.5]}], "id": 1})
+    }
+
+    fn response() -> Value {
+        json!({"jsonrpc": "2.0", "result": {"a": [1, null]}, "id": "x"})
+    }
+
+    fn error() -> Value {
+        json!({"jsonrpc": "2.0", "error": {"code": -32000, "message": "failed", "data": {"why": 1}}, "id": null})
+    }
+
+    #[test]
+    fn id() {
+        round_trip(Id::from_u64(1));
+        round_trip(Id::from("1"));
+        round_trip(Id::Null);
+    }
+
+    #[test]
+    fn request_and_response() {
+        round_trip(serde_json::from_value::<Request>(request()).unwrap());
+        round_trip(
+            serde_json::from_value::<Request>(
+                json!({"jsonrpc": "2.0", "method": "m", "params": {"a": 1}}),
+            )
+            .unwrap(),
+        );
+        round_trip(serde_json::from_value::<Response>(response()).unwrap());
+        round_trip(serde_json::from_value::<Response>(error()).unwrap());
+    }
+
+    #[test]
+    fn batch() {
+        round_trip(
+            serde_json::from_value::<MaybeBatchedRequest>(json!([
+                request(),
+                {"jsonrpc": "2.0", "method": "n"}
+            ]))
+            .unwrap(),
+        );
+        round_trip(
+            serde_json::from_value::<MaybeBatchedResponse>(json!([response(), error()])).unwrap(),
+        );
+    }
+
+    #[test]
+    fn raw() {
+        // The raw values are encoded as structures, not as JSON text.
+        assert_eq!(round_trip_json::<LazyRequest>(request()), request());
+        assert_eq!(round_trip_json::<LazyResponse>(response()), response());
+        assert_eq!(round_trip_json::<LazyResponse>(error()), error());
+    }
+}
@@ -0,0 +1,562 @@
+//! Clients for talking to a `JSON-RPC 2.0` server over HTTP.
+//!
+//! - [`Client`] blocks, and is enabled by the `blocking` feature.
+//! - [`AsyncClient`] is driven by `tokio`, and is enabled by the `async` feature.
+//! - [`WasmClient`] runs in the browser, and is enabled by the `wasm` feature.
+
+use std::fmt;
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{
+    id::{IdGenerator as _, Sequential},
+    Id, MaybeBatchedRequest, MaybeBatchedResponse, Request, RequestParameters, Response, V2,
+};
+
+/// An error from sending a request or receiving its response.
+#[derive(Debug)]
+pub enum ClientError {
+    /// The request could not be sent, or the response could not be read.
+    Transport(Box<dyn std::error::Error + Send + Sync>),
+    /// The response body was not a `JSON-RPC 2.0` response.
+    Deserialize(crate::parse::Error),
+    /// The server responded to a call with an empty body.
+    NoResponse,
+    /// The server responded with an error object.
+    ///
+    /// Only returned by typed calls.
+    Server(crate::Error),
+}
+
+impl fmt::Display for ClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ClientError::Transport(e) => write!(f, "transport error: {}", e),
+            ClientError::Deserialize(e) => write!(f, "invalid response: {}", e),
+            ClientError::NoResponse => f.write_str("no response from server"),
+            ClientError::Server(e) => write!(f, "server returned error {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ClientError::Transport(e) => Some(&**e),
+            ClientError::Deserialize(e) => Some(e),
+            ClientError::Server(e) => Some(e),
+            ClientError::NoResponse => None,
+        }
+    }
+}
+
+fn transport(e: impl std::error::Error + Send + Sync + 'static) -> ClientError {
+    ClientError::Transport(Box::new(e))
+}
+
+fn call_request(id: Id, method: String, params: Option<RequestParameters>) -> Request {
+    Request {
+        jsonrpc: V2,
+        method,
+        params,
+        id: Some(id),
+    }
+}
+
+fn notification_request(method: String, params: Option<RequestParameters>) -> Request {
+    Request {
+        jsonrpc: V2,
+        method,
+        params,
+        id: None,
+    }
+}
+
+/// Servers don't respond to notifications, so an empty body is [`None`].
+#[cfg(any(feature = "blocking", feature = "wasm"))]
+fn parse_body<T: DeserializeOwned>(body: &[u8]) -> Result<Option<T>, ClientError> {
+    match body.iter().all(u8::is_ascii_whitespace) {
+        true => Ok(None),
+        false => crate::parse::from_slice(body)
+            .map(Some)
+            .map_err(ClientError::Deserialize),
+    }
+}
+
+fn expect_single(response: Option<Response>) -> Result<Response, ClientError> {
+    response.ok_or(ClientError::NoResponse)
+}
+
+fn typed<T: DeserializeOwned>(response: Response) -> Result<T, ClientError> {
+    response
+        .deserialize_result()
+        .map_err(|e| ClientError::Deserialize(e.into()))?
+        .map_err(ClientError::Server)
+}
+
+/// A server may reply to a batch with a single error, e.g if the batch was empty.
+fn flatten_batch(response: Option<MaybeBatchedResponse>) -> Vec<Response> {
+    match response {
+        Some(MaybeBatchedResponse::Batch(it)) => it,
+        Some(MaybeBatchedResponse::Single(it)) => vec![it],
+        None => vec![],
+    }
+}
+
+#[cfg(any(feature = "blocking", feature = "wasm"))]
+fn to_body(body: &impl Serialize) -> Vec<u8> {
+    serde_json::to_vec(body).expect("requests always serialize")
+}
+
+#[cfg(feature = "blocking")]
+pub use blocking::Client;
+
+#[cfg(feature = "blocking")]
+mod blocking {
+    use std::io::Read as _;
+
+    use super::*;
+
+    /// A blocking client, which reuses connections to the server.
+    #[derive(Debug)]
+    pub struct Client {
+        url: String,
+        agent: ureq::Agent,
+        ids: Sequential,
+    }
+
+    impl Client {
+        /// Create a client for the server at `url`.
+        pub fn new(url: impl Into<String>) -> Self {
+            Self::with_agent(url, ureq::agent())
+        }
+        /// Create a client for the server at `url`, sending requests with `agent`.
+        pub fn with_agent(url: impl Into<String>, agent: ureq::Agent) -> Self {
+            Self {
+                url: url.into(),
+                agent,
+                ids: Sequential::default(),
+            }
+        }
+        /// Call `method`, with an automatically assigned [`Id`].
+        pub fn call(
+            &self,
+            method: impl Into<String>,
+            params: impl Into<Option<RequestParameters>>,
+        ) -> Result<Response, ClientError> {
+            let request = call_request(self.ids.next_id(), method.into(), params.into());
+            expect_single(self.post(&request)?)
+        }
+        /// Call `method`, deserializing a successful result as a `T`.
+        pub fn call_typed<T: DeserializeOwned>(
+            &self,
+            method: impl Into<String>,
+            params: impl Into<Option<RequestParameters>>,
+        ) -> Result<T, ClientError> {
+            typed(self.call(method, params)?)
+        }
+        /// Send a notification for `method`, which the server won't respond to.
+        pub fn notify(
+            &self,
+            method: impl Into<String>,
+            params: impl Into<Option<RequestParameters>>,
+        ) -> Result<(), ClientError> {
+            let request = notification_request(method.into(), params.into());
+            self.post::<Response>(&request).map(drop)
+        }
+        /// Send `requests` as a single batch.
+        ///
+        /// Notifications in the batch have no corresponding response.
+        pub fn batch(&self, requests: Vec<Request>) -> Result<Vec<Response>, ClientError> {
+            self.post(&requests).map(flatten_batch)
+        }
+        /// Send `request` as-is, returning [`None`] if the server didn't respond.
+        pub fn send(
+            &self,
+            request: &MaybeBatchedRequest,
+        ) -> Result<Option<MaybeBatchedResponse>, ClientError> {
+            self.post(request)
+        }
+        fn post<T: DeserializeOwned>(
+            &self,
+            body: &impl Serialize,
+        ) -> Result<Option<T>, ClientError> {
+            let response = match self
+                .agent
+                .post(&self.url)
+                .set("Content-Type", "application/json")
+                .send_bytes(&to_body(body))
+            {
+                Ok(it) => it,
+                // Servers may report JSON-RPC errors with a non-2xx status.
+                Err(ureq::Error::Status(_, it)) => it,
+                Err(e) => return Err(transport(e)),
+            };
+            let mut body = vec![];
+            response
+                .into_reader()
+                .read_to_end(&mut body)
+                .map_err(transport)?;
+            parse_body(&body)
+        }
+    }
+}
+
+#[cfg(feature = "async")]
+pub use r#async::AsyncClient;
+
+#[cfg(feature = "async")]
+mod r#async {
+    use std::{future::Future, sync::Arc};
+
+    use http::Uri;
+    use http_body_util::{BodyExt as _, Full};
+    use hyper::body::Bytes;
+    use hyper_util::{
+        client::legacy::{connect::HttpConnector, Client},
+        rt::TokioExecutor,
+    };
+
+    use super::*;
+    use crate::http::HttpError;
+
+    /// An asynchronous client, which reuses connections to the server.
+    ///
+    /// Must be used within a `tokio` runtime.
+    ///
+    /// Cheap to clone, sharing connections between clones.
+    #[derive(Debug, Clone)]
+    pub struct AsyncClient {
+        url: Uri,
+        inner: Client<HttpConnector, Full<Bytes>>,
+        ids: Arc<Sequential>,
+    }
+
+    impl AsyncClient {
+        /// Create a client for the server at `url`.
+        pub fn new(url: Uri) -> Self {
+            Self {
+                url,
+                inner: Client::builder(TokioExecutor::new()).build(HttpConnector::new()),
+                ids: Arc::default(),
+            }
+        }
+        /// Call `method`, with an automatically assigned [`Id`].
+        pub async fn call(
+            &self,
+            method: impl Into<String>,
+            params: impl Into<Option<RequestParameters>>,
+        ) -> Result<Response, ClientError> {
+            let request = call_request(self.ids.next_id(), method.into(), params.into());
+            expect_single(self.post(&request).await?)
+        }
+        /// Call `method`, deserializing a successful result as a `T`.
+        pub async fn call_typed<T: DeserializeOwned>(
+            &self,
+            method: impl Into<String>,
+            params: impl Into<Option<RequestParameters>>,
+        ) -> Result<T, ClientError> {
+            typed(self.call(method, params).await?)
+        }
+        /// Send a notification for `method`, which the server won't respond to.
+        pub async fn notify(
+            &self,
+            method: impl Into<String>,
+            params: impl Into<Option<RequestParameters>>,
+        ) -> Result<(), ClientError> {
+            let request = notification_request(method.into(), params.into());
+            self.post::<Response>(&request).await.map(drop)
+        }
+        /// Send `requests` as a single batch.
+        ///
+        /// Notifications in the batch have no corresponding response.
+        pub async fn batch(&self, requests: Vec<Request>) -> Result<Vec<Response>, ClientError> {
+            self.post(&requests).await.map(flatten_batch)
+        }
+        /// Send `request` as-is, returning [`None`] if the server didn't respond.
+        pub async fn send(
+            &self,
+            request: &MaybeBatchedRequest,
+        ) -> Result<Option<MaybeBatchedResponse>, ClientError> {
+            self.post(request).await
+        }
+        /// The returned future doesn't borrow `self`.
+        pub(crate) fn post<T: DeserializeOwned>(
+            &self,
+            body: &impl Serialize,
+        ) -> impl Future<Output = Result<Option<T>, ClientError>> + Send + 'static {
+            let response = self.inner.request(
+                crate::http::to_http_request(body, self.url.clone())
+                    .map(|it| Full::new(Bytes::from(it))),
+            );
+            async move {
+                let (parts, body) = response.await.map_err(transport)?.into_parts();
+                let body = body.collect().await.map_err(transport)?.to_bytes();
+                crate::http::from_http_response(&parts, &body).map_err(|e| match e {
+                    HttpError::Json(e) => ClientError::Deserialize(e),
+                    e => transport(e),
+                })
+            }
+        }
+    }
+}
+
+#[cfg(feature = "wasm")]
+pub use wasm::WasmClient;
+
+#[cfg(feature = "wasm")]
+mod wasm {
+    use std::sync::Arc;
+
+    use super::*;
+
+    /// A client for use in the browser, using the `fetch` API.
+    #[derive(Debug, Clone)]
+    pub struct WasmClient {
+        url: String,
+        ids: Arc<Sequential>,
+    }
+
+    impl WasmClient {
+        /// Create a client for the server at `url`.
+        pub fn new(url: impl Into<String>) -> Self {
+            Self {
+                url: url.into(),
+                ids: Arc::default(),
+            }
+        }
+        /// Call `method`, with an automatically assigned [`Id`].
+        pub async fn call(
+            &self,
+            method: impl Into<String>,
+            params: impl Into<Option<RequestParameters>>,
+        ) -> Result<Response, ClientError> {
+            let request = call_request(self.ids.next_id(), method.into(), params.into());
+            expect_single(self.post(&request).await?)
+        }
+        /// Call `method`, deserializing a successful result as a `T`.
+        pub async fn call_typed<T: DeserializeOwned>(
+            &self,
+            method: impl Into<String>,
+            params: impl Into<Option<RequestParameters>>,
+        ) -> Result<T, ClientError> {
+            typed(self.call(method, params).await?)
+        }
+        /// Send a notification for `method`, which the server won't respond to.
+        pub async fn notify(
+            &self,
+            method: impl Into<String>,
+            params: impl Into<Option<RequestParameters>>,
+        ) -> Result<(), ClientError> {
+            let request = notification_request(method.into(), params.into());
+            self.post::<Response>(&request).await.map(drop)
+        }
+        /// Send `requests` as a single batch.
+        ///
+        /// Notifications in the batch have no corresponding response.
+        pub async fn batch(&self, requests: Vec<Request>) -> Result<Vec<Response>, ClientError> {
+            self.post(&requests).await.map(flatten_batch)
+        }
+        /// Send `request` as-is, returning [`None`] if the server didn't respond.
+        pub async fn send(
+            &self,
+            request: &MaybeBatchedRequest,
+        ) -> Result<Option<MaybeBatchedResponse>, ClientError> {
+            self.post(request).await
+        }
+        async fn post<T: DeserializeOwned>(
+            &self,
+            body: &impl Serialize,
+        ) -> Result<Option<T>, ClientError> {
+            let body = String::from_utf8(to_body(body)).expect("JSON is always UTF-8");
+            // Like the other clients, non-2xx responses are still read as bodies.
+            let response = gloo_net::http::Request::post(&self.url)
+                .header("Content-Type", "application/json")
+                .body(body)
+                .map_err(transport)?
+                .send()
+                .await
+                .map_err(transport)?;
+            parse_body(&response.binary().await.map_err(transport)?)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead as _, BufReader, Read as _, Write as _},
+        net::TcpListener,
+        thread,
+    };
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::router::Router;
+
+    /// Serve an echo server on a local port, returning its URL.
+    ///
+    /// `fail` returns an error, over a `500` at `/status`,
+    /// and everything at `/garbage` returns a body which isn't JSON.
+    fn serve() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let router = Router::new()
+            .method("echo", |it: Value| async move { Ok(it) })
+            .method("fail", |_: Value| async {
+                Err::<Value, _>(crate::Error::new(-32000, "failed", None))
+            });
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .build()
+            .unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = BufReader::new(stream.unwrap());
+                let (mut path, mut length) = (String::new(), 0);
+                loop {
+                    let mut line = String::new();
+                    stream.read_line(&mut line).unwrap();
+                    let line = line.trim_end();
+                    if line.is_empty() {
+                        break;
+                    }
+                    if let Some(it) = line.strip_prefix("POST ") {
+                        path = it.split(' ').next().unwrap().into()
+                    }
+                    if let Some((name, value)) = line.split_once(": ") {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            length = value.parse().unwrap()
+                        }
+                    }
+                }
+                let mut body = vec![0; length];
+                stream.read_exact(&mut body).unwrap();
+                let response = runtime.block_on(router.handle_slice(&body));
+                let (status, body) = match (path.as_str(), response) {
+                    ("/garbage", _) => (200, b"garbage".to_vec()),
+                    ("/status", it) => (500, serde_json::to_vec(&it).unwrap()),
+                    (_, Some(it)) => (200, serde_json::to_vec(&it).unwrap()),
+                    (_, None) => (204, vec![]),
+                };
+                write!(
+                    stream.get_mut(),
+                    "HTTP/1.1 {} _\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                )
+                .unwrap();
+                stream.get_mut().write_all(&body).unwrap();
+            }
+        });
+        url
+    }
+
+    fn params() -> RequestParameters {
+        RequestParameters::ByPosition(vec![json!(1), json!("two")])
+    }
+
+    fn batch() -> Vec<Request> {
+        vec![
+            call_request(Id::from_u64(7), String::from("echo"), Some(params())),
+            notification_request(String::from("echo"), None),
+            call_request(Id::from_u64(8), String::from("missing"), None),
+        ]
+    }
+
+    #[track_caller]
+    fn check_batch(responses: Vec<Response>) {
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0].id, Id::from_u64(7));
+        assert_eq!(responses[0].result, Ok(json!([1, "two"])));
+        assert_eq!(
+            responses[1].result.as_ref().unwrap_err().code,
+            crate::Error::METHOD_NOT_FOUND
+        );
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn blocking() {
+        let url = serve();
+        let client = Client::new(&url);
+        let response = client.call("echo", params()).unwrap();
+        assert_eq!(response.result, Ok(json!([1, "two"])));
+        // Ids are assigned in order.
+        assert_eq!(
+            client.call("echo", None).unwrap().id,
+            Id::from_u64(response.id.as_u64().unwrap() + 1)
+        );
+        assert_eq!(
+            client
+                .call_typed::<(u32, String)>("echo", params())
+                .unwrap(),
+            (1, String::from("two"))
+        );
+        let Err(ClientError::Server(e)) = client.call_typed::<Value>("fail", None) else {
+            panic!()
+        };
+        assert_eq!(e.code, -32000);
+        client.notify("echo", params()).unwrap();
+        check_batch(client.batch(batch()).unwrap());
+
+        let response = Client::new(format!("{}/status", url))
+            .call("fail", None)
+            .unwrap();
+        assert_eq!(response.result.unwrap_err().message, "failed");
+        assert!(matches!(
+            Client::new(format!("{}/garbage", url)).call("echo", None),
+            Err(ClientError::Deserialize(_))
+        ));
+        assert!(matches!(
+            Client::new(unreachable()).call("echo", None),
+            Err(ClientError::Transport(_))
+        ));
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn r#async() {
+        let url = serve();
+        let client = AsyncClient::new(url.parse().unwrap());
+        let response = client.call("echo", params()).await.unwrap();
+        assert_eq!(response.result, Ok(json!([1, "two"])));
+        assert_eq!(
+            client
+                .call_typed::<(u32, String)>("echo", params())
+                .await
+                .unwrap(),
+            (1, String::from("two"))
+        );
+        let Err(ClientError::Server(e)) = client.call_typed::<Value>("fail", None).await else {
+            panic!()
+        };
+        assert_eq!(e.code, -32000);
+        client.notify("echo", params()).await.unwrap();
+        check_batch(client.batch(batch()).await.unwrap());
+
+        let response = AsyncClient::new(format!("{}/status", url).parse().unwrap())
+            .call("fail", None)
+            .await
+            .unwrap();
+        assert_eq!(response.result.unwrap_err().message, "failed");
+        assert!(matches!(
+            AsyncClient::new(format!("{}/garbage", url).parse().unwrap())
+                .call("echo", None)
+                .await,
+            Err(ClientError::Deserialize(_))
+        ));
+        assert!(matches!(
+            AsyncClient::new(unreachable().parse().unwrap())
+                .call("echo", None)
+                .await,
+            Err(ClientError::Transport(_))
+        ));
+    }
+
+    /// The URL of a port which nothing is listening on.
+    fn unreachable() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    }
+}
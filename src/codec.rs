@@ -0,0 +1,263 @@
+//! [`tokio_util::codec`] implementations, for framing messages over byte streams.
+//!
+//! Use them with [`FramedRead`](tokio_util::codec::FramedRead) and [`FramedWrite`](tokio_util::codec::FramedWrite)
+//! to send and receive messages over any `tokio` `AsyncRead` or `AsyncWrite`,
+//! like stdio or a TCP socket.
+//!
+//! Decoded messages are deserialized as a `T`, and any [`Serialize`] type may be encoded.
+
+use std::{fmt, io, marker::PhantomData};
+
+use bytes::{Buf as _, BufMut as _, BytesMut};
+use serde::{de::DeserializeOwned, Serialize};
+use tokio_util::codec::{Decoder, Encoder};
+
+/// An error from framing a message.
+#[derive(Debug)]
+pub enum CodecError {
+    /// The underlying stream failed.
+    Io(io::Error),
+    /// A message could not be (de)serialized.
+    Json(serde_json::Error),
+    /// The headers of a frame were malformed.
+    Header(String),
+    /// A frame was longer than the configured maximum, in bytes.
+    TooLong(usize),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Io(e) => write!(f, "io error: {}", e),
+            CodecError::Json(e) => write!(f, "invalid message: {}", e),
+            CodecError::Header(s) => write!(f, "invalid header: {}", s),
+            CodecError::TooLong(max) => write!(f, "frame is longer than {} bytes", max),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CodecError::Io(e) => Some(e),
+            CodecError::Json(e) => Some(e),
+            CodecError::Header(_) | CodecError::TooLong(_) => None,
+        }
+    }
+}
+
+impl From<io::Error> for CodecError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+/// Frames messages with a `Content-Length` header, as used by the
+/// [Language Server Protocol](https://microsoft.github.io/language-server-protocol/specifications/base/0.9/specification/#headerPart).
+///
+/// ```text
+/// Content-Length: 52\r\n
+/// \r\n
+/// {"jsonrpc":"2.0","method":"initialized","params":{}}
+/// ```
+///
+/// Other headers (like `Content-Type`) are ignored when decoding, and never written when encoding.
+pub struct ContentLengthCodec<T> {
+    /// The length of the body of the frame being decoded, once its headers have been read.
+    content_length: Option<usize>,
+    message: PhantomData<fn() -> T>,
+}
+
+impl<T> ContentLengthCodec<T> {
+    pub fn new() -> Self {
+        Self {
+            content_length: None,
+            message: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for ContentLengthCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for ContentLengthCodec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ContentLengthCodec")
+            .field("content_length", &self.content_length)
+            .finish()
+    }
+}
+
+/// Parse the header part of a frame, which excludes the final `\r\n\r\n`.
+fn content_length(headers: &[u8]) -> Result<usize, CodecError> {
+    let headers = std::str::from_utf8(headers)
+        .map_err(|_| CodecError::Header(String::from("headers are not ASCII")))?;
+    let mut content_length = None;
+    for header in headers.split("\r\n") {
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(CodecError::Header(format!(
+                "expected `name: value`, not `{}`",
+                header
+            )));
+        };
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            let value = value.trim();
+            content_length = Some(value.parse().map_err(|_| {
+                CodecError::Header(format!("`{}` is not a valid Content-Length", value))
+            })?);
+        }
+    }
+    content_length.ok_or_else(|| CodecError::Header(String::from("missing Content-Length")))
+}
+
+impl<T: DeserializeOwned> Decoder for ContentLengthCodec<T> {
+    type Item = T;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let len = match self.content_length {
+            Some(it) => it,
+            None => {
+                let Some(end) = src.windows(4).position(|it| it == b"\r\n\r\n") else {
+                    return Ok(None);
+                };
+                let len = content_length(&src[..end])?;
+                src.advance(end + 4);
+                self.content_length = Some(len);
+                len
+            }
+        };
+        if src.len() < len {
+            src.reserve(len - src.len());
+            return Ok(None);
+        }
+        self.content_length = None;
+        let body = src.split_to(len);
+        serde_json::from_slice(&body)
+            .map(Some)
+            .map_err(CodecError::Json)
+    }
+}
+
+impl<T, E: Serialize> Encoder<E> for ContentLengthCodec<T> {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: E, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let body = serde_json::to_vec(&item).map_err(CodecError::Json)?;
+        let header = format!("Content-Length: {}\r\n\r\n", body.len());
+        dst.reserve(header.len() + body.len());
+        dst.put_slice(header.as_bytes());
+        dst.put_slice(&body);
+        Ok(())
+    }
+}
+
+/// Frames messages by newlines, as in [NDJSON](https://github.com/ndjson/ndjson-spec).
+///
+/// Blank lines are skipped when decoding.
+pub struct NdjsonCodec<T> {
+    max_length: usize,
+    /// How much of the buffer has already been searched for a newline.
+    searched: usize,
+    message: PhantomData<fn() -> T>,
+}
+
+impl<T> NdjsonCodec<T> {
+    /// A codec which accepts lines of any length.
+    pub fn new() -> Self {
+        Self::with_max_length(usize::MAX)
+    }
+    /// A codec which fails to decode lines longer than `max_length` bytes,
+    /// instead of buffering them indefinitely.
+    pub fn with_max_length(max_length: usize) -> Self {
+        Self {
+            max_length,
+            searched: 0,
+            message: PhantomData,
+        }
+    }
+    pub fn max_length(&self) -> usize {
+        self.max_length
+    }
+}
+
+impl<T> Default for NdjsonCodec<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> fmt::Debug for NdjsonCodec<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("NdjsonCodec")
+            .field("max_length", &self.max_length)
+            .field("searched", &self.searched)
+            .finish()
+    }
+}
+
+impl<T: DeserializeOwned> NdjsonCodec<T> {
+    fn parse(&self, line: &[u8]) -> Result<Option<T>, CodecError> {
+        if line.len() > self.max_length {
+            return Err(CodecError::TooLong(self.max_length));
+        }
+        match line.iter().all(u8::is_ascii_whitespace) {
+            true => Ok(None),
+            false => serde_json::from_slice(line)
+                .map(Some)
+                .map_err(CodecError::Json),
+        }
+    }
+}
+
+impl<T: DeserializeOwned> Decoder for NdjsonCodec<T> {
+    type Item = T;
+    type Error = CodecError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        loop {
+            let Some(newline) = src[self.searched..].iter().position(|it| *it == b'\n') else {
+                self.searched = src.len();
+                return match src.len() > self.max_length {
+                    true => Err(CodecError::TooLong(self.max_length)),
+                    false => Ok(None),
+                };
+            };
+            let line = src.split_to(self.searched + newline + 1);
+            self.searched = 0;
+            if let Some(it) = self.parse(&line[..line.len() - 1])? {
+                return Ok(Some(it));
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self.decode(src)? {
+            Some(it) => Ok(Some(it)),
+            None => {
+                self.searched = 0;
+                let line = src.split();
+                self.parse(&line)
+            }
+        }
+    }
+}
+
+/// Messages are written compactly, so never contain a newline.
+impl<T, E: Serialize> Encoder<E> for NdjsonCodec<T> {
+    type Error = CodecError;
+
+    fn encode(&mut self, item: E, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let line = serde_json::to_vec(&item).map_err(CodecError::Json)?;
+        if line.len() > self.max_length {
+            return Err(CodecError::TooLong(self.max_length));
+        }
+        dst.reserve(line.len() + 1);
+        dst.put_slice(&line);
+        dst.put_u8(b'\n');
+        Ok(())
+    }
+}
@@ -0,0 +1,415 @@
+//! Checking calls against the methods declared in an [OpenRPC](https://open-rpc.org) document.
+//!
+//! Schemas are checked with [`jsonschema`], as draft 7 (which OpenRPC uses), including `format`s.
+//! `$ref`s must point into the document, like `#/components/schemas/Block`.
+//!
+//! ```
+//! use jsonrpcli::{contract::Contract, Request};
+//! use serde_json::json;
+//!
+//! let contract = Contract::new(&json!({
+//!     "methods": [{
+//!         "name": "add",
+//!         "params": [{"name": "a", "required": true, "schema": {"type": "integer"}}],
+//!         "result": {"name": "sum", "schema": {"type": "integer"}}
+//!     }]
+//! }))
+//! .unwrap();
+//!
+//! let request = serde_json::from_value::<Request>(json!({
+//!     "jsonrpc": "2.0", "method": "add", "params": ["one"], "id": 1
+//! }))
+//! .unwrap();
+//! assert_eq!(contract.params(&request)[0].to_string(), r#"`/params/0`: "one" is not of type "integer""#);
+//! assert!(contract.result("add", &json!(1)).is_empty());
+//! ```
+
+use std::{collections::HashMap, fmt, str::FromStr};
+
+use jsonschema::{Draft, Registry, Validator};
+use serde_json::Value;
+
+use crate::{Request, RequestParameters};
+
+/// Where the document is registered, for resolving `$ref`s into it.
+const BASE: &str = "urn:jsonrpcli:openrpc";
+
+/// Where in a call a value didn't match its schema, and why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// A JSON Pointer into `{"params": ..., "result": ...}`, like `/params/0`.
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.path.is_empty() {
+            true => f.write_str(&self.message),
+            false => write!(f, "`{}`: {}", self.path, self.message),
+        }
+    }
+}
+
+/// An OpenRPC document which couldn't be loaded.
+#[derive(Debug, Clone)]
+pub struct ContractError(String);
+
+impl fmt::Display for ContractError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for ContractError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Structure {
+    ByName,
+    ByPosition,
+    Either,
+}
+
+struct Param {
+    name: String,
+    required: bool,
+    schema: Validator,
+}
+
+struct Method {
+    params: Vec<Param>,
+    structure: Structure,
+    /// If the method declares one.
+    result: Option<Validator>,
+}
+
+/// The methods of an OpenRPC document.
+pub struct Contract {
+    methods: HashMap<String, Method>,
+}
+
+impl FromStr for Contract {
+    type Err = ContractError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let document =
+            serde_json::from_str(s).map_err(|e| ContractError(format!("invalid JSON: {}", e)))?;
+        Self::new(&document)
+    }
+}
+
+impl Contract {
+    pub fn new(document: &Value) -> Result<Self, ContractError> {
+        let registry = Registry::new()
+            .draft(Draft::Draft7)
+            .add(BASE, document)
+            .and_then(|it| it.prepare())
+            .map_err(|e| ContractError(e.to_string()))?;
+        let loader = Loader {
+            document,
+            registry: &registry,
+        };
+        let methods = document
+            .get("methods")
+            .and_then(Value::as_array)
+            .ok_or_else(|| ContractError(String::from("no `methods`")))?
+            .iter()
+            .enumerate()
+            .map(|(ix, _)| loader.method(&format!("/methods/{}", ix)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { methods })
+    }
+    /// Check the params of a call against its method.
+    ///
+    /// Calls to methods which aren't in the document are a violation.
+    pub fn params(&self, request: &Request) -> Vec<Violation> {
+        let Some(method) = self.methods.get(&request.method) else {
+            return vec![Violation {
+                path: String::new(),
+                message: format!("method `{}` isn't in the document", request.method),
+            }];
+        };
+        let mut out = vec![];
+        match (&request.params, method.structure) {
+            (Some(RequestParameters::ByPosition(_)), Structure::ByName) => out.push(Violation {
+                path: String::from("/params"),
+                message: String::from("expected params by name"),
+            }),
+            (Some(RequestParameters::ByName(_)), Structure::ByPosition) => out.push(Violation {
+                path: String::from("/params"),
+                message: String::from("expected params by position"),
+            }),
+            (None, _) => {
+                out.extend(
+                    method
+                        .params
+                        .iter()
+                        .filter(|it| it.required)
+                        .map(|it| Violation {
+                            path: String::from("/params"),
+                            message: format!("missing required param `{}`", it.name),
+                        }),
+                )
+            }
+            (Some(RequestParameters::ByPosition(values)), _) => {
+                for (ix, param) in method.params.iter().enumerate() {
+                    let path = format!("/params/{}", ix);
+                    match values.get(ix) {
+                        Some(value) => check(&param.schema, value, &path, &mut out),
+                        None if param.required => out.push(Violation {
+                            path,
+                            message: format!("missing required param `{}`", param.name),
+                        }),
+                        None => {}
+                    }
+                }
+                if values.len() > method.params.len() {
+                    out.push(Violation {
+                        path: String::from("/params"),
+                        message: format!(
+                            "expected at most {} params, not {}",
+                            method.params.len(),
+                            values.len()
+                        ),
+                    })
+                }
+            }
+            (Some(RequestParameters::ByName(values)), _) => {
+                for param in &method.params {
+                    let path = format!("/params/{}", escape(&param.name));
+                    match values.get(&param.name) {
+                        Some(value) => check(&param.schema, value, &path, &mut out),
+                        None if param.required => out.push(Violation {
+                            path,
+                            message: format!("missing required param `{}`", param.name),
+                        }),
+                        None => {}
+                    }
+                }
+                for name in values.keys() {
+                    if !method.params.iter().any(|it| it.name == *name) {
+                        out.push(Violation {
+                            path: format!("/params/{}", escape(name)),
+                            message: format!("unknown param `{}`", name),
+                        })
+                    }
+                }
+            }
+        }
+        out
+    }
+    /// Check the result of a call to `method` against the method's result schema.
+    pub fn result(&self, method: &str, result: &Value) -> Vec<Violation> {
+        let mut out = vec![];
+        if let Some(schema) = self.methods.get(method).and_then(|it| it.result.as_ref()) {
+            check(schema, result, "/result", &mut out)
+        }
+        out
+    }
+}
+
+fn check(schema: &Validator, value: &Value, path: &str, out: &mut Vec<Violation>) {
+    out.extend(schema.iter_errors(value).map(|it| Violation {
+        path: format!("{}{}", path, it.instance_path()),
+        message: it.to_string(),
+    }))
+}
+
+struct Loader<'a> {
+    document: &'a Value,
+    registry: &'a Registry<'a>,
+}
+
+impl Loader<'_> {
+    /// Follow `$ref`s from the value at `pointer`, returning where they lead.
+    fn resolve(&self, mut pointer: String) -> Result<(String, &Value), ContractError> {
+        for _ in 0..64 {
+            let it = self
+                .document
+                .pointer(&pointer)
+                .ok_or_else(|| ContractError(format!("nothing at `{}`", pointer)))?;
+            match it.get("$ref").and_then(Value::as_str) {
+                Some(reference) => {
+                    pointer = String::from(reference.strip_prefix('#').ok_or_else(|| {
+                        ContractError(format!("`$ref` `{}` isn't into the document", reference))
+                    })?)
+                }
+                None => return Ok((pointer, it)),
+            }
+        }
+        Err(ContractError(format!(
+            "`$ref`s nest too deeply at `{}`",
+            pointer
+        )))
+    }
+    /// The schema at `pointer`, which `$ref`s are resolved relative to.
+    fn schema(&self, pointer: &str) -> Result<Validator, ContractError> {
+        jsonschema::options()
+            .with_draft(Draft::Draft7)
+            .should_validate_formats(true)
+            .with_registry(self.registry)
+            .build(&serde_json::json!({ "$ref": format!("{}#{}", BASE, fragment(pointer)) }))
+            .map_err(|e| ContractError(format!("invalid schema at `{}`: {}", pointer, e)))
+    }
+    /// The schema of the content descriptor at `pointer`, which may be omitted.
+    fn content(&self, pointer: &str) -> Result<Validator, ContractError> {
+        let (pointer, it) = self.resolve(String::from(pointer))?;
+        match it.get("schema") {
+            Some(_) => self.schema(&format!("{}/schema", pointer)),
+            None => Ok(jsonschema::validator_for(&Value::Bool(true)).expect("`true` is a schema")),
+        }
+    }
+    fn method(&self, pointer: &str) -> Result<(String, Method), ContractError> {
+        let (pointer, it) = self.resolve(String::from(pointer))?;
+        let name = it
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| ContractError(format!("the method at `{}` has no `name`", pointer)))?;
+        let params = match it.get("params") {
+            Some(Value::Array(params)) => (0..params.len())
+                .map(|ix| {
+                    let (at, it) = self.resolve(format!("{}/params/{}", pointer, ix))?;
+                    Ok(Param {
+                        name: String::from(it.get("name").and_then(Value::as_str).ok_or_else(
+                            || ContractError(format!("a param of `{}` has no `name`", name)),
+                        )?),
+                        required: it.get("required").and_then(Value::as_bool) == Some(true),
+                        schema: self.content(&at)?,
+                    })
+                })
+                .collect::<Result<_, ContractError>>()?,
+            _ => vec![],
+        };
+        let result = match it.get("result") {
+            Some(_) => Some(self.content(&format!("{}/result", pointer))?),
+            None => None,
+        };
+        let structure = match it.get("paramStructure").and_then(Value::as_str) {
+            Some("by-name") => Structure::ByName,
+            Some("by-position") => Structure::ByPosition,
+            _ => Structure::Either,
+        };
+        Ok((
+            String::from(name),
+            Method {
+                params,
+                structure,
+                result,
+            },
+        ))
+    }
+}
+
+/// Escape a JSON Pointer segment.
+fn escape(segment: &str) -> String {
+    segment.replace('~', "~0").replace('/', "~1")
+}
+
+/// Percent-encode a JSON Pointer for a URI fragment.
+fn fragment(pointer: &str) -> String {
+    let mut out = String::new();
+    for byte in pointer.bytes() {
+        match byte.is_ascii_alphanumeric() || b"-._~/!$&'()*+,;=:@".contains(&byte) {
+            true => out.push(byte as char),
+            false => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn contract() -> Contract {
+        Contract::new(&json!({
+            "methods": [
+                {
+                    "name": "get",
+                    "params": [
+                        {"name": "hash", "required": true, "schema": {"$ref": "#/components/schemas/Hash"}},
+                        {"$ref": "#/components/contentDescriptors/Since"}
+                    ],
+                    "result": {"name": "block", "schema": {"$ref": "#/components/schemas/Block"}}
+                },
+                {"name": "named", "paramStructure": "by-name", "params": [{"name": "a", "schema": {}}]},
+                {"name": "anything", "result": {"name": "it"}}
+            ],
+            "components": {
+                "schemas": {
+                    "Hash": {"type": "string", "pattern": "^0x[0-9a-f]+$"},
+                    "Block": {
+                        "type": "object",
+                        "required": ["hash"],
+                        "properties": {"hash": {"$ref": "#/components/schemas/Hash"}}
+                    }
+                },
+                "contentDescriptors": {
+                    "Since": {"name": "since", "schema": {"type": "string", "format": "date"}}
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    fn request(method: &str, params: Value) -> Request {
+        serde_json::from_value(
+            json!({"jsonrpc": "2.0", "method": method, "params": params, "id": 1}),
+        )
+        .unwrap()
+    }
+
+    fn paths(violations: Vec<Violation>) -> Vec<String> {
+        violations.into_iter().map(|it| it.path).collect()
+    }
+
+    #[test]
+    fn params() {
+        let contract = contract();
+        assert_eq!(contract.params(&request("get", json!(["0xab"]))), []);
+        assert_eq!(
+            paths(contract.params(&request("get", json!(["ab", "yesterday"])))),
+            ["/params/0", "/params/1"]
+        );
+        let missing = contract.params(&request("get", json!([])));
+        assert_eq!(missing[0].message, "missing required param `hash`");
+        assert_eq!(
+            paths(contract.params(&request("get", json!({"hash": "0x1", "until": 1})))),
+            ["/params/until"]
+        );
+        assert_eq!(
+            contract.params(&request("named", json!([1])))[0].message,
+            "expected params by name"
+        );
+        assert_eq!(
+            contract.params(&request("nope", json!([])))[0].to_string(),
+            "method `nope` isn't in the document"
+        );
+    }
+
+    #[test]
+    fn result() {
+        let contract = contract();
+        assert_eq!(contract.result("get", &json!({"hash": "0x1"})), []);
+        assert_eq!(
+            paths(contract.result("get", &json!({"hash": "0xz"}))),
+            ["/result/hash"]
+        );
+        assert_eq!(paths(contract.result("get", &json!({}))), ["/result"]);
+        assert_eq!(contract.result("anything", &json!([1, "two"])), []);
+        assert_eq!(contract.result("nope", &json!(1)), []);
+    }
+
+    #[test]
+    fn invalid() {
+        let error = |document| Contract::new(&document).err().unwrap().to_string();
+        assert_eq!(error(json!({})), "no `methods`");
+        assert!(error(json!({"methods": [{"name": "m", "params": [{"name": "a", "schema": {"$ref": "#/nope"}}]}]}))
+            .starts_with("invalid schema at `/methods/0/params/0/schema`"));
+        assert_eq!(
+            error(json!({"methods": [{"$ref": "other.json#/m"}]})),
+            "`$ref` `other.json#/m` isn't into the document"
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Structural differences between [`Value`]s.
+//!
+//! Each [`Difference`] is addressed by a [JSON Pointer](https://www.rfc-editor.org/rfc/rfc6901),
+//! and [`Options`] control what counts as different,
+//! e.g when comparing the results of two servers which disagree on unimportant details.
+
+use alloc::{
+    string::{String, ToString as _},
+    vec,
+    vec::Vec,
+};
+use core::fmt;
+
+use serde_json::{Number, Value};
+
+/// What to ignore when comparing, see [`diff`].
+///
+/// The default is strict equality.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Options {
+    /// JSON Pointers to ignore, along with everything beneath them.
+    ///
+    /// A `*` segment matches any member or element, e.g `/*/timestamp`.
+    pub ignore: Vec<String>,
+    /// Compare arrays as unordered collections.
+    pub ignore_array_order: bool,
+    /// Compare numbers by value, so that e.g `1` and `1.0` are the same.
+    pub numbers_by_value: bool,
+}
+
+/// A single difference between two [`Value`]s.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    /// A JSON Pointer to the difference.
+    pub path: String,
+    pub kind: DifferenceKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DifferenceKind {
+    /// Only present on the left.
+    Removed(Value),
+    /// Only present on the right.
+    Added(Value),
+    /// Present on both sides, with different values.
+    Changed { left: Value, right: Value },
+}
+
+/// Formats as `{path}: {left} -> {right}`, with compact values.
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Self { path, kind } = self;
+        let path = match path.is_empty() {
+            true => "(root)",
+            false => path,
+        };
+        match kind {
+            DifferenceKind::Removed(it) => write!(f, "{}: removed {}", path, it),
+            DifferenceKind::Added(it) => write!(f, "{}: added {}", path, it),
+            DifferenceKind::Changed { left, right } => write!(f, "{}: {} -> {}", path, left, right),
+        }
+    }
+}
+
+/// Find all the differences between `left` and `right`, according to `options`.
+///
+/// Returns an empty [`Vec`] if they are the same.
+pub fn diff(left: &Value, right: &Value, options: &Options) -> Vec<Difference> {
+    let ignore = options
+        .ignore
+        .iter()
+        .map(|it| parse_pointer(it))
+        .collect::<Vec<_>>();
+    let mut out = vec![];
+    Differ {
+        options,
+        ignore: &ignore,
+    }
+    .diff(&mut vec![], left, right, &mut out);
+    out
+}
+
+/// Whether `left` and `right` are the same, according to `options`.
+pub fn eq(left: &Value, right: &Value, options: &Options) -> bool {
+    diff(left, right, options).is_empty()
+}
+
+struct Differ<'a> {
+    options: &'a Options,
+    ignore: &'a [Vec<String>],
+}
+
+impl Differ<'_> {
+    fn is_ignored(&self, path: &[String]) -> bool {
+        self.ignore.iter().any(|pattern| {
+            pattern.len() <= path.len()
+                && pattern
+                    .iter()
+                    .zip(path)
+                    .all(|(pattern, segment)| pattern == "*" || pattern == segment)
+        })
+    }
+    fn diff(&self, path: &mut Vec<String>, left: &Value, right: &Value, out: &mut Vec<Difference>) {
+        if self.is_ignored(path) {
+            return;
+        }
+        match (left, right) {
+            (Value::Object(l), Value::Object(r)) => {
+                for (k, lv) in l {
+                    path.push(k.clone());
+                    match r.get(k) {
+                        Some(rv) => self.diff(path, lv, rv, out),
+                        None => self.push(path, DifferenceKind::Removed(lv.clone()), out),
+                    }
+                    path.pop();
+                }
+                for (k, rv) in r.iter().filter(|(k, _)| !l.contains_key(*k)) {
+                    path.push(k.clone());
+                    self.push(path, DifferenceKind::Added(rv.clone()), out);
+                    path.pop();
+                }
+            }
+            (Value::Array(l), Value::Array(r)) if self.options.ignore_array_order => {
+                let mut unmatched = r.iter().enumerate().collect::<Vec<_>>();
+                for (ix, lv) in l.iter().enumerate() {
+                    path.push(ix.to_string());
+                    let found = unmatched.iter().position(|(_, rv)| {
+                        let mut scratch = vec![];
+                        self.diff(path, lv, rv, &mut scratch);
+                        scratch.is_empty()
+                    });
+                    match found {
+                        Some(it) => drop(unmatched.remove(it)),
+                        None => self.push(path, DifferenceKind::Removed(lv.clone()), out),
+                    }
+                    path.pop();
+                }
+                for (ix, rv) in unmatched {
+                    path.push(ix.to_string());
+                    self.push(path, DifferenceKind::Added(rv.clone()), out);
+                    path.pop();
+                }
+            }
+            (Value::Array(l), Value::Array(r)) => {
+                for ix in 0..l.len().max(r.len()) {
+                    path.push(ix.to_string());
+                    match (l.get(ix), r.get(ix)) {
+                        (Some(lv), Some(rv)) => self.diff(path, lv, rv, out),
+                        (Some(lv), None) => {
+                            self.push(path, DifferenceKind::Removed(lv.clone()), out)
+                        }
+                        (None, Some(rv)) => self.push(path, DifferenceKind::Added(rv.clone()), out),
+                        (None, None) => unreachable!(),
+                    }
+                    path.pop();
+                }
+            }
+            (Value::Number(l), Value::Number(r)) if self.options.numbers_by_value => {
+                if !same_number(l, r) {
+                    self.changed(path, left, right, out)
+                }
+            }
+            (l, r) => {
+                if l != r {
+                    self.changed(path, left, right, out)
+                }
+            }
+        }
+    }
+    fn changed(&self, path: &[String], left: &Value, right: &Value, out: &mut Vec<Difference>) {
+        let kind = DifferenceKind::Changed {
+            left: left.clone(),
+            right: right.clone(),
+        };
+        self.push(path, kind, out)
+    }
+    fn push(&self, path: &[String], kind: DifferenceKind, out: &mut Vec<Difference>) {
+        if !self.is_ignored(path) {
+            out.push(Difference {
+                path: to_pointer(path),
+                kind,
+            })
+        }
+    }
+}
+
+/// Integers are compared exactly, so that large values aren't conflated by rounding.
+fn same_number(l: &Number, r: &Number) -> bool {
+    if let (Some(l), Some(r)) = (l.as_i64(), r.as_i64()) {
+        return l == r;
+    }
+    if let (Some(l), Some(r)) = (l.as_u64(), r.as_u64()) {
+        return l == r;
+    }
+    l.as_f64() == r.as_f64()
+}
+
+fn parse_pointer(pointer: &str) -> Vec<String> {
+    pointer
+        .split('/')
+        .skip(1)
+        .map(|it| it.replace("~1", "/").replace("~0", "~"))
+        .collect()
+}
+
+fn to_pointer(path: &[String]) -> String {
+    let mut out = String::new();
+    for segment in path {
+        out.push('/');
+        out.push_str(&segment.replace('~', "~0").replace('/', "~1"))
+    }
+    out
+}
@@ -0,0 +1,83 @@
+//! Named error codes.
+//!
+//! See the associated constants on [`Error`] for their documentation.
+
+use crate::Error;
+
+/// A well-known [`Error::code`], or any other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// [`Error::PARSE_ERROR`].
+    ParseError,
+    /// [`Error::INVALID_REQUEST`].
+    InvalidRequest,
+    /// [`Error::METHOD_NOT_FOUND`].
+    MethodNotFound,
+    /// [`Error::INVALID_PARAMS`].
+    InvalidParams,
+    /// [`Error::INTERNAL_ERROR`].
+    InternalError,
+    /// [`Error::SERVER_ERROR`].
+    ServerError,
+    /// [`Error::REQUEST_CANCELLED`].
+    RequestCancelled,
+    /// [`Error::CONTENT_MODIFIED`].
+    ContentModified,
+    /// [`Error::SERVER_CANCELLED`].
+    ServerCancelled,
+    /// [`Error::REQUEST_FAILED`].
+    RequestFailed,
+    /// Any code without a name above.
+    Other(i64),
+}
+
+const NAMED: [(ErrorCode, i64); 10] = [
+    (ErrorCode::ParseError, Error::PARSE_ERROR),
+    (ErrorCode::InvalidRequest, Error::INVALID_REQUEST),
+    (ErrorCode::MethodNotFound, Error::METHOD_NOT_FOUND),
+    (ErrorCode::InvalidParams, Error::INVALID_PARAMS),
+    (ErrorCode::InternalError, Error::INTERNAL_ERROR),
+    (ErrorCode::ServerError, Error::SERVER_ERROR),
+    (ErrorCode::RequestCancelled, Error::REQUEST_CANCELLED),
+    (ErrorCode::ContentModified, Error::CONTENT_MODIFIED),
+    (ErrorCode::ServerCancelled, Error::SERVER_CANCELLED),
+    (ErrorCode::RequestFailed, Error::REQUEST_FAILED),
+];
+
+impl ErrorCode {
+    pub fn code(self) -> i64 {
+        match self {
+            ErrorCode::Other(it) => it,
+            named => NAMED
+                .iter()
+                .find_map(|(it, code)| (*it == named).then_some(*code))
+                .expect("all named codes are listed"),
+        }
+    }
+    /// Whether the code is in [`Error::SERVER_ERROR_RANGE`].
+    pub fn is_server_error(self) -> bool {
+        Error::SERVER_ERROR_RANGE.contains(&self.code())
+    }
+    /// Whether the code is in [`Error::RESERVED_RANGE`].
+    ///
+    /// Note that the codes from the Language Server Protocol are outside this range.
+    pub fn is_reserved(self) -> bool {
+        Error::RESERVED_RANGE.contains(&self.code())
+    }
+}
+
+impl From<i64> for ErrorCode {
+    fn from(value: i64) -> Self {
+        NAMED
+            .iter()
+            .find_map(|(it, code)| (*code == value).then_some(*it))
+            .unwrap_or(ErrorCode::Other(value))
+    }
+}
+
+impl From<ErrorCode> for i64 {
+    fn from(value: ErrorCode) -> Self {
+        value.code()
+    }
+}
@@ -0,0 +1,92 @@
+//! Helpers for common shapes of [`Error::data`], beyond those given by the specification.
+
+use alloc::{
+    string::{String, ToString as _},
+    vec::Vec,
+};
+
+use serde_json::{Map, Value};
+
+use crate::Error;
+
+/// The selector for Solidity's `Error(string)`, used by `require` and `revert`.
+const ERROR_STRING_SELECTOR: [u8; 4] = [0x08, 0xc3, 0x79, 0xa0];
+
+impl Error {
+    /// An [`Error::INTERNAL_ERROR`], with `err` as the message,
+    /// and the messages of it and its [`source`](core::error::Error::source)s as `data`:
+    ///
+    /// `{"chain": ["outermost", ..., "innermost"]}`
+    ///
+    /// See [`Self::error_chain`].
+    pub fn internal_from(err: impl core::error::Error) -> Self {
+        let chain = core::iter::successors(Some(&err as &dyn core::error::Error), |it| it.source())
+            .map(|it| Value::String(it.to_string()))
+            .collect();
+        Self::internal_error(
+            &err,
+            Value::Object(Map::from_iter([(
+                String::from("chain"),
+                Value::Array(chain),
+            )])),
+        )
+    }
+    /// Like [`Self::internal_from`], for an [`anyhow::Error`].
+    #[cfg(feature = "anyhow")]
+    pub fn internal_from_anyhow(err: anyhow::Error) -> Self {
+        Self::internal_from(&*err)
+    }
+    /// Returns the messages in `data`, if it was created by [`Self::internal_from`].
+    pub fn error_chain(&self) -> Option<Vec<&str>> {
+        self.data
+            .as_ref()?
+            .get("chain")?
+            .as_array()?
+            .iter()
+            .map(Value::as_str)
+            .collect()
+    }
+    /// Returns `data` if it is a string.
+    ///
+    /// Many servers use this for a human-readable elaboration of the message.
+    pub fn data_str(&self) -> Option<&str> {
+        self.data.as_ref()?.as_str()
+    }
+    /// Returns `data` if it is a `0x`-prefixed hex string, decoded.
+    ///
+    /// Ethereum nodes use this to return the raw output of reverted calls.
+    pub fn data_hex(&self) -> Option<Vec<u8>> {
+        decode_hex(self.data_str()?.strip_prefix("0x")?)
+    }
+    /// Returns the reason string of a reverted Ethereum call,
+    /// if `data` is an ABI-encoded Solidity `Error(string)`.
+    pub fn revert_reason(&self) -> Option<String> {
+        let data = self.data_hex()?;
+        let payload = data.strip_prefix(&ERROR_STRING_SELECTOR)?;
+        let offset = abi_word(payload, 0)?;
+        let len = abi_word(payload, offset)?;
+        let start = offset.checked_add(32)?;
+        let reason = payload.get(start..start.checked_add(len)?)?;
+        String::from_utf8(reason.to_vec()).ok()
+    }
+}
+
+fn decode_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|ix| u8::from_str_radix(s.get(ix..ix + 2)?, 16).ok())
+        .collect()
+}
+
+/// Read the 32-byte big-endian word at `at` as a [`usize`].
+fn abi_word(payload: &[u8], at: usize) -> Option<usize> {
+    let word = payload.get(at..at.checked_add(32)?)?;
+    let (high, low) = word.split_at(32 - core::mem::size_of::<usize>());
+    match high.iter().all(|it| *it == 0) {
+        true => Some(usize::from_be_bytes(low.try_into().ok()?)),
+        false => None,
+    }
+}
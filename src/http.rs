@@ -0,0 +1,261 @@
+//! Conversions to and from [`http`](https://docs.rs/http) requests and responses.
+//!
+//! Calls are `POST`ed with an `application/json` body.
+//! Servers respond with `200 OK` and a body, or `204 No Content` if there is nothing to respond with,
+//! i.e the request was a notification, or a batch of notifications.
+//!
+//! Servers may report errors with a non-2xx status,
+//! so responses are parsed from the body whatever the status,
+//! and the status is only reported if the body is not a response.
+
+use std::fmt;
+
+use ::http::{
+    header::{ACCEPT, CONTENT_TYPE},
+    response::Parts,
+    HeaderValue, Method, StatusCode, Uri,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::{MaybeBatchedRequest, MaybeBatchedResponse, Request, Response};
+
+const APPLICATION_JSON: HeaderValue = HeaderValue::from_static("application/json");
+
+/// A response which could not be read from a [`http::Response`](::http::Response).
+#[derive(Debug)]
+pub enum HttpError {
+    /// The server failed with this (non-2xx) status, without a `JSON-RPC 2.0` response.
+    Status(StatusCode),
+    /// The body was not JSON, according to the `Content-Type`.
+    ContentType(HeaderValue),
+    /// The body was not a `JSON-RPC 2.0` response.
+    Json(crate::parse::Error),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HttpError::Status(it) => write!(f, "server responded with status {}", it),
+            HttpError::ContentType(it) => write!(f, "unexpected content type {:?}", it),
+            HttpError::Json(e) => write!(f, "invalid response: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            HttpError::Json(e) => Some(e),
+            HttpError::Status(_) | HttpError::ContentType(_) => None,
+        }
+    }
+}
+
+pub(crate) fn to_http_request(body: &impl Serialize, uri: Uri) -> ::http::Request<Vec<u8>> {
+    let mut request = ::http::Request::new(to_body(body));
+    *request.method_mut() = Method::POST;
+    *request.uri_mut() = uri;
+    let headers = request.headers_mut();
+    headers.insert(CONTENT_TYPE, APPLICATION_JSON);
+    headers.insert(ACCEPT, APPLICATION_JSON);
+    request
+}
+
+fn to_http_response(body: Option<&impl Serialize>) -> ::http::Response<Vec<u8>> {
+    let Some(body) = body else {
+        let mut response = ::http::Response::new(vec![]);
+        *response.status_mut() = StatusCode::NO_CONTENT;
+        return response;
+    };
+    let mut response = ::http::Response::new(to_body(body));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, APPLICATION_JSON);
+    response
+}
+
+pub(crate) fn from_http_response<T: DeserializeOwned>(
+    parts: &Parts,
+    body: &[u8],
+) -> Result<Option<T>, HttpError> {
+    let success = parts.status.is_success();
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return match success {
+            true => Ok(None),
+            false => Err(HttpError::Status(parts.status)),
+        };
+    }
+    match (
+        crate::parse::from_slice(body),
+        parts.headers.get(CONTENT_TYPE),
+    ) {
+        (Ok(it), _) => Ok(Some(it)),
+        (Err(_), _) if !success => Err(HttpError::Status(parts.status)),
+        (Err(_), Some(it)) if !is_json(it) => Err(HttpError::ContentType(it.clone())),
+        (Err(e), _) => Err(HttpError::Json(e)),
+    }
+}
+
+/// `application/json`, or a `+json` suffix, ignoring any parameters.
+fn is_json(content_type: &HeaderValue) -> bool {
+    let Ok(it) = content_type.to_str() else {
+        return false;
+    };
+    let essence = it.split(';').next().unwrap_or_default().trim();
+    essence.eq_ignore_ascii_case("application/json")
+        || essence.to_ascii_lowercase().ends_with("+json")
+}
+
+fn to_body(body: &impl Serialize) -> Vec<u8> {
+    serde_json::to_vec(body).expect("requests and responses always serialize")
+}
+
+impl Request {
+    /// A `POST` to `uri`, see [the module documentation](mod@self).
+    pub fn into_http(self, uri: Uri) -> ::http::Request<Vec<u8>> {
+        to_http_request(&self, uri)
+    }
+}
+
+impl MaybeBatchedRequest {
+    /// A `POST` to `uri`, see [the module documentation](mod@self).
+    pub fn into_http(self, uri: Uri) -> ::http::Request<Vec<u8>> {
+        to_http_request(&self, uri)
+    }
+}
+
+impl Response {
+    /// Read the server's response to a single request.
+    ///
+    /// Returns [`None`] if the server didn't respond, i.e the request was a notification.
+    pub fn from_http(parts: &Parts, body: &[u8]) -> Result<Option<Self>, HttpError> {
+        from_http_response(parts, body)
+    }
+    /// A `200 OK`, see [the module documentation](mod@self).
+    pub fn into_http(self) -> ::http::Response<Vec<u8>> {
+        to_http_response(Some(&self))
+    }
+}
+
+impl MaybeBatchedResponse {
+    /// Read the server's response to a (possibly batched) request.
+    ///
+    /// Returns [`None`] if the server didn't respond.
+    pub fn from_http(parts: &Parts, body: &[u8]) -> Result<Option<Self>, HttpError> {
+        from_http_response(parts, body)
+    }
+    /// A `200 OK`, see [the module documentation](mod@self).
+    pub fn into_http(self) -> ::http::Response<Vec<u8>> {
+        to_http_response(Some(&self))
+    }
+}
+
+/// A `200 OK`, or a `204 No Content` for [`None`],
+/// as returned by [`Router::handle_batch`](crate::router::Router::handle_batch).
+pub fn respond(response: Option<MaybeBatchedResponse>) -> ::http::Response<Vec<u8>> {
+    to_http_response(response.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn parts(status: u16, content_type: Option<&str>) -> Parts {
+        let mut builder = ::http::Response::builder().status(status);
+        if let Some(it) = content_type {
+            builder = builder.header(CONTENT_TYPE, it)
+        }
+        builder.body(()).unwrap().into_parts().0
+    }
+
+    fn response() -> Response {
+        serde_json::from_value(json!({"jsonrpc": "2.0", "result": [1], "id": 1})).unwrap()
+    }
+
+    #[test]
+    fn request() {
+        let request = serde_json::from_value::<Request>(
+            json!({"jsonrpc": "2.0", "method": "a", "params": [1], "id": 1}),
+        )
+        .unwrap();
+        let http = request
+            .clone()
+            .into_http("http://localhost/rpc".parse().unwrap());
+        assert_eq!(http.method(), Method::POST);
+        assert_eq!(http.uri(), "http://localhost/rpc");
+        assert_eq!(http.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(http.headers()[ACCEPT], "application/json");
+        assert_eq!(
+            serde_json::from_slice::<Request>(http.body()).unwrap(),
+            request
+        );
+    }
+
+    #[test]
+    fn from_http() {
+        let body = serde_json::to_vec(&response()).unwrap();
+        let read = |status, content_type, body: &[u8]| {
+            Response::from_http(&parts(status, content_type), body)
+        };
+        assert_eq!(
+            read(200, Some("application/json"), &body).unwrap(),
+            Some(response())
+        );
+        // The body decides, whatever the status or content type.
+        assert_eq!(
+            read(500, Some("text/plain"), &body).unwrap(),
+            Some(response())
+        );
+        assert_eq!(read(204, None, b"").unwrap(), None);
+        assert_eq!(read(200, None, b" \n").unwrap(), None);
+
+        assert!(matches!(
+            read(502, None, b""),
+            Err(HttpError::Status(StatusCode::BAD_GATEWAY))
+        ));
+        assert!(matches!(
+            read(503, Some("text/html"), b"<html>"),
+            Err(HttpError::Status(StatusCode::SERVICE_UNAVAILABLE))
+        ));
+        match read(200, Some("text/html; charset=utf-8"), b"<html>") {
+            Err(HttpError::ContentType(it)) => assert_eq!(it, "text/html; charset=utf-8"),
+            other => panic!("{:?}", other),
+        }
+        for content_type in [
+            None,
+            Some("application/json"),
+            Some("Application/JSON; charset=utf-8"),
+            Some("application/problem+json"),
+        ] {
+            assert!(
+                matches!(read(200, content_type, b"<html>"), Err(HttpError::Json(_))),
+                "{:?}",
+                content_type
+            );
+        }
+    }
+
+    #[test]
+    fn respond() {
+        let ok = super::respond(Some(MaybeBatchedResponse::Single(response())));
+        assert_eq!(ok.status(), StatusCode::OK);
+        assert_eq!(ok.headers()[CONTENT_TYPE], "application/json");
+        assert_eq!(
+            serde_json::from_slice::<Response>(ok.body()).unwrap(),
+            response()
+        );
+
+        let none = super::respond(None);
+        assert_eq!(none.status(), StatusCode::NO_CONTENT);
+        assert!(none.body().is_empty() && none.headers().get(CONTENT_TYPE).is_none());
+
+        let batch = MaybeBatchedResponse::Batch(vec![response(), response()]);
+        let (parts, body) = batch.clone().into_http().into_parts();
+        assert_eq!(
+            MaybeBatchedResponse::from_http(&parts, &body).unwrap(),
+            Some(batch)
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Working with [`Id`]s, and generating them.
+
+use core::cmp::Ordering;
+#[cfg(target_has_atomic = "64")]
+use core::sync::atomic::{self, AtomicU64};
+
+use serde_json::Number;
+
+use crate::Id;
+
+impl Id {
+    /// An integer id, as used by [`Sequential`].
+    pub fn from_u64(n: u64) -> Self {
+        Self::Number(Number::from(n))
+    }
+    /// Returns [`Some`] if this is a non-negative integer.
+    pub fn as_u64(&self) -> Option<u64> {
+        match self {
+            Id::Number(it) => it.as_u64(),
+            Id::String(_) | Id::Null => None,
+        }
+    }
+    /// > Numbers SHOULD NOT contain fractional parts
+    ///
+    /// Numbers without a fractional part are written as integers (so `1.0` becomes `1`).
+    /// Numbers with one (or which are too large to be an integer) are rejected, returning [`None`].
+    pub fn normalize(self) -> Option<Self> {
+        let Id::Number(number) = &self else {
+            return Some(self);
+        };
+        // `as` saturates, so only cast values which are in range.
+        const I64_END: f64 = 9_223_372_036_854_775_808.0; // 2^63
+        const U64_END: f64 = 18_446_744_073_709_551_616.0; // 2^64
+        match number.as_f64() {
+            Some(f) if number.is_f64() => match f {
+                f if (-I64_END..I64_END).contains(&f) && f == (f as i64) as f64 => {
+                    Some(Id::Number(Number::from(f as i64)))
+                }
+                f if (0.0..U64_END).contains(&f) && f == (f as u64) as f64 => {
+                    Some(Id::from_u64(f as u64))
+                }
+                _ => None,
+            },
+            _ => Some(self),
+        }
+    }
+}
+
+/// Numbers are ordered by value, and strings lexicographically.
+///
+/// Numbers, strings and null are not comparable with each other.
+impl PartialOrd for Id {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Id::Null, Id::Null) => Some(Ordering::Equal),
+            (Id::String(l), Id::String(r)) => Some(l.cmp(r)),
+            (Id::Number(l), Id::Number(r)) => {
+                if let (Some(l), Some(r)) = (l.as_i64(), r.as_i64()) {
+                    return Some(l.cmp(&r));
+                }
+                if let (Some(l), Some(r)) = (l.as_u64(), r.as_u64()) {
+                    return Some(l.cmp(&r));
+                }
+                match l.as_f64()?.partial_cmp(&r.as_f64()?)? {
+                    // Stay consistent with `PartialEq`, where e.g `1.0 != 1`.
+                    Ordering::Equal if l != r => None,
+                    ordering => Some(ordering),
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A source of [`Id`]s for outgoing calls.
+///
+/// Implementations should return a different [`Id`] each time.
+pub trait IdGenerator {
+    fn next_id(&self) -> Id;
+}
+
+impl<F: Fn() -> Id> IdGenerator for F {
+    fn next_id(&self) -> Id {
+        self()
+    }
+}
+
+/// Consecutive integers, starting at zero by default.
+#[cfg(target_has_atomic = "64")]
+#[derive(Debug, Default)]
+pub struct Sequential(AtomicU64);
+
+#[cfg(target_has_atomic = "64")]
+impl Sequential {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn starting_at(n: u64) -> Self {
+        Self(AtomicU64::new(n))
+    }
+}
+
+#[cfg(target_has_atomic = "64")]
+impl IdGenerator for Sequential {
+    fn next_id(&self) -> Id {
+        Id::from_u64(self.0.fetch_add(1, atomic::Ordering::Relaxed))
+    }
+}
+
+/// [Version 7 UUIDs](https://www.rfc-editor.org/rfc/rfc9562#name-uuid-version-7),
+/// which are ordered by creation time, as strings.
+#[cfg(feature = "uuid")]
+#[derive(Debug, Default, Clone, Copy)]
+pub struct UuidV7;
+
+#[cfg(feature = "uuid")]
+impl IdGenerator for UuidV7 {
+    fn next_id(&self) -> Id {
+        Id::String(uuid::Uuid::now_v7().to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn float(f: f64) -> Id {
+        Id::Number(Number::from_f64(f).unwrap())
+    }
+
+    #[test]
+    fn normalize() {
+        assert_eq!(float(1.0).normalize(), Some(Id::from_u64(1)));
+        assert_eq!(float(-1.0).normalize(), Some(Id::Number(Number::from(-1))));
+        assert_eq!(float(1.5).normalize(), None);
+        assert_eq!(Id::from("1.0").normalize(), Some(Id::from("1.0")));
+        assert_eq!(
+            float(-9_223_372_036_854_775_808.0).normalize(),
+            Some(Id::Number(Number::from(i64::MIN)))
+        );
+    }
+
+    #[test]
+    fn normalize_out_of_range() {
+        // 2^63 is a `u64`, not `i64::MAX`.
+        assert_eq!(
+            float(9_223_372_036_854_775_808.0).normalize(),
+            Some(Id::from_u64(1 << 63))
+        );
+        // 2^64 isn't either.
+        assert_eq!(float(18_446_744_073_709_551_616.0).normalize(), None);
+        assert_eq!(float(-9_223_372_036_854_777_856.0).normalize(), None);
+        assert_eq!(float(1e300).normalize(), None);
+    }
+}
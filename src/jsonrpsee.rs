@@ -0,0 +1,175 @@
+//! Conversions to and from the types in [`jsonrpsee_types`].
+//!
+//! `jsonrpsee` is more restrictive than this crate:
+//! - Numeric ids must be unsigned integers.
+//! - Error codes must fit in an [`i32`].
+//! - Its [`Request`](jsonrpsee_types::Request) always has an id, so can't be a notification.
+
+use std::{borrow::Cow, fmt};
+
+use serde_json::{value::RawValue, Number, Value};
+
+use crate::{Error, Id, Request, RequestParameters, Response, V2};
+
+#[derive(Debug)]
+pub enum ConversionError {
+    /// `jsonrpsee` ids must be a string, an unsigned integer, or null.
+    Id,
+    /// `jsonrpsee` error codes must fit in an [`i32`].
+    ErrorCode(i64),
+    /// `jsonrpsee` requests always have an id.
+    Notification,
+    /// Parameters must be an `Array` or an `Object`.
+    Params(serde_json::Error),
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConversionError::Id => f.write_str("id must be a string, an unsigned integer, or null"),
+            ConversionError::ErrorCode(it) => write!(f, "error code {} does not fit in an i32", it),
+            ConversionError::Notification => {
+                f.write_str("jsonrpsee cannot represent a notification")
+            }
+            ConversionError::Params(e) => write!(f, "invalid parameters: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConversionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConversionError::Params(e) => Some(e),
+            ConversionError::Id | ConversionError::ErrorCode(_) | ConversionError::Notification => {
+                None
+            }
+        }
+    }
+}
+
+fn to_raw(value: &impl serde::Serialize) -> Box<RawValue> {
+    serde_json::value::to_raw_value(value).expect("values always serialize")
+}
+
+fn from_raw(raw: &RawValue) -> Value {
+    serde_json::from_str(raw.get()).expect("raw values are valid JSON")
+}
+
+impl From<jsonrpsee_types::Id<'_>> for Id {
+    fn from(value: jsonrpsee_types::Id<'_>) -> Self {
+        match value {
+            jsonrpsee_types::Id::Null => Id::Null,
+            jsonrpsee_types::Id::Number(it) => Id::Number(Number::from(it)),
+            jsonrpsee_types::Id::Str(it) => Id::String(it.into_owned()),
+        }
+    }
+}
+
+impl TryFrom<Id> for jsonrpsee_types::Id<'static> {
+    type Error = ConversionError;
+
+    fn try_from(value: Id) -> Result<Self, Self::Error> {
+        match value {
+            Id::Null => Ok(Self::Null),
+            Id::Number(it) => it.as_u64().map(Self::Number).ok_or(ConversionError::Id),
+            Id::String(it) => Ok(Self::Str(Cow::Owned(it))),
+        }
+    }
+}
+
+impl From<jsonrpsee_types::ErrorObject<'_>> for Error {
+    fn from(value: jsonrpsee_types::ErrorObject<'_>) -> Self {
+        Error {
+            code: value.code().into(),
+            message: value.message().into(),
+            data: value.data().map(from_raw),
+        }
+    }
+}
+
+impl TryFrom<Error> for jsonrpsee_types::ErrorObjectOwned {
+    type Error = ConversionError;
+
+    fn try_from(value: Error) -> Result<Self, Self::Error> {
+        let Error {
+            code,
+            message,
+            data,
+        } = value;
+        let code = i32::try_from(code).map_err(|_| ConversionError::ErrorCode(code))?;
+        Ok(Self::owned(code, message, data))
+    }
+}
+
+impl TryFrom<jsonrpsee_types::Request<'_>> for Request {
+    type Error = ConversionError;
+
+    fn try_from(value: jsonrpsee_types::Request<'_>) -> Result<Self, Self::Error> {
+        let jsonrpsee_types::Request {
+            id, method, params, ..
+        } = value;
+        Ok(Request {
+            jsonrpc: V2,
+            method: method.into_owned(),
+            params: params
+                .map(|it| serde_json::from_str::<RequestParameters>(it.get()))
+                .transpose()
+                .map_err(ConversionError::Params)?,
+            id: Some(id.into()),
+        })
+    }
+}
+
+impl TryFrom<Request> for jsonrpsee_types::Request<'static> {
+    type Error = ConversionError;
+
+    fn try_from(value: Request) -> Result<Self, Self::Error> {
+        let Request {
+            jsonrpc: V2,
+            method,
+            params,
+            id,
+        } = value;
+        let id = id.ok_or(ConversionError::Notification)?.try_into()?;
+        Ok(Self {
+            jsonrpc: jsonrpsee_types::TwoPointZero,
+            id,
+            method: Cow::Owned(method),
+            params: params.map(|it| Cow::Owned(to_raw(&it))),
+            extensions: Default::default(),
+        })
+    }
+}
+
+impl From<jsonrpsee_types::Response<'_, Value>> for Response {
+    fn from(value: jsonrpsee_types::Response<'_, Value>) -> Self {
+        let jsonrpsee_types::Response { payload, id, .. } = value;
+        Response {
+            jsonrpc: V2,
+            result: match payload {
+                jsonrpsee_types::ResponsePayload::Success(it) => Ok(it.into_owned()),
+                jsonrpsee_types::ResponsePayload::Error(it) => Err(it.into()),
+            },
+            id: id.into(),
+        }
+    }
+}
+
+impl TryFrom<Response> for jsonrpsee_types::Response<'static, Value> {
+    type Error = ConversionError;
+
+    fn try_from(value: Response) -> Result<Self, Self::Error> {
+        let Response {
+            jsonrpc: V2,
+            result,
+            id,
+        } = value;
+        let payload = match result {
+            Ok(it) => jsonrpsee_types::ResponsePayload::success(it),
+            Err(it) => jsonrpsee_types::ResponsePayload::error(
+                jsonrpsee_types::ErrorObjectOwned::try_from(it)?,
+            ),
+        };
+        Ok(Self::new(payload, id.try_into()?))
+    }
+}
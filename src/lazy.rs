@@ -0,0 +1,322 @@
+//! Variants of the `JSON-RPC 2.0` types which defer parsing of their payloads.
+//!
+//! The envelope (method, id, error...) is parsed up front,
+//! but `params` and `result` are kept as [`RawValue`]s until they are asked for.
+//! This suits workloads which mostly pass messages through,
+//! and only occasionally look inside them.
+//!
+//! Unlike the types in [`borrowed`](crate::borrowed), these own their data.
+//!
+//! Formats other than JSON which aren't human readable, like [MessagePack](crate::msgpack)
+//! and [CBOR](crate::cbor), encode the values the [`RawValue`]s hold, rather than their text.
+
+use alloc::{boxed::Box, string::String};
+
+use serde::{de::Error as _, ser::Error as _, Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{value::RawValue, Value};
+
+use crate::{deserialize_some, Error, Id, RawResponseSer, Request, Response, V2};
+
+/// A [`Request`] with unparsed `params`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LazyRequest {
+    pub jsonrpc: V2,
+    pub method: String,
+    /// Be lenient in what we accept: a `null` is treated as absent.
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        serialize_with = "serialize_raw",
+        deserialize_with = "deserialize_raw"
+    )]
+    pub params: Option<Box<RawValue>>,
+    /// [`None`] if this is a notification.
+    #[serde(
+        default,
+        deserialize_with = "deserialize_some",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub id: Option<Id>,
+}
+
+impl LazyRequest {
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+    /// See [`Request::deserialize_params`].
+    pub fn deserialize_params<'de, T>(&'de self) -> serde_json::Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        serde_json::from_str(self.params.as_deref().map(RawValue::get).unwrap_or("null"))
+    }
+    /// Parse the parameters.
+    pub fn materialize(self) -> serde_json::Result<Request> {
+        let Self {
+            jsonrpc,
+            method,
+            params,
+            id,
+        } = self;
+        Ok(Request {
+            jsonrpc,
+            method,
+            params: params
+                .map(|it| serde_json::from_str(it.get()))
+                .transpose()?,
+            id,
+        })
+    }
+}
+
+/// A [`Response`] with an unparsed `result`.
+#[derive(Debug, Clone)]
+pub struct LazyResponse {
+    pub jsonrpc: V2,
+    pub result: Result<Box<RawValue>, Error>,
+    pub id: Id,
+}
+
+impl LazyResponse {
+    /// See [`Response::deserialize_result`].
+    pub fn deserialize_result<'de, T>(&'de self) -> serde_json::Result<Result<T, Error>>
+    where
+        T: Deserialize<'de>,
+    {
+        match &self.result {
+            Ok(it) => serde_json::from_str(it.get()).map(Ok),
+            Err(e) => Ok(Err(e.clone())),
+        }
+    }
+    /// Parse the result.
+    pub fn materialize(self) -> serde_json::Result<Response> {
+        let Self {
+            jsonrpc,
+            result,
+            id,
+        } = self;
+        Ok(Response {
+            jsonrpc,
+            result: match result {
+                Ok(it) => Ok(serde_json::from_str(it.get())?),
+                Err(e) => Err(e),
+            },
+            id,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct RawResponseDe {
+    jsonrpc: V2,
+    #[serde(default, deserialize_with = "deserialize_some")]
+    result: Option<RawBox>,
+    #[serde(default)]
+    error: Option<Error>,
+    id: Id,
+}
+
+impl Serialize for LazyResponse {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let Self {
+            jsonrpc,
+            result,
+            id,
+        } = self;
+        let raw = result.as_ref().ok().map(|it| Raw(it));
+        RawResponseSer {
+            jsonrpc: *jsonrpc,
+            result: raw.as_ref(),
+            error: result.as_ref().err(),
+            id,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for LazyResponse {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let RawResponseDe {
+            jsonrpc,
+            result,
+            error,
+            id,
+        } = RawResponseDe::deserialize(deserializer)?;
+        let result = match (result, error) {
+            (Some(ok), None) => Ok(ok.0),
+            (None, Some(err)) => Err(err),
+            (Some(_), Some(_)) => {
+                return Err(D::Error::custom(
+                    "only ONE of `error` and `result` may be present",
+                ))
+            }
+            (None, None) => {
+                return Err(D::Error::custom("must have an `error` or `result` member"))
+            }
+        };
+        Ok(Self {
+            jsonrpc,
+            result,
+            id,
+        })
+    }
+}
+
+/// Serializes as its text for human readable formats, like JSON,
+/// and as the value it holds for others.
+struct Raw<'a>(&'a RawValue);
+
+impl Serialize for Raw<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match serializer.is_human_readable() {
+            true => self.0.serialize(serializer),
+            false => serde_json::from_str::<Value>(self.0.get())
+                .map_err(S::Error::custom)?
+                .serialize(serializer),
+        }
+    }
+}
+
+/// The counterpart to [`Raw`].
+struct RawBox(Box<RawValue>);
+
+impl<'de> Deserialize<'de> for RawBox {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match deserializer.is_human_readable() {
+            true => Box::<RawValue>::deserialize(deserializer).map(Self),
+            false => serde_json::value::to_raw_value(&Value::deserialize(deserializer)?)
+                .map(Self)
+                .map_err(D::Error::custom),
+        }
+    }
+}
+
+fn serialize_raw<S>(value: &Option<Box<RawValue>>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    value.as_deref().map(Raw).serialize(serializer)
+}
+
+fn deserialize_raw<'de, D>(deserializer: D) -> Result<Option<Box<RawValue>>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Ok(Option::<RawBox>::deserialize(deserializer)?.map(|it| it.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    const REQUEST: &str = r#"{"jsonrpc":"2.0","method":"a","params":[1, {"b" : 2}],"id":1}"#;
+    const RESPONSE: &str = r#"{"jsonrpc":"2.0","result":{"a" : [1]},"id":"x"}"#;
+    const ERROR: &str = r#"{"jsonrpc":"2.0","error":{"code":-32000,"message":"no"},"id":null}"#;
+
+    #[test]
+    fn request() {
+        let request = serde_json::from_str::<LazyRequest>(REQUEST).unwrap();
+        // The raw parameters keep their formatting.
+        assert_eq!(serde_json::to_string(&request).unwrap(), REQUEST);
+        assert_eq!(
+            request.deserialize_params::<(u8, Value)>().unwrap(),
+            (1, json!({"b": 2}))
+        );
+        assert_eq!(
+            request.materialize().unwrap(),
+            serde_json::from_str::<Request>(REQUEST).unwrap()
+        );
+
+        let null =
+            serde_json::from_str::<LazyRequest>(r#"{"jsonrpc":"2.0","method":"a","params":null}"#)
+                .unwrap();
+        assert!(null.params.is_none() && null.is_notification());
+        assert_eq!(
+            serde_json::to_string(&null).unwrap(),
+            r#"{"jsonrpc":"2.0","method":"a"}"#
+        );
+    }
+
+    #[test]
+    fn response() {
+        let response = serde_json::from_str::<LazyResponse>(RESPONSE).unwrap();
+        assert_eq!(serde_json::to_string(&response).unwrap(), RESPONSE);
+        assert_eq!(
+            response.deserialize_result::<Value>().unwrap(),
+            Ok(json!({"a": [1]}))
+        );
+        assert_eq!(
+            response.materialize().unwrap(),
+            serde_json::from_str::<Response>(RESPONSE).unwrap()
+        );
+
+        let error = serde_json::from_str::<LazyResponse>(ERROR).unwrap();
+        assert_eq!(serde_json::to_string(&error).unwrap(), ERROR);
+        assert_eq!(
+            error.materialize().unwrap(),
+            serde_json::from_str::<Response>(ERROR).unwrap()
+        );
+
+        for it in [
+            r#"{"jsonrpc":"2.0","id":1}"#,
+            r#"{"jsonrpc":"2.0","result":1,"error":{"code":1,"message":""},"id":1}"#,
+        ] {
+            assert!(serde_json::from_str::<LazyResponse>(it).is_err(), "{}", it);
+        }
+    }
+
+    /// Formats which aren't human readable see the values the raw JSON holds,
+    /// so encode exactly as the materialized types do.
+    #[test]
+    #[cfg(all(feature = "msgpack", feature = "cbor"))]
+    fn not_human_readable() {
+        use crate::{cbor, msgpack};
+
+        for text in [
+            REQUEST,
+            r#"{"jsonrpc":"2.0","method":"a"}"#,
+            r#"{"jsonrpc":"2.0","method":"a","params":{"k":[null]}}"#,
+        ] {
+            let lazy = serde_json::from_str::<LazyRequest>(text).unwrap();
+            let request = serde_json::from_str::<Request>(text).unwrap();
+
+            let bytes = msgpack::to_vec(&lazy).unwrap();
+            assert_eq!(bytes, msgpack::to_vec(&request).unwrap(), "{}", text);
+            let back = msgpack::from_slice::<LazyRequest>(&bytes).unwrap();
+            assert_eq!(back.materialize().unwrap(), request);
+
+            let bytes = cbor::to_vec(&lazy).unwrap();
+            assert_eq!(bytes, cbor::to_vec(&request).unwrap(), "{}", text);
+            let back = cbor::from_slice::<LazyRequest>(&bytes).unwrap();
+            assert_eq!(back.materialize().unwrap(), request);
+        }
+        for text in [RESPONSE, ERROR] {
+            let lazy = serde_json::from_str::<LazyResponse>(text).unwrap();
+            let response = serde_json::from_str::<Response>(text).unwrap();
+
+            let bytes = msgpack::to_vec(&lazy).unwrap();
+            assert_eq!(bytes, msgpack::to_vec(&response).unwrap(), "{}", text);
+            let back = msgpack::from_slice::<LazyResponse>(&bytes).unwrap();
+            assert_eq!(back.materialize().unwrap(), response);
+
+            let bytes = cbor::to_vec(&lazy).unwrap();
+            assert_eq!(bytes, cbor::to_vec(&response).unwrap(), "{}", text);
+            let back = cbor::from_slice::<LazyResponse>(&bytes).unwrap();
+            assert_eq!(back.materialize().unwrap(), response);
+        }
+    }
+}
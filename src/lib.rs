@@ -1,8 +1,41 @@
 //! A transcription of types from the [`JSON-RPC 2.0` Specification](https://www.jsonrpc.org/specification).
 //!
 //! > When quoted, the specification will appear as blockquoted text, like so.
-
-use std::{borrow::Cow, fmt::Display, ops::RangeInclusive, str::FromStr};
+//!
+//! # Features
+//! - `std` (default): without it, the crate is `no_std`, and only requires `alloc`.
+//!   Clients and [`batch`] require `std`.
+//! - `blocking` (default): a blocking [`Client`].
+//! - `async` (default): an [`AsyncClient`]. Implies `http`.
+//! - `wasm`: a [`WasmClient`] for the browser.
+//! - `simd-json`: parse bodies with `simd-json`, see [`parse`].
+//! - `uuid`: an [`id::UuidV7`] generator.
+//! - `glob`: a [`method::MethodGlob`] for matching families of methods.
+//! - `anyhow`: [`Error::internal_from_anyhow`].
+//! - `cli` (default): dependencies of the binaries.
+//! - `arbitrary`: implementations of `arbitrary::Arbitrary` for fuzzing.
+//! - `schemars`: implementations of `schemars::JsonSchema`.
+//! - `proptest`: [`proptest`](https://docs.rs/proptest) strategies in `strategy`.
+//! - `axum`: an extractor for requests, and responders for responses, see [`axum`](mod@axum).
+//! - `codec`: framing for byte streams in [`codec`].
+//! - `msgpack`: MessagePack encoding in [`msgpack`].
+//! - `cbor`: CBOR encoding in [`cbor`].
+//! - `contract`: checking calls against an OpenRPC document in [`contract`].
+//! - `peer`: bidirectional connections in [`peer`].
+//! - `http`: conversions to and from `http` requests and responses in [`http`](mod@http).
+//! - `jsonrpsee`: conversions to and from `jsonrpsee` types in [`jsonrpsee`](mod@jsonrpsee).
+//! - `tower`: implementations of `tower::Service` for [`router::Router`], and [`AsyncClient`] (with `async`).
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{
+    borrow::Cow,
+    string::{String, ToString as _},
+    vec::Vec,
+};
+use core::{fmt::Display, ops::RangeInclusive, str::FromStr};
 
 use serde::{
     de::{Error as _, Unexpected},
@@ -10,8 +43,62 @@ use serde::{
 };
 use serde_json::{Map, Number, Value};
 
+#[cfg(feature = "arbitrary")]
+mod arbitrary_impl;
+#[cfg(feature = "axum")]
+pub mod axum;
+#[cfg(feature = "std")]
+pub mod batch;
+pub mod borrowed;
+pub mod builder;
+pub mod canonical;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+#[cfg(any(feature = "blocking", feature = "async", feature = "wasm"))]
+pub mod client;
+#[cfg(feature = "codec")]
+pub mod codec;
+#[cfg(feature = "contract")]
+pub mod contract;
+pub mod diff;
+mod error_code;
+pub mod error_data;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod id;
+#[cfg(feature = "jsonrpsee")]
+pub mod jsonrpsee;
+pub mod lazy;
+pub mod limits;
+pub mod method;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+pub mod parse;
+#[cfg(feature = "peer")]
+pub mod peer;
+#[cfg(feature = "std")]
+pub mod router;
+#[cfg(feature = "schemars")]
+mod schema;
+#[cfg(feature = "tower")]
+mod service;
+#[cfg(feature = "proptest")]
+pub mod strategy;
+pub mod stream;
+pub mod v1;
+pub mod validate;
+pub use builder::RequestBuilder;
+#[cfg(feature = "async")]
+pub use client::AsyncClient;
+#[cfg(feature = "blocking")]
+pub use client::Client;
+#[cfg(feature = "wasm")]
+pub use client::WasmClient;
+pub use error_code::ErrorCode;
+
 /// A `JSON-RPC 2.0` request object.
 #[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Request {
     /// > A String specifying the version of the JSON-RPC protocol.
     /// > MUST be exactly "2.0".
@@ -35,10 +122,19 @@ pub struct Request {
 }
 
 impl Request {
+    /// Start building a request, see [`RequestBuilder`].
+    pub fn builder() -> RequestBuilder {
+        RequestBuilder::default()
+    }
     pub fn is_notification(&self) -> bool {
         self.id.is_none()
     }
     /// Perform straightforward parameter deserialization.
+    ///
+    /// When a struct is deserialized from by-position parameters,
+    /// the values are matched to its fields in order.
+    /// Trailing fields may be omitted if they are [`Option`]s or `#[serde(default)]`,
+    /// but it is an error to pass more values than there are fields.
     pub fn deserialize_params<'de, T>(self) -> serde_json::Result<T>
     where
         T: Deserialize<'de>,
@@ -65,9 +161,102 @@ impl Request {
                 }
             }
 
+            fn deserialize_struct<V: serde::de::Visitor<'de>>(
+                self,
+                name: &'static str,
+                // These include aliases, so can't be matched with positions.
+                _fields: &'static [&'static str],
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                match self.0 {
+                    Some(RequestParameters::ByPosition(it)) => {
+                        let len = it.len();
+                        let mut seq = Positional {
+                            values: it.into_iter(),
+                            requested: 0,
+                        };
+                        let out = visitor.visit_seq(&mut seq)?;
+                        match seq.values.len() {
+                            0 => Ok(out),
+                            _ => {
+                                let expected = alloc::format!(
+                                    "at most {} parameters for {}",
+                                    seq.requested,
+                                    name
+                                );
+                                Err(serde::de::Error::invalid_length(len, &expected.as_str()))
+                            }
+                        }
+                    }
+                    other => Self(other).deserialize_any(visitor),
+                }
+            }
+
             serde::forward_to_deserialize_any! {
                 bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
                 bytes byte_buf option unit unit_struct newtype_struct seq tuple
+                tuple_struct map enum identifier ignored_any
+            }
+        }
+
+        /// By-position parameters for a struct, where trailing fields may be omitted.
+        struct Positional {
+            values: alloc::vec::IntoIter<Value>,
+            /// How many fields the struct asked for.
+            requested: usize,
+        }
+
+        impl<'de> serde::de::SeqAccess<'de> for Positional {
+            type Error = serde_json::Error;
+            fn next_element_seed<S: serde::de::DeserializeSeed<'de>>(
+                &mut self,
+                seed: S,
+            ) -> Result<Option<S::Value>, Self::Error> {
+                self.requested += 1;
+                match self.values.next() {
+                    Some(it) => seed.deserialize(it).map(Some),
+                    // `Option`s are `None`, and anything else is left to the struct,
+                    // which fills in `#[serde(default)]`s, and rejects the rest.
+                    None => Ok(seed.deserialize(Omitted).ok()),
+                }
+            }
+        }
+
+        /// A trailing parameter which wasn't passed.
+        #[derive(Debug)]
+        struct Omitted;
+
+        impl Display for Omitted {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                f.write_str("omitted parameter")
+            }
+        }
+
+        impl serde::de::StdError for Omitted {}
+
+        impl serde::de::Error for Omitted {
+            fn custom<T: Display>(_: T) -> Self {
+                Self
+            }
+        }
+
+        impl<'de> Deserializer<'de> for Omitted {
+            type Error = Self;
+            fn deserialize_any<V: serde::de::Visitor<'de>>(
+                self,
+                _: V,
+            ) -> Result<V::Value, Self::Error> {
+                Err(self)
+            }
+            fn deserialize_option<V: serde::de::Visitor<'de>>(
+                self,
+                visitor: V,
+            ) -> Result<V::Value, Self::Error> {
+                visitor.visit_none()
+            }
+            serde::forward_to_deserialize_any! {
+                bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+                bytes byte_buf unit unit_struct newtype_struct seq tuple
                 tuple_struct map struct enum identifier ignored_any
             }
         }
@@ -115,6 +304,70 @@ impl<'de> Deserialize<'de> for Request {
     }
 }
 
+/// > A Notification is a Request object without an "id" member.
+/// > A Request object that is a Notification signifies the Client's lack of interest
+/// > in the corresponding Response object, and as such no Response object needs to be returned to the client.
+/// > The Server MUST NOT reply to a Notification, including those that are within a batch request.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Notification {
+    /// See [`Request::jsonrpc`].
+    pub jsonrpc: V2,
+    /// See [`Request::method`].
+    pub method: String,
+    /// See [`Request::params`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<RequestParameters>,
+}
+
+impl From<Notification> for Request {
+    fn from(value: Notification) -> Self {
+        let Notification {
+            jsonrpc,
+            method,
+            params,
+        } = value;
+        Self {
+            jsonrpc,
+            method,
+            params,
+            id: None,
+        }
+    }
+}
+
+/// Fails if the [`Request`] has an [`Id`], returning it unchanged.
+impl TryFrom<Request> for Notification {
+    type Error = Request;
+
+    fn try_from(value: Request) -> Result<Self, Self::Error> {
+        match value {
+            Request {
+                jsonrpc,
+                method,
+                params,
+                id: None,
+            } => Ok(Self {
+                jsonrpc,
+                method,
+                params,
+            }),
+            other => Err(other),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Notification {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        Request::deserialize(deserializer)?
+            .try_into()
+            .map_err(|_| D::Error::custom("a notification must not have an `id` member"))
+    }
+}
+
 /// A witness of the literal string "2.0"
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct V2;
@@ -147,6 +400,7 @@ impl Serialize for V2 {
     untagged,
     expecting = "an `Array` of by-position paramaters, or an `Object` of by-name parameters"
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum RequestParameters {
     /// > params MUST be an Array, containing the values in the Server expected order.
     ByPosition(Vec<Value>),
@@ -158,6 +412,28 @@ pub enum RequestParameters {
 }
 
 impl RequestParameters {
+    /// The inverse of [`Request::deserialize_params`].
+    ///
+    /// Structs and maps become by-name parameters,
+    /// and tuples and sequences become by-position parameters.
+    /// Any other value is an error.
+    pub fn from_serialize<T>(params: &T) -> serde_json::Result<Self>
+    where
+        T: Serialize + ?Sized,
+    {
+        let unexpected = match serde_json::to_value(params)? {
+            Value::Array(it) => return Ok(Self::ByPosition(it)),
+            Value::Object(it) => return Ok(Self::ByName(it)),
+            Value::Null => "`null`",
+            Value::Bool(_) => "a boolean",
+            Value::Number(_) => "a number",
+            Value::String(_) => "a string",
+        };
+        Err(serde::ser::Error::custom(format_args!(
+            "parameters must serialize to an `Array` or an `Object`, not {}",
+            unexpected
+        )))
+    }
     pub fn len(&self) -> usize {
         match self {
             RequestParameters::ByPosition(it) => it.len(),
@@ -175,6 +451,7 @@ impl RequestParameters {
 /// See [`Request::id`].
 #[derive(Serialize, Debug, Clone, PartialEq, Eq, Hash, Deserialize, Default)]
 #[serde(untagged, expecting = "a string, a number, or null")]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum Id {
     String(String),
     Number(Number),
@@ -182,6 +459,36 @@ pub enum Id {
     Null,
 }
 
+impl From<String> for Id {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for Id {
+    fn from(value: &str) -> Self {
+        Self::String(value.into())
+    }
+}
+
+impl From<Number> for Id {
+    fn from(value: Number) -> Self {
+        Self::Number(value)
+    }
+}
+
+impl From<u64> for Id {
+    fn from(value: u64) -> Self {
+        Self::Number(value.into())
+    }
+}
+
+impl From<i64> for Id {
+    fn from(value: i64) -> Self {
+        Self::Number(value.into())
+    }
+}
+
 impl FromStr for Id {
     type Err = serde_json::Error;
 
@@ -214,6 +521,26 @@ pub struct Response {
     pub id: Id,
 }
 
+impl Response {
+    /// Discard the envelope, keeping only the outcome of the call.
+    pub fn into_result(self) -> Result<Value, Error> {
+        self.result
+    }
+    /// Perform straightforward result deserialization.
+    ///
+    /// The outer [`Result`] is from deserialization,
+    /// and the inner [`Result`] from the server.
+    pub fn deserialize_result<'de, T>(self) -> serde_json::Result<Result<T, Error>>
+    where
+        T: Deserialize<'de>,
+    {
+        match self.result {
+            Ok(it) => T::deserialize(it).map(Ok),
+            Err(e) => Ok(Err(e)),
+        }
+    }
+}
+
 impl Default for Response {
     fn default() -> Self {
         Self {
@@ -224,8 +551,19 @@ impl Default for Response {
     }
 }
 
-#[derive(Serialize, Deserialize)]
-struct RawResponseDeSer {
+/// Borrows from the response, so that the result isn't cloned.
+#[derive(Serialize)]
+pub(crate) struct RawResponseSer<'a, T: ?Sized> {
+    pub jsonrpc: V2,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<&'a T>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<&'a Error>,
+    pub id: &'a Id,
+}
+
+#[derive(Deserialize)]
+struct RawResponseDe {
     jsonrpc: V2,
     #[serde(default, deserialize_with = "deserialize_some")]
     result: Option<Option<Value>>,
@@ -253,22 +591,14 @@ impl Serialize for Response {
             jsonrpc,
             result,
             id,
-        } = self.clone();
-        let helper = match result {
-            Ok(result) => RawResponseDeSer {
-                jsonrpc,
-                result: Some(Some(result)),
-                error: None,
-                id,
-            },
-            Err(error) => RawResponseDeSer {
-                jsonrpc,
-                result: None,
-                error: Some(error),
-                id,
-            },
-        };
-        helper.serialize(serializer)
+        } = self;
+        RawResponseSer {
+            jsonrpc: *jsonrpc,
+            result: result.as_ref().ok(),
+            error: result.as_ref().err(),
+            id,
+        }
+        .serialize(serializer)
     }
 }
 
@@ -277,12 +607,12 @@ impl<'de> Deserialize<'de> for Response {
     where
         D: serde::Deserializer<'de>,
     {
-        let RawResponseDeSer {
+        let RawResponseDe {
             jsonrpc,
             error,
             result,
             id,
-        } = RawResponseDeSer::deserialize(deserializer)?;
+        } = RawResponseDe::deserialize(deserializer)?;
         match (result, error) {
             (Some(ok), None) => Ok(Response {
                 jsonrpc,
@@ -304,6 +634,7 @@ impl<'de> Deserialize<'de> for Response {
 
 /// A `JSON-RPC 2.0` error object.
 #[derive(Serialize, Debug, Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Error {
     /// > A Number that indicates the error type that occurred.
     /// > This MUST be an integer.
@@ -354,12 +685,47 @@ impl Error {
             INVALID_PARAMS / invalid_params = -32602;
             /// > Internal JSON-RPC error.
             INTERNAL_ERROR / internal_error = -32603;
+    }
 
+    // Not defined by the specification, but widely used.
+    error_code_and_ctor! {
+            /// A generic error, at the start of [`Self::SERVER_ERROR_RANGE`].
+            ///
+            /// Used by many implementations (e.g Ethereum clients) when no more specific code applies.
+            SERVER_ERROR / server_error = -32000;
+            /// The client cancelled the request, from the Language Server Protocol.
+            REQUEST_CANCELLED / request_cancelled = -32800;
+            /// The document changed while the request was being handled, from the Language Server Protocol.
+            CONTENT_MODIFIED / content_modified = -32801;
+            /// The server cancelled the request, from the Language Server Protocol.
+            SERVER_CANCELLED / server_cancelled = -32802;
+            /// The request was valid, but failed, from the Language Server Protocol.
+            REQUEST_FAILED / request_failed = -32803;
     }
 
     /// > Reserved for implementation-defined server-errors.
     pub const SERVER_ERROR_RANGE: RangeInclusive<i64> = -32099..=-32000;
 
+    /// > The error codes from and including -32768 to -32000 are reserved for pre-defined errors.
+    /// > Any code within this range, but not defined explicitly below is reserved for future use.
+    pub const RESERVED_RANGE: RangeInclusive<i64> = -32768..=-32000;
+
+    /// Classify [`Self::code`].
+    pub fn error_code(&self) -> ErrorCode {
+        ErrorCode::from(self.code)
+    }
+
+    /// Perform straightforward deserialization of [`Self::data`],
+    /// returning [`None`] if it is absent.
+    ///
+    /// See also [`error_data`] for helpers for common shapes.
+    pub fn deserialize_data<'de, T>(&'de self) -> serde_json::Result<Option<T>>
+    where
+        T: Deserialize<'de>,
+    {
+        self.data.as_ref().map(T::deserialize).transpose()
+    }
+
     /// Convenience method for creating a new error.
     pub fn new(code: i64, message: impl Display, data: impl Into<Option<Value>>) -> Self {
         Self {
@@ -370,6 +736,24 @@ impl Error {
     }
 }
 
+/// Formats as `{code}: {message}`, followed by compact `data`, if present.
+impl Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let Self {
+            code,
+            message,
+            data,
+        } = self;
+        write!(f, "{}: {}", code, message)?;
+        if let Some(data) = data {
+            write!(f, " ({})", data)?;
+        }
+        Ok(())
+    }
+}
+
+impl core::error::Error for Error {}
+
 impl<'de> Deserialize<'de> for Error {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
@@ -405,6 +789,7 @@ impl<'de> Deserialize<'de> for Error {
     expecting = "a single response object, or an Array of batched response objects"
 )]
 /// A response to a [`MaybeBatchedRequest`].
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum MaybeBatchedResponse {
     Single(Response),
     Batch(Vec<Response>),
@@ -416,7 +801,93 @@ pub enum MaybeBatchedResponse {
     untagged,
     expecting = "a single request object, or an Array of batched request objects"
 )]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum MaybeBatchedRequest {
     Single(Request),
     Batch(Vec<Request>),
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn params<T: for<'de> Deserialize<'de>>(params: Value) -> serde_json::Result<T> {
+        Request {
+            jsonrpc: V2,
+            method: String::from("m"),
+            params: Some(serde_json::from_value(params).unwrap()),
+            id: None,
+        }
+        .deserialize_params()
+    }
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Params {
+        #[serde(alias = "x")]
+        a: u32,
+        b: Option<u32>,
+        #[serde(default)]
+        c: u32,
+    }
+
+    #[test]
+    fn by_position() {
+        assert_eq!(
+            params::<Params>(json!([1, 2, 3])).unwrap(),
+            Params {
+                a: 1,
+                b: Some(2),
+                c: 3
+            }
+        );
+    }
+
+    #[test]
+    fn by_position_with_aliases() {
+        // Aliases used to be counted as fields, so `2` was taken as a second `a`.
+        assert_eq!(
+            params::<Params>(json!([1, 2])).unwrap(),
+            Params {
+                a: 1,
+                b: Some(2),
+                c: 0
+            }
+        );
+    }
+
+    #[test]
+    fn by_position_omitting_trailing() {
+        assert_eq!(
+            params::<Params>(json!([1])).unwrap(),
+            Params {
+                a: 1,
+                b: None,
+                c: 0
+            }
+        );
+        assert!(params::<Params>(json!([])).is_err());
+    }
+
+    #[test]
+    fn by_position_too_many() {
+        let e = params::<Params>(json!([1, 2, 3, 4])).unwrap_err();
+        assert_eq!(
+            e.to_string(),
+            "invalid length 4, expected at most 3 parameters for Params"
+        );
+    }
+
+    #[test]
+    fn by_name() {
+        assert_eq!(
+            params::<Params>(json!({"x": 1, "c": 3})).unwrap(),
+            Params {
+                a: 1,
+                b: None,
+                c: 3
+            }
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! Reject requests which are too large, before handling them.
+//!
+//! Servers should parse untrusted input with [`from_slice_with_limits`],
+//! so that clients can't exhaust their resources with oversized requests.
+
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+use serde::{
+    de::{self, DeserializeSeed, MapAccess, SeqAccess, Visitor},
+    Deserialize as _,
+};
+use serde_json::{error::Category, Map, Number, Value};
+
+use crate::{Error, MaybeBatchedRequest};
+
+/// Bounds on the size of a request, see [`from_slice_with_limits`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Limits {
+    /// The longest the raw body may be, in bytes.
+    pub max_body_len: usize,
+    /// The most members a batch may have.
+    pub max_batch_len: usize,
+    /// How deeply the `params` of a request may nest.
+    ///
+    /// Primitive parameters have a depth of zero, and each array or object adds one.
+    pub max_params_depth: usize,
+    /// The longest any string (including object keys) may be, in bytes.
+    pub max_string_len: usize,
+}
+
+/// Generous limits, which still bound resource usage:
+/// - bodies up to 10 MiB,
+/// - batches of up to 1000 members,
+/// - parameters nested up to 32 levels deep,
+/// - strings up to 1 MiB.
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_body_len: 10 * 1024 * 1024,
+            max_batch_len: 1000,
+            max_params_depth: 32,
+            max_string_len: 1024 * 1024,
+        }
+    }
+}
+
+/// Deserialize a request from `slice`, enforcing `limits`.
+///
+/// Failures are shaped for returning to the client:
+/// - Invalid JSON gets an [`Error::PARSE_ERROR`].
+/// - Anything else gets an [`Error::INVALID_REQUEST`].
+///
+/// The body length is checked before parsing, so memory usage is bounded by [`Limits::max_body_len`],
+/// and the other limits are checked as it's parsed, so parsing stops at the first violation.
+pub fn from_slice_with_limits(slice: &[u8], limits: &Limits) -> Result<MaybeBatchedRequest, Error> {
+    if slice.len() > limits.max_body_len {
+        return Err(Error::invalid_request(
+            format_args!("body is longer than {} bytes", limits.max_body_len),
+            None,
+        ));
+    }
+    let mut deserializer = serde_json::Deserializer::from_slice(slice);
+    let value = Limited {
+        limits,
+        level: Level::Body,
+    }
+    .deserialize(&mut deserializer)
+    .and_then(|it| deserializer.end().map(|()| it))
+    .map_err(|e| match e.classify() {
+        Category::Data => Error::invalid_request(e, None),
+        _ => Error::parse_error(e, None),
+    })?;
+    MaybeBatchedRequest::deserialize(value).map_err(|e| Error::invalid_request(e, None))
+}
+
+/// Deserializes a [`Value`], failing as soon as it exceeds the [`Limits`].
+#[derive(Clone, Copy)]
+struct Limited<'a> {
+    limits: &'a Limits,
+    level: Level,
+}
+
+#[derive(Clone, Copy)]
+enum Level {
+    /// The whole body, which may be a batch.
+    Body,
+    /// A request, whose `params` are limited in depth.
+    Member,
+    /// Any other value, which may nest this many more levels, or without limit.
+    Value(Option<usize>),
+}
+
+impl Limited<'_> {
+    fn at(self, level: Level) -> Self {
+        Self { level, ..self }
+    }
+    /// The level of the members of an array or object at this level.
+    fn nested<E: de::Error>(self) -> Result<Self, E> {
+        match self.level {
+            Level::Value(Some(0)) => Err(E::custom(format_args!(
+                "params nest more than {} deep",
+                self.limits.max_params_depth
+            ))),
+            Level::Value(it) => Ok(self.at(Level::Value(it.map(|it| it - 1)))),
+            Level::Body | Level::Member => Ok(self.at(Level::Value(None))),
+        }
+    }
+    fn string<E: de::Error>(self, s: &str) -> Result<String, E> {
+        match s.len() > self.limits.max_string_len {
+            true => Err(E::custom(format_args!(
+                "string of {} bytes is longer than {}",
+                s.len(),
+                self.limits.max_string_len
+            ))),
+            false => Ok(String::from(s)),
+        }
+    }
+}
+
+impl<'de> DeserializeSeed<'de> for Limited<'_> {
+    type Value = Value;
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        deserializer.deserialize_any(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Limited<'_> {
+    type Value = Value;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value")
+    }
+    fn visit_bool<E>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+    fn visit_i64<E>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_u64<E>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_f64<E>(self, v: f64) -> Result<Value, E> {
+        Ok(Number::from_f64(v).map_or(Value::Null, Value::Number))
+    }
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Value, E> {
+        self.string(v).map(Value::String)
+    }
+    fn visit_unit<E>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let (nested, batch) = match self.level {
+            Level::Body => (self.at(Level::Member), true),
+            _ => (self.nested()?, false),
+        };
+        let mut values = Vec::new();
+        while let Some(it) = seq.next_element_seed(nested)? {
+            if batch && values.len() == self.limits.max_batch_len {
+                return Err(de::Error::custom(format_args!(
+                    "batch is longer than {}",
+                    self.limits.max_batch_len
+                )));
+            }
+            values.push(it)
+        }
+        Ok(Value::Array(values))
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let nested = self.nested()?;
+        let mut values = Map::new();
+        while let Some(key) = map.next_key_seed(Key(self))? {
+            let seed = match (self.level, &*key) {
+                (Level::Body | Level::Member, "params") => {
+                    self.at(Level::Value(Some(self.limits.max_params_depth)))
+                }
+                _ => nested,
+            };
+            values.insert(key, map.next_value_seed(seed)?);
+        }
+        Ok(Value::Object(values))
+    }
+}
+
+/// An object key, limited like other strings.
+struct Key<'a>(Limited<'a>);
+
+impl<'de> DeserializeSeed<'de> for Key<'_> {
+    type Value = String;
+    fn deserialize<D: de::Deserializer<'de>>(self, deserializer: D) -> Result<String, D::Error> {
+        deserializer.deserialize_str(self)
+    }
+}
+
+impl<'de> Visitor<'de> for Key<'_> {
+    type Value = String;
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a string")
+    }
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<String, E> {
+        self.0.string(v)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+
+    use super::*;
+
+    #[track_caller]
+    fn parse(body: &str, limits: Limits) -> Result<MaybeBatchedRequest, Error> {
+        from_slice_with_limits(body.as_bytes(), &limits)
+    }
+
+    #[track_caller]
+    fn rejected(body: &str, limits: Limits) -> String {
+        let e = parse(body, limits).unwrap_err();
+        assert_eq!(e.code, Error::INVALID_REQUEST, "{}", e.message);
+        e.message
+    }
+
+    #[test]
+    fn body_len() {
+        let body = r#"{"jsonrpc": "2.0", "method": "m", "id": 1}"#;
+        let limits = |max_body_len| Limits {
+            max_body_len,
+            ..Limits::default()
+        };
+        assert!(parse(body, limits(body.len())).is_ok());
+        assert!(rejected(body, limits(body.len() - 1)).starts_with("body is longer"));
+    }
+
+    #[test]
+    fn batch_len() {
+        let call = r#"{"jsonrpc": "2.0", "method": "m", "id": 1}"#;
+        let limits = Limits {
+            max_batch_len: 2,
+            ..Limits::default()
+        };
+        assert!(parse(&format!("[{call}, {call}]"), limits).is_ok());
+        assert!(rejected(&format!("[{call}, {call}, {call}]"), limits)
+            .starts_with("batch is longer than 2"));
+    }
+
+    #[test]
+    fn params_depth() {
+        let limits = Limits {
+            max_params_depth: 2,
+            ..Limits::default()
+        };
+        let call = |params: &str| {
+            format!(r#"{{"jsonrpc": "2.0", "method": "m", "params": {params}, "id": 1}}"#)
+        };
+        assert!(parse(&call("[[1], {}]"), limits).is_ok());
+        assert!(parse(&format!("[{}]", call(r#"{"a": [1]}"#)), limits).is_ok());
+        assert!(rejected(&call("[[[1]]]"), limits).starts_with("params nest more than 2 deep"));
+        assert!(
+            rejected(&format!("[{}]", call(r#"{"a": {"b": {}}}"#)), limits)
+                .starts_with("params nest more than 2 deep")
+        );
+        // Deeper than `serde_json` would parse, but rejected before it gives up.
+        let deep = format!("{}{}", "[".repeat(1000), "]".repeat(1000));
+        assert!(rejected(&call(&deep), limits).starts_with("params nest more than 2 deep"));
+        // Only params are limited, and this fails for its id.
+        let id = rejected(
+            r#"{"jsonrpc": "2.0", "method": "m", "params": [], "id": [[[[1]]]]}"#,
+            limits,
+        );
+        assert!(!id.starts_with("params nest"), "{}", id);
+    }
+
+    #[test]
+    fn string_len() {
+        // Longer than the keys of a request.
+        let limits = Limits {
+            max_string_len: 8,
+            ..Limits::default()
+        };
+        let call =
+            |params: &str| format!(r#"{{"jsonrpc": "2.0", "method": "m", "params": {params}}}"#);
+        assert!(parse(&call(r#"["abcdefgh", {"abcdefgh": 1}]"#), limits).is_ok());
+        for it in [
+            r#"["abcdefghi"]"#,
+            r#"{"abcdefghi": 1}"#,
+            r#"[[{"a": "abcdefghi"}]]"#,
+        ] {
+            assert!(rejected(&call(it), limits).starts_with("string of 9 bytes is longer than 8"))
+        }
+        assert!(
+            rejected(r#"{"jsonrpc": "2.0", "method": "too_long_"}"#, limits)
+                .starts_with("string of 9 bytes")
+        );
+    }
+
+    #[test]
+    fn parse_error() {
+        for it in ["{", r#"{"jsonrpc": "2.0", "method": "m"} trailing"#, "nope"] {
+            let e = parse(it, Limits::default()).unwrap_err();
+            assert_eq!(e.code, Error::PARSE_ERROR, "{}", it);
+        }
+        rejected("1", Limits::default());
+        rejected(r#"{"jsonrpc": "2.0", "method": 1}"#, Limits::default());
+    }
+}
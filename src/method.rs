@@ -0,0 +1,224 @@
+//! Method names which follow the rules of the specification.
+//!
+//! > Method names that begin with the word rpc followed by a period character
+//! > (U+002E or ASCII 46) are reserved for rpc-internal methods and extensions
+//! > and MUST NOT be used for anything else.
+//!
+//! Use [`MethodName::new`] for application methods,
+//! and [`MethodName::extension`] to opt in to the reserved prefix.
+//!
+//! With the `glob` feature, `MethodGlob` matches families of methods, e.g `eth_*`.
+
+use alloc::string::String;
+#[cfg(feature = "glob")]
+use core::str::FromStr;
+use core::{fmt, ops::Deref};
+
+use crate::Request;
+
+/// The prefix reserved for rpc-internal methods and extensions.
+pub const RESERVED_PREFIX: &str = "rpc.";
+
+/// A method name which is not empty.
+///
+/// Unless created with [`Self::extension`], it also doesn't start with [`RESERVED_PREFIX`].
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct MethodName(String);
+
+impl MethodName {
+    /// A name for an application method.
+    pub fn new(name: impl Into<String>) -> Result<Self, MethodNameError> {
+        let name = name.into();
+        check(&name)?;
+        Ok(Self(name))
+    }
+    /// A name for an rpc-internal method or extension,
+    /// which is allowed to start with [`RESERVED_PREFIX`].
+    pub fn extension(name: impl Into<String>) -> Result<Self, MethodNameError> {
+        let name = name.into();
+        match check(&name) {
+            Ok(()) | Err(MethodNameError::Reserved) => Ok(Self(name)),
+            Err(e) => Err(e),
+        }
+    }
+    /// Whether this name starts with [`RESERVED_PREFIX`].
+    pub fn is_reserved(&self) -> bool {
+        self.0.starts_with(RESERVED_PREFIX)
+    }
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+fn check(name: &str) -> Result<(), MethodNameError> {
+    match (name.is_empty(), name.starts_with(RESERVED_PREFIX)) {
+        (true, _) => Err(MethodNameError::Empty),
+        (false, true) => Err(MethodNameError::Reserved),
+        (false, false) => Ok(()),
+    }
+}
+
+impl Deref for MethodName {
+    type Target = str;
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<str> for MethodName {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl fmt::Display for MethodName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl TryFrom<String> for MethodName {
+    type Error = MethodNameError;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl TryFrom<&str> for MethodName {
+    type Error = MethodNameError;
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        Self::new(value)
+    }
+}
+
+impl From<MethodName> for String {
+    fn from(value: MethodName) -> Self {
+        value.0
+    }
+}
+
+/// Why a [`MethodName`] was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MethodNameError {
+    /// The name was the empty string.
+    Empty,
+    /// The name started with [`RESERVED_PREFIX`], but wasn't an [extension](MethodName::extension).
+    Reserved,
+}
+
+impl fmt::Display for MethodNameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MethodNameError::Empty => f.write_str("method name is empty"),
+            MethodNameError::Reserved => write!(
+                f,
+                "method names starting with `{}` are reserved for extensions",
+                RESERVED_PREFIX
+            ),
+        }
+    }
+}
+
+impl core::error::Error for MethodNameError {}
+
+impl Request {
+    /// Check that [`Self::method`] is a valid application [`MethodName`].
+    ///
+    /// Servers which implement extensions should accept [`MethodNameError::Reserved`]
+    /// for the extensions they know about.
+    pub fn validate_method(&self) -> Result<(), MethodNameError> {
+        check(&self.method)
+    }
+}
+
+/// A pattern for method names, where `*` matches any sequence of characters,
+/// `?` matches any single character, and `[...]` matches any of the characters in the brackets,
+/// as for [`glob::Pattern`].
+///
+/// `*` and `?` don't match `/`, which some servers use to namespace methods.
+#[cfg(feature = "glob")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MethodGlob(glob::Pattern);
+
+#[cfg(feature = "glob")]
+impl MethodGlob {
+    pub fn new(pattern: &str) -> Result<Self, glob::PatternError> {
+        glob::Pattern::new(pattern).map(Self)
+    }
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+    pub fn matches(&self, method: &str) -> bool {
+        self.0.matches_with(
+            method,
+            glob::MatchOptions {
+                case_sensitive: true,
+                require_literal_separator: true,
+                require_literal_leading_dot: false,
+            },
+        )
+    }
+}
+
+#[cfg(feature = "glob")]
+impl fmt::Display for MethodGlob {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(feature = "glob")]
+impl FromStr for MethodGlob {
+    type Err = glob::PatternError;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(MethodName::new("eth_call").unwrap().as_str(), "eth_call");
+        assert_eq!(MethodName::new(""), Err(MethodNameError::Empty));
+        assert_eq!(
+            MethodName::new("rpc.discover"),
+            Err(MethodNameError::Reserved)
+        );
+        assert!(MethodName::extension("rpc.discover").unwrap().is_reserved());
+    }
+
+    #[test]
+    #[cfg(feature = "glob")]
+    fn globs() {
+        let matches =
+            |pattern: &str, method: &str| MethodGlob::new(pattern).unwrap().matches(method);
+        assert!(matches("eth_call", "eth_call"));
+        assert!(!matches("eth_call", "eth_calls"));
+        assert!(matches("eth_*", "eth_call"));
+        assert!(matches("eth_*", "eth_"));
+        assert!(!matches("eth_*", "net_version"));
+        assert!(matches("*", ""));
+        assert!(matches("eth_get*By*", "eth_getBlockByNumber"));
+        assert!(!matches("eth_get*By*", "eth_getBalance"));
+        // The first `Hash` isn't the last.
+        assert!(matches("*Hash", "eth_getBlockByHashHash"));
+        assert!(matches("eth_???", "eth_abc"));
+        assert!(!matches("eth_???", "eth_ab"));
+        assert!(matches("é?", "éü"));
+        assert!(matches("eth_[gs]et*", "eth_setX"));
+        assert!(!matches("eth_[!gs]et*", "eth_getX"));
+        assert!(matches("a[*]", "a*"));
+        assert!(!matches("a[*]", "ab"));
+        assert!(!matches("Eth_*", "eth_call"));
+        assert!(matches("ns/*", "ns/call"));
+        assert!(!matches("*", "ns/call"));
+        assert!(MethodGlob::new("eth_[").is_err());
+        assert_eq!(MethodGlob::new("eth_*").unwrap().to_string(), "eth_*");
+    }
+}
@@ -0,0 +1,111 @@
+//! Encode and decode messages as [MessagePack](https://msgpack.org), instead of JSON.
+//!
+//! Structs are encoded as maps, keyed by field name, so messages have the same shape as their JSON equivalents.
+//!
+//! The raw JSON in [`lazy`](crate::lazy) types is encoded as the value it holds,
+//! but types which borrow raw JSON (like those in [`borrowed`](crate::borrowed)) can't be encoded this way.
+
+use serde::{Deserialize, Serialize};
+
+pub use rmp_serde::{decode::Error as DecodeError, encode::Error as EncodeError};
+
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, EncodeError>
+where
+    T: Serialize + ?Sized,
+{
+    rmp_serde::to_vec_named(value)
+}
+
+pub fn from_slice<'a, T>(slice: &'a [u8]) -> Result<T, DecodeError>
+where
+    T: Deserialize<'a>,
+{
+    rmp_serde::from_slice(slice)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Debug;
+
+    use serde::de::DeserializeOwned;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{
+        lazy::{LazyRequest, LazyResponse},
+        Id, MaybeBatchedRequest, MaybeBatchedResponse, Request, Response,
+    };
+
+    fn round_trip<T>(value: T)
+    where
+        T: Serialize + DeserializeOwned + PartialEq + Debug,
+    {
+        assert_eq!(from_slice::<T>(&to_vec(&value).unwrap()).unwrap(), value)
+    }
+
+    /// Round trip `json` as a `T`, which isn't comparable, returning what it's decoded as.
+    fn round_trip_json<T>(json: Value) -> Value
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let value = serde_json::from_value::<T>(json.clone()).unwrap();
+        let bytes = to_vec(&value).unwrap();
+        let back = from_slice::<T>(&bytes).unwrap();
+        assert_eq!(serde_json::to_value(back).unwrap(), json);
+        from_slice::<Value>(&bytes).unwrap()
+    }
+
+    fn request() -> Value {
+        json!({"jsonrpc": "2.0", "method": "m", "params": [1, "two", {"three": [3.5]}], "id": 1})
+    }
+
+    fn response() -> Value {
+        json!({"jsonrpc": "2.0", "result": {"a": [1, null]}, "id": "x"})
+    }
+
+    fn error() -> Value {
+        json!({"jsonrpc": "2.0", "error": {"code": -32000, "message": "failed", "data": {"why": 1}}, "id": null})
+    }
+
+    #[test]
+    fn id() {
+        round_trip(Id::from_u64(1));
+        round_trip(Id::from("1"));
+        round_trip(Id::Null);
+    }
+
+    #[test]
+    fn request_and_response() {
+        round_trip(serde_json::from_value::<Request>(request()).unwrap());
+        round_trip(
+            serde_json::from_value::<Request>(
+                json!({"jsonrpc": "2.0", "method": "m", "params": {"a": 1}}),
+            )
+            .unwrap(),
+        );
+        round_trip(serde_json::from_value::<Response>(response()).unwrap());
+        round_trip(serde_json::from_value::<Response>(error()).unwrap());
+    }
+
+    #[test]
+    fn batch() {
+        round_trip(
+            serde_json::from_value::<MaybeBatchedRequest>(json!([
+                request(),
+                {"jsonrpc": "2.0", "method": "n"}
+            ]))
+            .unwrap(),
+        );
+        round_trip(
+            serde_json::from_value::<MaybeBatchedResponse>(json!([response(), error()])).unwrap(),
+        );
+    }
+
+    #[test]
+    fn raw() {
+        // The raw values are encoded as structures, not as JSON text.
+        assert_eq!(round_trip_json::<LazyRequest>(request()), request());
+        assert_eq!(round_trip_json::<LazyResponse>(response()), response());
+        assert_eq!(round_trip_json::<LazyResponse>(error()), error());
+    }
+}
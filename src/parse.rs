@@ -0,0 +1,141 @@
+//! Deserialize bodies, using [`simd-json`](https://docs.rs/simd-json) if the `simd-json` feature is enabled.
+//!
+//! `simd-json` is significantly faster for large bodies (like `eth_getLogs` results),
+//! but doesn't support [`RawValue`](serde_json::value::RawValue),
+//! so it can't be used for the types in [`lazy`](crate::lazy) or [`borrowed`](crate::borrowed).
+//!
+//! Serialization always uses `serde_json`.
+
+use core::fmt;
+
+use serde::de::DeserializeOwned;
+use serde_json::error::Category;
+
+/// An error from [`from_slice`] or [`from_mut_slice`].
+#[derive(Debug)]
+pub enum Error {
+    Json(serde_json::Error),
+    #[cfg(feature = "simd-json")]
+    Simd(simd_json::Error),
+}
+
+impl Error {
+    /// Whether the body wasn't JSON, or wasn't a `T`, like [`serde_json::Error::classify`].
+    pub fn classify(&self) -> Category {
+        match self {
+            Error::Json(e) => e.classify(),
+            #[cfg(feature = "simd-json")]
+            Error::Simd(e) => {
+                use simd_json::ErrorType;
+                match e.error() {
+                    ErrorType::Eof => Category::Eof,
+                    ErrorType::Io(_) => Category::Io,
+                    ErrorType::Serde(_)
+                    | ErrorType::Unexpected(..)
+                    | ErrorType::BadKeyType
+                    | ErrorType::ExpectedArray
+                    | ErrorType::ExpectedBoolean
+                    | ErrorType::ExpectedEnum
+                    | ErrorType::ExpectedFloat
+                    | ErrorType::ExpectedInteger
+                    | ErrorType::ExpectedMap
+                    | ErrorType::ExpectedNull
+                    | ErrorType::ExpectedNumber
+                    | ErrorType::ExpectedSigned
+                    | ErrorType::ExpectedString
+                    | ErrorType::ExpectedUnsigned
+                    | ErrorType::Overflow => Category::Data,
+                    _ => Category::Syntax,
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Json(e) => e.fmt(f),
+            #[cfg(feature = "simd-json")]
+            Error::Simd(e) => e.fmt(f),
+        }
+    }
+}
+
+impl core::error::Error for Error {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            Error::Json(e) => Some(e),
+            #[cfg(feature = "simd-json")]
+            Error::Simd(e) => Some(e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+/// Deserialize `body` as a `T`.
+///
+/// With `simd-json`, `body` is first copied, see [`from_mut_slice`] to avoid this.
+pub fn from_slice<T: DeserializeOwned>(body: &[u8]) -> Result<T, Error> {
+    #[cfg(feature = "simd-json")]
+    {
+        from_mut_slice(&mut body.to_vec())
+    }
+    #[cfg(not(feature = "simd-json"))]
+    {
+        serde_json::from_slice(body).map_err(Error::Json)
+    }
+}
+
+/// Deserialize `body` as a `T`.
+///
+/// With `simd-json`, `body` is used as scratch space, so its contents are unspecified afterwards.
+pub fn from_mut_slice<T: DeserializeOwned>(body: &mut [u8]) -> Result<T, Error> {
+    #[cfg(feature = "simd-json")]
+    {
+        simd_json::serde::from_slice(body).map_err(Error::Simd)
+    }
+    #[cfg(not(feature = "simd-json"))]
+    {
+        serde_json::from_slice(body).map_err(Error::Json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::MaybeBatchedRequest;
+
+    use super::*;
+
+    fn classify(body: &str) -> Category {
+        from_slice::<MaybeBatchedRequest>(body.as_bytes())
+            .unwrap_err()
+            .classify()
+    }
+
+    #[test]
+    fn classify_errors() {
+        assert_eq!(
+            classify(r#"{"jsonrpc": "2.0", "method": }"#),
+            Category::Syntax
+        );
+        assert_eq!(
+            classify(r#"{"jsonrpc": "2.0" "method": "m"}"#),
+            Category::Syntax
+        );
+        assert_eq!(
+            classify(r#"{"jsonrpc": "2.0", "method": 1}"#),
+            Category::Data
+        );
+        assert_eq!(
+            classify(r#"{"jsonrpc": "1.0", "method": "m"}"#),
+            Category::Data
+        );
+        assert_eq!(classify("1"), Category::Data);
+    }
+}
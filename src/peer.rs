@@ -0,0 +1,436 @@
+//! Both call, and be called by, the other end of a connection.
+//!
+//! Many protocols (like the Language Server Protocol, or the Chrome DevTools Protocol)
+//! have servers which send requests back to the client over the same connection.
+//! A [`Peer`] sends outgoing calls, matching responses by [`Id`],
+//! while handling incoming requests with a [`Router`].
+//!
+//! The transport is any [`Stream`] and [`Sink`] of [`Message`]s,
+//! like a [`Framed`](https://docs.rs/tokio-util/latest/tokio_util/codec/struct.Framed.html)
+//! using one of the [`codec`](crate::codec)s.
+//!
+//! # Cancellation
+//! Peers follow the [`$/cancelRequest`](CANCEL_REQUEST) convention of the Language Server Protocol.
+//! - Outgoing calls may be cancelled with [`Peer::cancel`].
+//! - Incoming calls are given a [`CancellationToken`] in their [`router::Context`],
+//!   which is cancelled when the other end sends a `$/cancelRequest` for them.
+//!   The members of batches can't be cancelled.
+//!
+//! # Progress
+//! Peers follow the [`$/progress`](PROGRESS) convention of the Language Server Protocol.
+//! - A client registers a token with [`Peer::progress`], and includes it in the parameters of a call,
+//!   as the protocol requires.
+//! - A server reports progress for that token with [`router::Context::progress`].
+
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use futures::{
+    channel::{mpsc, oneshot},
+    select,
+    stream::FuturesUnordered,
+    Sink, SinkExt as _, Stream, StreamExt as _,
+};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{Map, Value};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    id::{IdGenerator as _, Sequential},
+    router::{self, Router},
+    Id, MaybeBatchedRequest, MaybeBatchedResponse, Request, RequestParameters, Response, V2,
+};
+
+/// The method of a notification that the sender is no longer interested in the result of a call.
+///
+/// The parameters are `{"id": <id of the call>}`.
+pub const CANCEL_REQUEST: &str = "$/cancelRequest";
+
+/// The method of a notification reporting progress (or partial results) for a call.
+///
+/// The parameters are `{"token": <token>, "value": <payload>}`.
+pub const PROGRESS: &str = "$/progress";
+
+/// Anything that may be sent over a connection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, expecting = "a request, or a response")]
+pub enum Message {
+    Request(MaybeBatchedRequest),
+    Response(MaybeBatchedResponse),
+}
+
+/// An error from calling the other end of a connection.
+#[derive(Debug)]
+pub enum PeerError {
+    /// The connection closed before a response was received.
+    Closed,
+    /// A successful result could not be deserialized.
+    ///
+    /// Only returned by typed calls.
+    Deserialize(serde_json::Error),
+    /// The other end responded with an error object.
+    ///
+    /// Only returned by typed calls.
+    Server(crate::Error),
+}
+
+impl fmt::Display for PeerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeerError::Closed => f.write_str("connection closed"),
+            PeerError::Deserialize(e) => write!(f, "invalid result: {}", e),
+            PeerError::Server(e) => write!(f, "server returned error {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PeerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PeerError::Closed => None,
+            PeerError::Deserialize(e) => Some(e),
+            PeerError::Server(e) => Some(e),
+        }
+    }
+}
+
+/// A handle for calling the other end of a connection.
+///
+/// Cheap to clone, with all clones sharing the connection.
+#[derive(Debug, Clone)]
+pub struct Peer {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    ids: Sequential,
+    /// Outgoing calls, awaiting a response.
+    pending: Mutex<HashMap<Id, oneshot::Sender<Response>>>,
+    /// Incoming calls, which haven't been responded to.
+    in_flight: Mutex<HashMap<Id, CancellationToken>>,
+    /// Registered progress tokens.
+    progress: Mutex<HashMap<Id, mpsc::UnboundedSender<Value>>>,
+    outgoing: mpsc::UnboundedSender<Message>,
+}
+
+impl Peer {
+    /// Returns a [`Peer`] for `transport`, and a future which drives it.
+    ///
+    /// The future must be polled (e.g. spawned onto a runtime) for calls to make progress.
+    /// It handles incoming requests with `router`,
+    /// and completes when `transport` is closed, or fails.
+    /// Outstanding calls then fail with [`PeerError::Closed`].
+    pub fn new<T, E>(transport: T, router: Router) -> (Self, impl Future<Output = Result<(), E>>)
+    where
+        T: Stream<Item = Result<Message, E>> + Sink<Message, Error = E>,
+    {
+        let (outgoing, rx) = mpsc::unbounded();
+        let shared = Arc::new(Shared {
+            ids: Sequential::new(),
+            pending: Mutex::default(),
+            in_flight: Mutex::default(),
+            progress: Mutex::default(),
+            outgoing,
+        });
+        let peer = Self {
+            shared: shared.clone(),
+        };
+        (peer, drive(transport, router, shared, rx))
+    }
+    /// Call `method`, with an automatically assigned [`Id`].
+    pub async fn call(
+        &self,
+        method: impl Into<String>,
+        params: impl Into<Option<RequestParameters>>,
+    ) -> Result<Response, PeerError> {
+        self.start(method, params)?.await
+    }
+    /// Send a call for `method`, returning a future for its response.
+    ///
+    /// Use this instead of [`Self::call`] to learn the [`Id`] of the call, e.g to [`Self::cancel`] it.
+    pub fn start(
+        &self,
+        method: impl Into<String>,
+        params: impl Into<Option<RequestParameters>>,
+    ) -> Result<PendingCall, PeerError> {
+        let id = self.shared.ids.next_id();
+        let (tx, rx) = oneshot::channel();
+        self.shared.pending().insert(id.clone(), tx);
+        let request = Request {
+            jsonrpc: V2,
+            method: method.into(),
+            params: params.into(),
+            id: Some(id.clone()),
+        };
+        if self.shared.send(request).is_err() {
+            self.shared.pending().remove(&id);
+            return Err(PeerError::Closed);
+        }
+        Ok(PendingCall { id, rx })
+    }
+    /// Register a new progress token, returning a stream of the `value`s reported for it.
+    ///
+    /// The token is unregistered when the [`Progress`] is dropped.
+    pub fn progress(&self) -> Progress {
+        let token = self.shared.ids.next_id();
+        let (tx, rx) = mpsc::unbounded();
+        self.shared.progress().insert(token.clone(), tx);
+        Progress {
+            token,
+            rx,
+            shared: self.shared.clone(),
+        }
+    }
+    /// A handle for reporting progress for `token` to the other end.
+    pub fn progress_sender(&self, token: Id) -> ProgressSender {
+        ProgressSender {
+            token,
+            peer: self.clone(),
+        }
+    }
+    /// Ask the other end to stop working on the call with the given `id`.
+    ///
+    /// The other end should still respond to the call, usually with an error.
+    pub fn cancel(&self, id: &Id) -> Result<(), PeerError> {
+        let id = serde_json::to_value(id).expect("ids always serialize");
+        self.notify(
+            CANCEL_REQUEST,
+            RequestParameters::ByName(Map::from_iter([(String::from("id"), id)])),
+        )
+    }
+    /// Call `method`, deserializing a successful result as a `T`.
+    pub async fn call_typed<T: DeserializeOwned>(
+        &self,
+        method: impl Into<String>,
+        params: impl Into<Option<RequestParameters>>,
+    ) -> Result<T, PeerError> {
+        match self.call(method, params).await?.result {
+            Ok(it) => serde_json::from_value(it).map_err(PeerError::Deserialize),
+            Err(e) => Err(PeerError::Server(e)),
+        }
+    }
+    /// Send a notification for `method`, which the other end won't respond to.
+    pub fn notify(
+        &self,
+        method: impl Into<String>,
+        params: impl Into<Option<RequestParameters>>,
+    ) -> Result<(), PeerError> {
+        self.shared
+            .send(Request {
+                jsonrpc: V2,
+                method: method.into(),
+                params: params.into(),
+                id: None,
+            })
+            .map_err(|_| PeerError::Closed)
+    }
+}
+
+/// A call that has been sent, awaiting its response.
+#[derive(Debug)]
+pub struct PendingCall {
+    id: Id,
+    rx: oneshot::Receiver<Response>,
+}
+
+impl PendingCall {
+    pub fn id(&self) -> &Id {
+        &self.id
+    }
+}
+
+impl Future for PendingCall {
+    type Output = Result<Response, PeerError>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        Pin::new(&mut self.rx)
+            .poll(cx)
+            .map_err(|_| PeerError::Closed)
+    }
+}
+
+/// A stream of progress reported by the other end, see [`Peer::progress`].
+#[derive(Debug)]
+pub struct Progress {
+    token: Id,
+    rx: mpsc::UnboundedReceiver<Value>,
+    shared: Arc<Shared>,
+}
+
+impl Progress {
+    /// Include this in the parameters of a call.
+    pub fn token(&self) -> &Id {
+        &self.token
+    }
+}
+
+impl Stream for Progress {
+    type Item = Value;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_next_unpin(cx)
+    }
+}
+
+impl Drop for Progress {
+    fn drop(&mut self) {
+        self.shared.progress().remove(&self.token);
+    }
+}
+
+/// Reports progress for a token to the other end, see [`router::Context::progress`].
+#[derive(Debug, Clone)]
+pub struct ProgressSender {
+    token: Id,
+    peer: Peer,
+}
+
+impl ProgressSender {
+    pub fn token(&self) -> &Id {
+        &self.token
+    }
+    /// Send a `$/progress` notification with the given `value`.
+    pub fn send(&self, value: Value) -> Result<(), PeerError> {
+        let Self { token, peer } = self;
+        let Ok(Value::Object(params)) = serde_json::to_value(ProgressParams { token, value })
+        else {
+            unreachable!("structs of values serialize to objects")
+        };
+        peer.notify(PROGRESS, RequestParameters::ByName(params))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProgressParams<T, V> {
+    token: T,
+    value: V,
+}
+
+impl Shared {
+    fn pending(&self) -> std::sync::MutexGuard<'_, HashMap<Id, oneshot::Sender<Response>>> {
+        self.pending.lock().expect("poisoned")
+    }
+    fn progress(&self) -> std::sync::MutexGuard<'_, HashMap<Id, mpsc::UnboundedSender<Value>>> {
+        self.progress.lock().expect("poisoned")
+    }
+    fn in_flight(&self) -> std::sync::MutexGuard<'_, HashMap<Id, CancellationToken>> {
+        self.in_flight.lock().expect("poisoned")
+    }
+    fn send(&self, request: Request) -> Result<(), mpsc::TrySendError<Message>> {
+        self.outgoing
+            .unbounded_send(Message::Request(MaybeBatchedRequest::Single(request)))
+    }
+    /// Responses we aren't waiting for are ignored.
+    fn complete(&self, response: Response) {
+        if let Some(tx) = self.pending().remove(&response.id) {
+            let _ = tx.send(response);
+        }
+    }
+}
+
+async fn drive<T, E>(
+    transport: T,
+    router: Router,
+    shared: Arc<Shared>,
+    mut rx: mpsc::UnboundedReceiver<Message>,
+) -> Result<(), E>
+where
+    T: Stream<Item = Result<Message, E>> + Sink<Message, Error = E>,
+{
+    let (mut sink, stream) = transport.split();
+    let mut incoming = stream.fuse();
+    let mut handling = FuturesUnordered::new();
+    let result = loop {
+        select! {
+            message = incoming.next() => match message {
+                Some(Ok(Message::Request(it))) => handling.push(handle(&router, &shared, it)),
+                Some(Ok(Message::Response(MaybeBatchedResponse::Single(it)))) => shared.complete(it),
+                Some(Ok(Message::Response(MaybeBatchedResponse::Batch(it)))) => {
+                    it.into_iter().for_each(|it| shared.complete(it))
+                }
+                Some(Err(e)) => break Err(e),
+                None => break Ok(()),
+            },
+            message = rx.select_next_some() => {
+                if let Err(e) = sink.send(message).await {
+                    break Err(e);
+                }
+            }
+            response = handling.select_next_some() => {
+                if let Some(it) = response {
+                    if let Err(e) = sink.send(Message::Response(it)).await {
+                        break Err(e);
+                    }
+                }
+            }
+        }
+    };
+    // Close the channel before failing pending calls, so none can be added after.
+    rx.close();
+    shared.pending().clear();
+    result
+}
+
+async fn handle(
+    router: &Router,
+    shared: &Arc<Shared>,
+    request: MaybeBatchedRequest,
+) -> Option<MaybeBatchedResponse> {
+    let request = match request {
+        MaybeBatchedRequest::Single(it) => it,
+        batch => return router.handle_batch(batch).await,
+    };
+    let cx = router::Context::default().with_peer(Peer {
+        shared: shared.clone(),
+    });
+    match request.id.clone() {
+        None if request.method == CANCEL_REQUEST => {
+            #[derive(Deserialize)]
+            struct Params {
+                id: Id,
+            }
+            // Malformed cancellations are ignored, as for any other notification.
+            if let Ok(Params { id }) = request.deserialize_params() {
+                if let Some(token) = shared.in_flight().get(&id) {
+                    token.cancel()
+                }
+            }
+            None
+        }
+        None if request.method == PROGRESS => {
+            // Progress for tokens we didn't register is left to the router.
+            if let Ok(ProgressParams::<Id, Value> { token, value }) =
+                request.clone().deserialize_params()
+            {
+                if let Some(tx) = shared.progress().get(&token) {
+                    let _ = tx.unbounded_send(value);
+                    return None;
+                }
+            }
+            router
+                .handle_with(request, cx)
+                .await
+                .map(MaybeBatchedResponse::Single)
+        }
+        None => router
+            .handle_with(request, cx)
+            .await
+            .map(MaybeBatchedResponse::Single),
+        Some(id) => {
+            let token = CancellationToken::new();
+            shared.in_flight().insert(id.clone(), token.clone());
+            let response = router
+                .handle_with(request, cx.with_cancellation(token))
+                .await;
+            shared.in_flight().remove(&id);
+            response.map(MaybeBatchedResponse::Single)
+        }
+    }
+}
@@ -0,0 +1,267 @@
+//! Dispatch requests to handlers by method name.
+//!
+//! Handlers are `async` functions of their (deserialized) parameters,
+//! and errors are mapped to the codes given by the specification.
+
+use std::{
+    collections::HashMap,
+    fmt,
+    future::{poll_fn, Future},
+    pin::Pin,
+    sync::Arc,
+    task::Poll,
+};
+
+use serde::{de::DeserializeOwned, Deserialize as _, Serialize};
+use serde_json::Value;
+#[cfg(feature = "peer")]
+use tokio_util::sync::CancellationToken;
+
+#[cfg(feature = "peer")]
+use crate::peer::{Peer, ProgressSender};
+
+use crate::{
+    method::MethodNameError, Error, Id, MaybeBatchedRequest, MaybeBatchedResponse, Request,
+    Response, V2,
+};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+type ErasedHandler = dyn Fn(Request, Context) -> BoxFuture<Result<Value, Error>> + Send + Sync;
+
+/// Per-request state, passed to handlers registered with [`Router::method_with_context`].
+#[derive(Debug, Clone, Default)]
+pub struct Context {
+    #[cfg(feature = "peer")]
+    cancellation: CancellationToken,
+    #[cfg(feature = "peer")]
+    peer: Option<Peer>,
+}
+
+#[cfg(feature = "peer")]
+impl Context {
+    /// Use `token` to signal that the client is no longer interested in the result.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = token;
+        self
+    }
+    /// Cancelled when the client is no longer interested in the result,
+    /// e.g when a [`Peer`] receives a `$/cancelRequest`.
+    ///
+    /// Handlers may stop early, and should then return an [`Error::REQUEST_CANCELLED`].
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+    /// The connection the request arrived on.
+    pub fn with_peer(mut self, peer: Peer) -> Self {
+        self.peer = Some(peer);
+        self
+    }
+    /// The connection the request arrived on, if it was handled by a [`Peer`].
+    pub fn peer(&self) -> Option<&Peer> {
+        self.peer.as_ref()
+    }
+    /// Report progress for the given `token` back to the client, if it was handled by a [`Peer`].
+    ///
+    /// How the client chooses the `token` depends on the protocol.
+    pub fn progress(&self, token: Id) -> Option<ProgressSender> {
+        self.peer.clone().map(|it| it.progress_sender(token))
+    }
+}
+
+/// A collection of handlers, keyed by method name.
+///
+/// Cheap to clone.
+#[derive(Clone, Default)]
+pub struct Router {
+    methods: Arc<HashMap<String, Arc<ErasedHandler>>>,
+}
+
+impl fmt::Debug for Router {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Router")
+            .field("methods", &self.methods.keys())
+            .finish()
+    }
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Register `handler` for calls to `name`, replacing any existing handler.
+    ///
+    /// Names starting with [`RESERVED_PREFIX`](crate::method::RESERVED_PREFIX) should only be used for extensions.
+    ///
+    /// Parameters are deserialized with [`Request::deserialize_params`],
+    /// and mapped to [`Error::INVALID_PARAMS`] on failure.
+    pub fn method<P, R, F, Fut>(self, name: impl Into<String>, handler: F) -> Self
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        F: Fn(P) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, Error>> + Send + 'static,
+    {
+        self.method_with_context(name, move |params, _| handler(params))
+    }
+    /// Like [`Self::method`], but `handler` also receives the [`Context`] of the request.
+    pub fn method_with_context<P, R, F, Fut>(mut self, name: impl Into<String>, handler: F) -> Self
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        F: Fn(P, Context) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, Error>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        Arc::make_mut(&mut self.methods).insert(
+            name.into(),
+            Arc::new(move |request: Request, cx: Context| {
+                let handler = handler.clone();
+                Box::pin(async move {
+                    let params = request
+                        .deserialize_params::<P>()
+                        .map_err(|e| Error::invalid_params(e, None))?;
+                    let result = handler(params, cx).await?;
+                    serde_json::to_value(result).map_err(|e| Error::internal_error(e, None))
+                }) as BoxFuture<_>
+            }),
+        );
+        self
+    }
+    /// The names of all registered methods.
+    pub fn methods(&self) -> impl Iterator<Item = &str> {
+        self.methods.keys().map(String::as_str)
+    }
+    /// Handle a single request.
+    ///
+    /// Returns [`None`] for notifications.
+    pub async fn handle(&self, request: Request) -> Option<Response> {
+        self.handle_with(request, Context::default()).await
+    }
+    /// Handle a single request, with the given [`Context`].
+    ///
+    /// Returns [`None`] for notifications.
+    pub async fn handle_with(&self, request: Request, cx: Context) -> Option<Response> {
+        let id = request.id.clone();
+        let result = match self.methods.get(&request.method) {
+            Some(handler) => handler(request, cx).await,
+            None if request.validate_method() == Err(MethodNameError::Empty) => {
+                Err(Error::invalid_request(MethodNameError::Empty, None))
+            }
+            None => Err(Error::method_not_found(
+                format_args!("method `{}` not found", request.method),
+                None,
+            )),
+        };
+        Some(Response {
+            jsonrpc: V2,
+            result,
+            id: id?,
+        })
+    }
+    /// Handle a request, running the members of a batch concurrently.
+    ///
+    /// Returns [`None`] if there is nothing to respond with,
+    /// i.e the request was a notification, or a batch of notifications.
+    pub async fn handle_batch(&self, request: MaybeBatchedRequest) -> Option<MaybeBatchedResponse> {
+        match request {
+            MaybeBatchedRequest::Single(it) => {
+                self.handle(it).await.map(MaybeBatchedResponse::Single)
+            }
+            MaybeBatchedRequest::Batch(it) if it.is_empty() => {
+                Some(MaybeBatchedResponse::Single(invalid_request("empty batch")))
+            }
+            MaybeBatchedRequest::Batch(it) => {
+                let responses = join_all(it.into_iter().map(|it| self.handle(it)).collect())
+                    .await
+                    .into_iter()
+                    .flatten()
+                    .collect::<Vec<_>>();
+                match responses.is_empty() {
+                    true => None,
+                    false => Some(MaybeBatchedResponse::Batch(responses)),
+                }
+            }
+        }
+    }
+    /// Handle a raw request body.
+    ///
+    /// Unlike [`Self::handle_batch`], malformed requests are answered according to the specification:
+    /// - Invalid JSON gets an [`Error::PARSE_ERROR`].
+    /// - Valid JSON which isn't a request gets an [`Error::INVALID_REQUEST`].
+    ///   If it is a member of a batch, the other members are still handled.
+    pub async fn handle_slice(&self, body: &[u8]) -> Option<MaybeBatchedResponse> {
+        let value = match serde_json::from_slice::<Value>(body) {
+            Ok(it) => it,
+            Err(e) => {
+                return Some(MaybeBatchedResponse::Single(error_response(
+                    Error::parse_error(e, None),
+                )))
+            }
+        };
+        match value {
+            Value::Array(members) if !members.is_empty() => {
+                let responses = join_all(
+                    members
+                        .into_iter()
+                        .map(|member| async move {
+                            match Request::deserialize(member) {
+                                Ok(it) => self.handle(it).await,
+                                Err(e) => Some(invalid_request(e)),
+                            }
+                        })
+                        .collect(),
+                )
+                .await
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>();
+                match responses.is_empty() {
+                    true => None,
+                    false => Some(MaybeBatchedResponse::Batch(responses)),
+                }
+            }
+            other => match MaybeBatchedRequest::deserialize(other) {
+                Ok(it) => self.handle_batch(it).await,
+                Err(e) => Some(MaybeBatchedResponse::Single(invalid_request(e))),
+            },
+        }
+    }
+}
+
+fn error_response(error: Error) -> Response {
+    Response {
+        jsonrpc: V2,
+        result: Err(error),
+        id: Id::Null,
+    }
+}
+
+fn invalid_request(message: impl fmt::Display) -> Response {
+    error_response(Error::invalid_request(message, None))
+}
+
+/// Poll all `futures` to completion, returning their outputs in order.
+async fn join_all<F: Future>(futures: Vec<F>) -> Vec<F::Output> {
+    let mut futures = futures.into_iter().map(Box::pin).collect::<Vec<_>>();
+    let mut outputs = futures.iter().map(|_| None).collect::<Vec<_>>();
+    poll_fn(|cx| {
+        let mut done = true;
+        for (future, output) in futures.iter_mut().zip(&mut outputs) {
+            if output.is_none() {
+                match future.as_mut().poll(cx) {
+                    Poll::Ready(it) => *output = Some(it),
+                    Poll::Pending => done = false,
+                }
+            }
+        }
+        match done {
+            true => Poll::Ready(()),
+            false => Poll::Pending,
+        }
+    })
+    .await;
+    outputs
+        .into_iter()
+        .map(|it| it.expect("polled to completion"))
+        .collect()
+}
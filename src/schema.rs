@@ -0,0 +1,56 @@
+//! [`JsonSchema`] implementations for types with hand-written serialization.
+//!
+//! The rest are derived alongside their definitions.
+
+use schemars::{gen::SchemaGenerator, schema::Schema, JsonSchema};
+use serde_json::json;
+
+use crate::{Error, Id, Response, V2};
+
+fn from_json(value: serde_json::Value) -> Schema {
+    serde_json::from_value(value).expect("schema literals are valid")
+}
+
+fn to_json(schema: Schema) -> serde_json::Value {
+    serde_json::to_value(schema).expect("schemas always serialize")
+}
+
+impl JsonSchema for V2 {
+    fn schema_name() -> String {
+        "V2".into()
+    }
+    fn json_schema(_: &mut SchemaGenerator) -> Schema {
+        from_json(json!({
+            "description": "A witness of the literal string \"2.0\"",
+            "type": "string",
+            "const": "2.0",
+        }))
+    }
+}
+
+/// Exactly one of `result` and `error` is present.
+impl JsonSchema for Response {
+    fn schema_name() -> String {
+        "Response".into()
+    }
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        let jsonrpc = to_json(gen.subschema_for::<V2>());
+        let id = to_json(gen.subschema_for::<Id>());
+        let error = to_json(gen.subschema_for::<Error>());
+        from_json(json!({
+            "description": "A `JSON-RPC 2.0` response object.",
+            "type": "object",
+            "properties": {
+                "jsonrpc": jsonrpc,
+                "result": {},
+                "error": error,
+                "id": id,
+            },
+            "required": ["jsonrpc", "id"],
+            "oneOf": [
+                { "required": ["result"], "not": { "required": ["error"] } },
+                { "required": ["error"], "not": { "required": ["result"] } },
+            ],
+        }))
+    }
+}
@@ -0,0 +1,84 @@
+//! [`Service`] implementations, for use with `tower` middleware.
+//!
+//! Responses are [`Option`]al, because notifications (and batches of them) get no response.
+
+use core::{
+    convert::Infallible,
+    future::Future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use tower_service::Service;
+
+use crate::{router::Router, MaybeBatchedRequest, MaybeBatchedResponse, Request, Response};
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+
+/// See [`Router::handle`].
+impl Service<Request> for Router {
+    type Response = Option<Response>;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request) -> Self::Future {
+        let router = self.clone();
+        Box::pin(async move { Ok(router.handle(request).await) })
+    }
+}
+
+/// See [`Router::handle_batch`].
+impl Service<MaybeBatchedRequest> for Router {
+    type Response = Option<MaybeBatchedResponse>;
+    type Error = Infallible;
+    type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: MaybeBatchedRequest) -> Self::Future {
+        let router = self.clone();
+        Box::pin(async move { Ok(router.handle_batch(request).await) })
+    }
+}
+
+#[cfg(feature = "async")]
+mod client {
+    use super::*;
+    use crate::client::{AsyncClient, ClientError};
+
+    /// Sends `request` as-is, so the caller is responsible for assigning [`Id`](crate::Id)s.
+    impl Service<Request> for AsyncClient {
+        type Response = Option<Response>;
+        type Error = ClientError;
+        type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: Request) -> Self::Future {
+            Box::pin(self.post(&request))
+        }
+    }
+
+    /// See [`AsyncClient::send`].
+    impl Service<MaybeBatchedRequest> for AsyncClient {
+        type Response = Option<MaybeBatchedResponse>;
+        type Error = ClientError;
+        type Future = BoxFuture<Result<Self::Response, Self::Error>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: MaybeBatchedRequest) -> Self::Future {
+            Box::pin(self.post(&request))
+        }
+    }
+}
@@ -0,0 +1,121 @@
+//! [`proptest`] strategies, which only produce values that are valid according to the specification.
+
+use proptest::{collection, option, prelude::*};
+use serde_json::{Map, Number, Value};
+
+use crate::{
+    Error, Id, MaybeBatchedRequest, MaybeBatchedResponse, Notification, Request, RequestParameters,
+    Response, V2,
+};
+
+/// The most members a structured value, or batch, may have.
+const MAX_LEN: usize = 4;
+
+/// Any JSON value, nested up to three levels deep.
+pub fn value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(|it| Value::Number(it.into())),
+        proptest::num::f64::NORMAL
+            .prop_filter_map("must round-trip", |it| float(it).map(Value::Number)),
+        any::<String>().prop_map(Value::String),
+    ];
+    leaf.prop_recursive(3, 64, MAX_LEN as u32, |inner| {
+        prop_oneof![
+            collection::vec(inner.clone(), 0..=MAX_LEN).prop_map(Value::Array),
+            collection::btree_map(any::<String>(), inner, 0..=MAX_LEN)
+                .prop_map(|it| Value::Object(it.into_iter().collect())),
+        ]
+    })
+}
+
+/// `serde_json` doesn't parse all floats exactly,
+/// so only produce those that survive a round-trip.
+fn float(f: f64) -> Option<Number> {
+    let number = Number::from_f64(f)?;
+    match serde_json::from_str::<Number>(&number.to_string()).ok()? == number {
+        true => Some(number),
+        false => None,
+    }
+}
+
+/// Numbers are always integers.
+pub fn id() -> impl Strategy<Value = Id> {
+    prop_oneof![
+        any::<String>().prop_map(Id::String),
+        any::<i64>().prop_map(|it| Id::Number(it.into())),
+        Just(Id::Null),
+    ]
+}
+
+/// Avoids names reserved for extensions.
+pub fn method() -> impl Strategy<Value = String> {
+    any::<String>().prop_map(|it| match it.starts_with("rpc.") {
+        true => format!("_{}", it),
+        false => it,
+    })
+}
+
+pub fn request_parameters() -> impl Strategy<Value = RequestParameters> {
+    prop_oneof![
+        collection::vec(value(), 0..=MAX_LEN).prop_map(RequestParameters::ByPosition),
+        collection::btree_map(any::<String>(), value(), 0..=MAX_LEN)
+            .prop_map(|it| RequestParameters::ByName(it.into_iter().collect::<Map<_, _>>())),
+    ]
+}
+
+pub fn request() -> impl Strategy<Value = Request> {
+    (method(), option::of(request_parameters()), option::of(id())).prop_map(
+        |(method, params, id)| Request {
+            jsonrpc: V2,
+            method,
+            params,
+            id,
+        },
+    )
+}
+
+pub fn notification() -> impl Strategy<Value = Notification> {
+    (method(), option::of(request_parameters())).prop_map(|(method, params)| Notification {
+        jsonrpc: V2,
+        method,
+        params,
+    })
+}
+
+pub fn error() -> impl Strategy<Value = Error> {
+    (any::<i64>(), any::<String>(), option::of(value())).prop_map(|(code, message, data)| Error {
+        code,
+        message,
+        data,
+    })
+}
+
+pub fn response() -> impl Strategy<Value = Response> {
+    (
+        prop_oneof![value().prop_map(Ok), error().prop_map(Err)],
+        id(),
+    )
+        .prop_map(|(result, id)| Response {
+            jsonrpc: V2,
+            result,
+            id,
+        })
+}
+
+/// Batches are never empty.
+pub fn maybe_batched_request() -> impl Strategy<Value = MaybeBatchedRequest> {
+    prop_oneof![
+        request().prop_map(MaybeBatchedRequest::Single),
+        collection::vec(request(), 1..=MAX_LEN).prop_map(MaybeBatchedRequest::Batch),
+    ]
+}
+
+/// Batches are never empty.
+pub fn maybe_batched_response() -> impl Strategy<Value = MaybeBatchedResponse> {
+    prop_oneof![
+        response().prop_map(MaybeBatchedResponse::Single),
+        collection::vec(response(), 1..=MAX_LEN).prop_map(MaybeBatchedResponse::Batch),
+    ]
+}
@@ -0,0 +1,240 @@
+//! Deserialize the members of a batch one at a time.
+//!
+//! [`MaybeBatchedRequest`](crate::MaybeBatchedRequest) and [`MaybeBatchedResponse`](crate::MaybeBatchedResponse)
+//! materialize every member before returning.
+//! [`Members`] instead splits the batch into [`RawValue`]s as it goes,
+//! so processing can start with the first member,
+//! and only one member is deserialized at a time.
+
+use core::{fmt, marker::PhantomData};
+
+use serde::{de::DeserializeOwned, de::Error as _};
+use serde_json::value::RawValue;
+
+/// An [`Iterator`] over the members of a (possibly batched) request or response,
+/// see [the module documentation](mod@self).
+///
+/// A non-batch yields a single item.
+///
+/// A member which is valid JSON but not a `T` yields an [`Err`], and iteration continues,
+/// like a server handling a batch.
+/// Invalid JSON yields an [`Err`] and ends iteration.
+pub struct Members<'a, T> {
+    slice: &'a [u8],
+    /// Where the next member starts, or [`None`] if iteration has finished.
+    position: Option<usize>,
+    state: State,
+    t: PhantomData<fn() -> T>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Nothing has been read.
+    Start,
+    /// Inside a batch, expecting a member.
+    First,
+    /// Inside a batch, after a member.
+    Rest,
+}
+
+impl<T> fmt::Debug for Members<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Members")
+            .field("position", &self.position)
+            .field("state", &self.state)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a, T> Members<'a, T> {
+    pub fn new(slice: &'a [u8]) -> Self {
+        Self {
+            slice,
+            position: Some(0),
+            state: State::Start,
+            t: PhantomData,
+        }
+    }
+    /// Whether the input was a batch.
+    ///
+    /// Only known once the first item has been returned.
+    pub fn is_batch(&self) -> Option<bool> {
+        match self.state {
+            State::Start if self.position.is_some() => None,
+            State::Start => Some(false),
+            State::First | State::Rest => Some(true),
+        }
+    }
+    fn skip_whitespace(&self, mut position: usize) -> usize {
+        while let Some(b' ' | b'\n' | b'\t' | b'\r') = self.slice.get(position) {
+            position += 1
+        }
+        position
+    }
+    /// Parse the [`RawValue`] starting at `position`, returning it and the position after it.
+    fn raw(&self, position: usize) -> serde_json::Result<(&'a RawValue, usize)> {
+        let mut stream =
+            serde_json::Deserializer::from_slice(&self.slice[position..]).into_iter::<&RawValue>();
+        match stream.next() {
+            Some(Ok(it)) => Ok((it, position + stream.byte_offset())),
+            Some(Err(e)) => Err(e),
+            None => Err(serde_json::Error::custom("unexpected end of input")),
+        }
+    }
+    fn unexpected(&self, position: usize, expected: &str) -> serde_json::Error {
+        match self.slice.get(position) {
+            Some(it) => serde_json::Error::custom(format_args!(
+                "expected {} at byte {}, found `{}`",
+                expected,
+                position,
+                it.escape_ascii()
+            )),
+            None => serde_json::Error::custom(format_args!(
+                "expected {} at byte {}, found end of input",
+                expected, position
+            )),
+        }
+    }
+    /// Find the next member, returning [`None`] if there are no more.
+    fn next_raw(&mut self) -> Option<serde_json::Result<&'a RawValue>> {
+        let position = self.skip_whitespace(self.position?);
+        let member_at = match (self.state, self.slice.get(position)) {
+            (State::Start, Some(b'[')) => {
+                self.state = State::First;
+                let position = self.skip_whitespace(position + 1);
+                match self.slice.get(position) {
+                    Some(b']') => return self.finish(position + 1).map(|()| None).transpose(),
+                    _ => position,
+                }
+            }
+            (State::Start, _) => {
+                self.position = None;
+                let result = self.raw(position);
+                return Some(result.and_then(|(raw, end)| self.finish(end).map(|()| raw)));
+            }
+            (State::First, _) => position,
+            (State::Rest, Some(b',')) => self.skip_whitespace(position + 1),
+            (State::Rest, Some(b']')) => {
+                return self.finish(position + 1).map(|()| None).transpose()
+            }
+            (State::Rest, _) => return Some(Err(self.fail(position, "`,` or `]`"))),
+        };
+        match self.raw(member_at) {
+            Ok((raw, end)) => {
+                self.state = State::Rest;
+                self.position = Some(end);
+                Some(Ok(raw))
+            }
+            Err(e) => {
+                self.position = None;
+                Some(Err(e))
+            }
+        }
+    }
+    /// Check that there is nothing but whitespace after `position`.
+    fn finish(&mut self, position: usize) -> serde_json::Result<()> {
+        self.position = None;
+        let position = self.skip_whitespace(position);
+        match position == self.slice.len() {
+            true => Ok(()),
+            false => Err(self.unexpected(position, "end of input")),
+        }
+    }
+    fn fail(&mut self, position: usize, expected: &str) -> serde_json::Error {
+        self.position = None;
+        self.unexpected(position, expected)
+    }
+}
+
+impl<T: DeserializeOwned> Iterator for Members<'_, T> {
+    type Item = serde_json::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(
+            self.next_raw()?
+                .and_then(|it| serde_json::from_str(it.get())),
+        )
+    }
+}
+
+impl<T: DeserializeOwned> core::iter::FusedIterator for Members<'_, T> {}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        string::{String, ToString as _},
+        vec,
+        vec::Vec,
+    };
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::Request;
+
+    fn members(s: &str) -> (Vec<Result<Value, String>>, Option<bool>) {
+        let mut members = Members::<Value>::new(s.as_bytes());
+        let items = members
+            .by_ref()
+            .map(|it| it.map_err(|e| e.to_string()))
+            .collect();
+        (items, members.is_batch())
+    }
+
+    #[test]
+    fn single() {
+        assert_eq!(members(" 1 "), (vec![Ok(json!(1))], Some(false)));
+        assert_eq!(
+            members("1 2").0,
+            [Err(String::from(
+                "expected end of input at byte 2, found `2`"
+            ))]
+        );
+    }
+
+    #[test]
+    fn batch() {
+        assert_eq!(
+            members("[1, {\"a\": [2]}\n,3]"),
+            (
+                vec![Ok(json!(1)), Ok(json!({"a": [2]})), Ok(json!(3))],
+                Some(true)
+            )
+        );
+        assert_eq!(members(" [ ] "), (vec![], Some(true)));
+        assert_eq!(
+            members("[1 2]").0,
+            [
+                Ok(json!(1)),
+                Err(String::from("expected `,` or `]` at byte 3, found `2`"))
+            ]
+        );
+        assert_eq!(
+            members("[1,").0,
+            [Ok(json!(1)), Err(String::from("unexpected end of input"))]
+        );
+        assert_eq!(
+            members("[1] x").0,
+            [
+                Ok(json!(1)),
+                Err(String::from("expected end of input at byte 4, found `x`"))
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_members_continue() {
+        let body = br#"[{"jsonrpc": "2.0", "method": "a", "id": 1}, 1, {"jsonrpc": "2.0", "method": "b"}]"#;
+        let members = Members::<Request>::new(body).collect::<Vec<_>>();
+        assert_eq!(members.len(), 3);
+        assert_eq!(members[0].as_ref().unwrap().method, "a");
+        assert!(members[1].is_err());
+        assert_eq!(members[2].as_ref().unwrap().method, "b");
+    }
+
+    #[test]
+    fn is_batch_before_first() {
+        let members = Members::<Value>::new(b"[1]");
+        assert_eq!(members.is_batch(), None);
+    }
+}
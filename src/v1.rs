@@ -0,0 +1,316 @@
+//! Types from the [`JSON-RPC 1.0` Specification](https://www.jsonrpc.org/specification_v1),
+//! with conversions to and from the `JSON-RPC 2.0` types where no information is lost.
+//!
+//! > When quoted, the specification will appear as blockquoted text, like so.
+
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{Id, RequestParameters, V2};
+
+/// A `JSON-RPC 1.0` request, or notification.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+pub struct Request {
+    /// > A String containing the name of the method to be invoked.
+    pub method: String,
+    /// > An Array of objects to pass as arguments to the method.
+    #[serde(default)]
+    pub params: Vec<Value>,
+    /// > The request id. This can be of any type.
+    /// > It is used to match the response with the request that it is replying to.
+    ///
+    /// > A notification is a special request which does not have a response.
+    /// > The notification has the same properties as the request object except for the id.
+    /// > id - Must be null.
+    #[serde(default)]
+    pub id: Value,
+}
+
+impl Request {
+    pub fn is_notification(&self) -> bool {
+        self.id.is_null()
+    }
+}
+
+/// A `JSON-RPC 1.0` response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    /// > result - The Object that was returned by the invoked method.
+    /// > This must be null in case there was an error invoking the method.
+    /// >
+    /// > error - An Error object if there was an error invoking the method.
+    /// > It must be null if there was no error.
+    pub result: Result<Value, Value>,
+    /// > This must be the same id as the request it is responding to.
+    pub id: Value,
+}
+
+impl Default for Response {
+    fn default() -> Self {
+        Self {
+            result: Ok(Value::Null),
+            id: Value::Null,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct RawResponseDeSer {
+    #[serde(default)]
+    result: Value,
+    #[serde(default)]
+    error: Value,
+    #[serde(default)]
+    id: Value,
+}
+
+/// Both `result` and `error` are always present.
+impl Serialize for Response {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        let Self { result, id } = self.clone();
+        let (result, error) = match result {
+            Ok(result) => (result, Value::Null),
+            Err(error) => (Value::Null, error),
+        };
+        RawResponseDeSer { result, error, id }.serialize(serializer)
+    }
+}
+
+/// Be lenient in what we accept: absent members are treated as `null`.
+impl<'de> Deserialize<'de> for Response {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        let RawResponseDeSer { result, error, id } = RawResponseDeSer::deserialize(deserializer)?;
+        Ok(Self {
+            result: match error {
+                Value::Null => Ok(result),
+                error => Err(error),
+            },
+            id,
+        })
+    }
+}
+
+/// A value that cannot be represented in the other version of the protocol.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConversionError {
+    /// `JSON-RPC 2.0` ids must be a string, number, or null.
+    Id,
+    /// `JSON-RPC 1.0` only supports by-position parameters.
+    ByNameParams,
+    /// `JSON-RPC 2.0` notifications have no id,
+    /// but `JSON-RPC 1.0` notifications have a null id,
+    /// so a `JSON-RPC 2.0` request with a null id has no equivalent.
+    NullId,
+    /// `JSON-RPC 2.0` errors must be an object with a `code` and a `message`.
+    Error,
+}
+
+impl fmt::Display for ConversionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConversionError::Id => "id must be a string, number, or null",
+            ConversionError::ByNameParams => "JSON-RPC 1.0 does not support by-name parameters",
+            ConversionError::NullId => "JSON-RPC 1.0 cannot represent a call with a null id",
+            ConversionError::Error => "error must be an object with a `code` and a `message`",
+        })
+    }
+}
+
+impl core::error::Error for ConversionError {}
+
+fn id_from_v1(id: Value) -> Result<Id, ConversionError> {
+    match id {
+        Value::Null => Ok(Id::Null),
+        Value::String(it) => Ok(Id::String(it)),
+        Value::Number(it) => Ok(Id::Number(it)),
+        Value::Bool(_) | Value::Array(_) | Value::Object(_) => Err(ConversionError::Id),
+    }
+}
+
+fn id_to_v1(id: Id) -> Value {
+    match id {
+        Id::String(it) => Value::String(it),
+        Id::Number(it) => Value::Number(it),
+        Id::Null => Value::Null,
+    }
+}
+
+/// A null id becomes a notification, and parameters are passed by-position.
+///
+/// `JSON-RPC 1.0` parameters are always present,
+/// so converting back from `JSON-RPC 2.0` is lossless, but the reverse isn't:
+/// absent `JSON-RPC 2.0` parameters become empty by-position parameters.
+impl TryFrom<Request> for crate::Request {
+    type Error = ConversionError;
+
+    fn try_from(value: Request) -> Result<Self, Self::Error> {
+        let Request { method, params, id } = value;
+        Ok(Self {
+            jsonrpc: V2,
+            method,
+            params: Some(RequestParameters::ByPosition(params)),
+            id: match id {
+                Value::Null => None,
+                id => Some(id_from_v1(id)?),
+            },
+        })
+    }
+}
+
+/// Absent parameters become an empty array, so don't survive a round trip.
+impl TryFrom<crate::Request> for Request {
+    type Error = ConversionError;
+
+    fn try_from(value: crate::Request) -> Result<Self, Self::Error> {
+        let crate::Request {
+            jsonrpc: V2,
+            method,
+            params,
+            id,
+        } = value;
+        Ok(Self {
+            method,
+            params: match params {
+                Some(RequestParameters::ByPosition(it)) => it,
+                Some(RequestParameters::ByName(_)) => return Err(ConversionError::ByNameParams),
+                None => vec![],
+            },
+            id: match id {
+                Some(Id::Null) => return Err(ConversionError::NullId),
+                Some(id) => id_to_v1(id),
+                None => Value::Null,
+            },
+        })
+    }
+}
+
+impl TryFrom<Response> for crate::Response {
+    type Error = ConversionError;
+
+    fn try_from(value: Response) -> Result<Self, Self::Error> {
+        let Response { result, id } = value;
+        Ok(Self {
+            jsonrpc: V2,
+            result: match result {
+                Ok(it) => Ok(it),
+                Err(it) => Err(crate::Error::deserialize(it).map_err(|_| ConversionError::Error)?),
+            },
+            id: id_from_v1(id)?,
+        })
+    }
+}
+
+impl From<crate::Response> for Response {
+    fn from(value: crate::Response) -> Self {
+        let crate::Response {
+            jsonrpc: V2,
+            result,
+            id,
+        } = value;
+        Self {
+            result: result.map_err(|it| serde_json::to_value(it).expect("errors always serialize")),
+            id: id_to_v1(id),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{from_value, json};
+
+    use super::*;
+
+    #[track_caller]
+    fn v2_request(value: Value) -> crate::Request {
+        from_value(value).unwrap()
+    }
+
+    #[test]
+    fn request_from_v1() {
+        for it in [
+            json!({"method": "a", "params": [1, "two"], "id": 1}),
+            json!({"method": "a", "params": [], "id": "x"}),
+            json!({"method": "a", "params": [{"k": null}], "id": null}),
+        ] {
+            let v1 = from_value::<Request>(it).unwrap();
+            let v2 = crate::Request::try_from(v1.clone()).unwrap();
+            assert_eq!(Request::try_from(v2).unwrap(), v1);
+        }
+        let notification = from_value::<Request>(json!({"method": "a", "id": null})).unwrap();
+        assert_eq!(
+            crate::Request::try_from(notification).unwrap(),
+            v2_request(json!({"jsonrpc": "2.0", "method": "a", "params": []}))
+        );
+        let bad = from_value::<Request>(json!({"method": "a", "params": [], "id": [1]})).unwrap();
+        assert_eq!(crate::Request::try_from(bad), Err(ConversionError::Id));
+    }
+
+    #[test]
+    fn request_to_v1() {
+        for it in [
+            json!({"jsonrpc": "2.0", "method": "a", "params": [1], "id": 1}),
+            json!({"jsonrpc": "2.0", "method": "a", "params": [], "id": "x"}),
+            json!({"jsonrpc": "2.0", "method": "a", "params": [true]}),
+        ] {
+            let v2 = v2_request(it);
+            let v1 = Request::try_from(v2.clone()).unwrap();
+            assert_eq!(crate::Request::try_from(v1).unwrap(), v2);
+        }
+        // Absent parameters aren't preserved.
+        let v1 = Request::try_from(v2_request(
+            json!({"jsonrpc": "2.0", "method": "a", "id": 1}),
+        ))
+        .unwrap();
+        assert_eq!(
+            crate::Request::try_from(v1).unwrap(),
+            v2_request(json!({"jsonrpc": "2.0", "method": "a", "params": [], "id": 1}))
+        );
+        assert_eq!(
+            Request::try_from(v2_request(
+                json!({"jsonrpc": "2.0", "method": "a", "params": {"k": 1}, "id": 1})
+            )),
+            Err(ConversionError::ByNameParams)
+        );
+        assert_eq!(
+            Request::try_from(v2_request(
+                json!({"jsonrpc": "2.0", "method": "a", "id": null})
+            )),
+            Err(ConversionError::NullId)
+        );
+    }
+
+    #[test]
+    fn response() {
+        for it in [
+            json!({"jsonrpc": "2.0", "result": [1], "id": 1}),
+            json!({"jsonrpc": "2.0", "result": null, "id": "x"}),
+            json!({"jsonrpc": "2.0", "error": {"code": -32000, "message": "no", "data": 1}, "id": null}),
+        ] {
+            let v2 = from_value::<crate::Response>(it).unwrap();
+            let v1 = Response::from(v2.clone());
+            let v1 = from_value::<Response>(serde_json::to_value(v1).unwrap()).unwrap();
+            assert_eq!(crate::Response::try_from(v1).unwrap(), v2);
+        }
+        assert_eq!(
+            serde_json::to_value(Response {
+                result: Err(json!("no")),
+                id: json!(1)
+            })
+            .unwrap(),
+            json!({"result": null, "error": "no", "id": 1})
+        );
+        let v1 = from_value::<Response>(json!({"error": "no", "id": 1})).unwrap();
+        assert_eq!(crate::Response::try_from(v1), Err(ConversionError::Error));
+        let v1 = from_value::<Response>(json!({"result": 1, "id": true})).unwrap();
+        assert_eq!(crate::Response::try_from(v1), Err(ConversionError::Id));
+    }
+}
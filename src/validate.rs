@@ -0,0 +1,454 @@
+//! Strict checks against the specification.
+//!
+//! Parsing is lenient, accepting anything that is unambiguous.
+//! These checks additionally enforce the rules that parsing can't,
+//! including the specification's recommendations.
+//!
+//! [`NoDuplicateKeys`] and [`RequestParameters::deserialize_strict`] opt in to rejecting
+//! objects with duplicate member names, which `serde_json` otherwise silently accepts,
+//! keeping the last value.
+
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt;
+
+use serde::{
+    de::{DeserializeOwned, Error as _, MapAccess, SeqAccess, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_json::{Map, Value};
+
+use crate::{
+    method::MethodNameError, Error, Id, MaybeBatchedRequest, MaybeBatchedResponse, Request,
+    RequestParameters, Response,
+};
+
+/// A departure from the specification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// For batches, the position of the offending member.
+    pub index: Option<usize>,
+    pub kind: ViolationKind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ViolationKind {
+    /// > Numbers SHOULD NOT contain fractional parts
+    FractionalId,
+    /// > The value SHOULD normally not be Null
+    NullId,
+    /// > Method names that begin with the word rpc followed by a period character
+    /// > (U+002E or ASCII 46) are reserved for rpc-internal methods and extensions
+    /// > and MUST NOT be used for anything else.
+    ReservedMethodName,
+    /// The method name was the empty string, see [`MethodName`](crate::method::MethodName).
+    EmptyMethodName,
+    /// > rpc call with an empty Array
+    /// >
+    /// > `--> []`
+    /// >
+    /// > `<-- {"jsonrpc": "2.0", "error": {"code": -32600, "message": "Invalid Request"}, "id": null}`
+    EmptyBatch,
+    /// See [`Error::RESERVED_RANGE`].
+    ReservedErrorCode(i64),
+    /// The [`Id`] was used by an earlier member of the batch.
+    ///
+    /// The specification doesn't forbid this,
+    /// but clients can't tell which response belongs to which request.
+    DuplicateId(Id),
+}
+
+fn check_id(id: &Id, out: &mut Vec<ViolationKind>) {
+    match id {
+        Id::Number(it) if it.is_f64() => out.push(ViolationKind::FractionalId),
+        Id::Null => out.push(ViolationKind::NullId),
+        Id::String(_) | Id::Number(_) => {}
+    }
+}
+
+fn request(request: &Request) -> Vec<ViolationKind> {
+    let mut out = vec![];
+    match request.validate_method() {
+        Ok(()) => {}
+        Err(MethodNameError::Reserved) => out.push(ViolationKind::ReservedMethodName),
+        Err(MethodNameError::Empty) => out.push(ViolationKind::EmptyMethodName),
+    }
+    if let Some(id) = &request.id {
+        check_id(id, &mut out)
+    }
+    out
+}
+
+fn response(response: &Response) -> Vec<ViolationKind> {
+    let mut out = vec![];
+    match &response.result {
+        Err(Error { code, .. })
+            if Error::RESERVED_RANGE.contains(code)
+                && !Error::SERVER_ERROR_RANGE.contains(code)
+                && ![
+                    Error::PARSE_ERROR,
+                    Error::INVALID_REQUEST,
+                    Error::METHOD_NOT_FOUND,
+                    Error::INVALID_PARAMS,
+                    Error::INTERNAL_ERROR,
+                ]
+                .contains(code) =>
+        {
+            out.push(ViolationKind::ReservedErrorCode(*code))
+        }
+        // Servers MUST respond with a null id if they couldn't parse the request.
+        Err(_) if response.id == Id::Null => return out,
+        _ => {}
+    }
+    check_id(&response.id, &mut out);
+    out
+}
+
+fn single(kinds: Vec<ViolationKind>) -> Vec<Violation> {
+    kinds
+        .into_iter()
+        .map(|kind| Violation { index: None, kind })
+        .collect()
+}
+
+fn batch<T>(members: &[T], f: fn(&T) -> Vec<ViolationKind>) -> Vec<Violation> {
+    let mut out = vec![];
+    if members.is_empty() {
+        out.push(Violation {
+            index: None,
+            kind: ViolationKind::EmptyBatch,
+        })
+    }
+    for (ix, member) in members.iter().enumerate() {
+        out.extend(f(member).into_iter().map(|kind| Violation {
+            index: Some(ix),
+            kind,
+        }))
+    }
+    out
+}
+
+impl Request {
+    /// Check this request against the specification, see [the module documentation](mod@self).
+    pub fn validate_strict(&self) -> Vec<Violation> {
+        single(request(self))
+    }
+}
+
+impl Response {
+    /// Check this response against the specification, see [the module documentation](mod@self).
+    pub fn validate_strict(&self) -> Vec<Violation> {
+        single(response(self))
+    }
+}
+
+impl MaybeBatchedRequest {
+    /// Check this request against the specification, see [the module documentation](mod@self).
+    pub fn validate_strict(&self) -> Vec<Violation> {
+        match self {
+            MaybeBatchedRequest::Single(it) => it.validate_strict(),
+            MaybeBatchedRequest::Batch(it) => {
+                let mut out = batch(it, request);
+                out.extend(duplicate_ids(it).map(|(index, id)| Violation {
+                    index: Some(index),
+                    kind: ViolationKind::DuplicateId(id.clone()),
+                }));
+                out
+            }
+        }
+    }
+    /// Check that no two members of a batch share an [`Id`],
+    /// returning each duplicated [`Id`] once.
+    ///
+    /// Notifications have no [`Id`], so are never duplicates.
+    pub fn check_unique_ids(&self) -> Result<(), Vec<Id>> {
+        let MaybeBatchedRequest::Batch(members) = self else {
+            return Ok(());
+        };
+        let mut reported = Seen::default();
+        let duplicates = duplicate_ids(members)
+            .filter(|(_, id)| reported.insert(id))
+            .map(|(_, id)| id)
+            .collect::<Vec<_>>();
+        match duplicates.is_empty() {
+            true => Ok(()),
+            false => Err(duplicates.into_iter().cloned().collect()),
+        }
+    }
+}
+
+/// Members whose [`Id`] was used by an earlier member.
+fn duplicate_ids(members: &[Request]) -> impl Iterator<Item = (usize, &Id)> {
+    let mut seen = Seen::default();
+    members.iter().enumerate().filter_map(move |(ix, member)| {
+        let id = member.id.as_ref()?;
+        (!seen.insert(id)).then_some((ix, id))
+    })
+}
+
+/// The [`Id`]s seen so far.
+#[cfg(feature = "std")]
+#[derive(Default)]
+struct Seen<'a>(std::collections::HashSet<&'a Id>);
+
+/// The [`Id`]s seen so far.
+///
+/// Without `std` there's no (randomly seeded) `HashSet`,
+/// so they're ordered by a key which is equal exactly when the [`Id`]s are.
+#[cfg(not(feature = "std"))]
+#[derive(Default)]
+struct Seen<'a>(alloc::collections::BTreeSet<(u8, alloc::borrow::Cow<'a, str>)>);
+
+impl<'a> Seen<'a> {
+    /// Returns `false` if `id` was already seen.
+    fn insert(&mut self, id: &'a Id) -> bool {
+        #[cfg(feature = "std")]
+        return self.0.insert(id);
+        #[cfg(not(feature = "std"))]
+        return self.0.insert(match id {
+            Id::Null => (0, alloc::borrow::Cow::Borrowed("")),
+            Id::String(it) => (1, alloc::borrow::Cow::Borrowed(it.as_str())),
+            // `Number`s are equal exactly when they're written the same way.
+            Id::Number(it) => (
+                2,
+                alloc::borrow::Cow::Owned(alloc::string::ToString::to_string(it)),
+            ),
+        });
+    }
+}
+
+/// A request which fails to deserialize if [`MaybeBatchedRequest::check_unique_ids`] would fail.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UniqueIds(pub MaybeBatchedRequest);
+
+impl<'de> Deserialize<'de> for UniqueIds {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let request = MaybeBatchedRequest::deserialize(deserializer)?;
+        match request.check_unique_ids() {
+            Ok(()) => Ok(Self(request)),
+            Err(ids) => Err(D::Error::custom(format_args!(
+                "duplicate ids in batch: {}",
+                serde_json::to_string(&ids).expect("ids always serialize")
+            ))),
+        }
+    }
+}
+
+impl MaybeBatchedResponse {
+    /// Check this response against the specification, see [the module documentation](mod@self).
+    pub fn validate_strict(&self) -> Vec<Violation> {
+        match self {
+            MaybeBatchedResponse::Single(it) => it.validate_strict(),
+            MaybeBatchedResponse::Batch(it) => batch(it, response),
+        }
+    }
+}
+
+/// A `T` which fails to deserialize if any object in the input has duplicate member names.
+///
+/// The input is first deserialized as a [`Value`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct NoDuplicateKeys<T>(pub T);
+
+impl<'de, T: DeserializeOwned> Deserialize<'de> for NoDuplicateKeys<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let UniqueValue(value) = UniqueValue::deserialize(deserializer)?;
+        T::deserialize(value).map(Self).map_err(D::Error::custom)
+    }
+}
+
+impl RequestParameters {
+    /// Like [`Deserialize::deserialize`], but fails if any object has duplicate member names.
+    ///
+    /// For use with `#[serde(deserialize_with = "RequestParameters::deserialize_strict")]`.
+    pub fn deserialize_strict<'de, D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let NoDuplicateKeys(it) = NoDuplicateKeys::deserialize(deserializer)?;
+        Ok(it)
+    }
+}
+
+/// A [`Value`], rejecting duplicate member names.
+struct UniqueValue(Value);
+
+impl<'de> Deserialize<'de> for UniqueValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(UniqueValueVisitor).map(Self)
+    }
+}
+
+struct UniqueValueVisitor;
+
+impl<'de> Visitor<'de> for UniqueValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any JSON value, without duplicate member names")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Value, A::Error> {
+        let mut out = Map::new();
+        while let Some(key) = map.next_key::<String>()? {
+            if out.contains_key(&key) {
+                return Err(A::Error::custom(format_args!("duplicate member `{}`", key)));
+            }
+            let UniqueValue(value) = map.next_value()?;
+            out.insert(key, value);
+        }
+        Ok(Value::Object(out))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut out = vec![];
+        while let Some(UniqueValue(it)) = seq.next_element()? {
+            out.push(it)
+        }
+        Ok(Value::Array(out))
+    }
+
+    // Everything else is as for `Value`.
+    fn visit_bool<E: serde::de::Error>(self, v: bool) -> Result<Value, E> {
+        Ok(Value::Bool(v))
+    }
+    fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Value, E> {
+        Ok(Value::from(v))
+    }
+    fn visit_string<E: serde::de::Error>(self, v: String) -> Result<Value, E> {
+        Ok(Value::String(v))
+    }
+    fn visit_none<E: serde::de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+    fn visit_unit<E: serde::de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        UniqueValue::deserialize(deserializer).map(|it| it.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{format, string::ToString as _};
+
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn unique_ids() {
+        let batch = serde_json::from_value::<MaybeBatchedRequest>(json!([
+            {"jsonrpc": "2.0", "method": "a", "id": 1},
+            {"jsonrpc": "2.0", "method": "a", "id": "1"},
+            {"jsonrpc": "2.0", "method": "a", "id": 1.0},
+            {"jsonrpc": "2.0", "method": "a"},
+            {"jsonrpc": "2.0", "method": "a"},
+            {"jsonrpc": "2.0", "method": "a", "id": null},
+        ]))
+        .unwrap();
+        assert_eq!(batch.check_unique_ids(), Ok(()));
+    }
+
+    #[test]
+    fn duplicate_ids_are_reported_once() {
+        let batch = serde_json::from_value::<MaybeBatchedRequest>(json!([
+            {"jsonrpc": "2.0", "method": "a", "id": 1},
+            {"jsonrpc": "2.0", "method": "a", "id": "x"},
+            {"jsonrpc": "2.0", "method": "a", "id": 1},
+            {"jsonrpc": "2.0", "method": "a", "id": 1},
+            {"jsonrpc": "2.0", "method": "a", "id": "x"},
+        ]))
+        .unwrap();
+        assert_eq!(
+            batch.check_unique_ids(),
+            Err(vec![Id::from_u64(1), Id::from("x")])
+        );
+        let violations = batch
+            .validate_strict()
+            .into_iter()
+            .filter(|it| matches!(it.kind, ViolationKind::DuplicateId(_)))
+            .map(|it| it.index)
+            .collect::<Vec<_>>();
+        assert_eq!(violations, [Some(2), Some(3), Some(4)]);
+    }
+
+    #[derive(Debug, Deserialize)]
+    struct Strict {
+        #[serde(deserialize_with = "RequestParameters::deserialize_strict")]
+        params: RequestParameters,
+    }
+
+    fn strict(params: &str) -> Result<RequestParameters, String> {
+        serde_json::from_str::<Strict>(&format!(r#"{{"params": {}}}"#, params))
+            .map(|it| it.params)
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn duplicate_keys() {
+        assert_eq!(
+            strict(r#"{"a": 1, "b": {"a": [{"a": 2}]}}"#),
+            Ok(RequestParameters::ByName(
+                json!({"a": 1, "b": {"a": [{"a": 2}]}})
+                    .as_object()
+                    .unwrap()
+                    .clone()
+            ))
+        );
+        assert_eq!(
+            strict(r#"[1, {"a": 1}]"#),
+            Ok(RequestParameters::ByPosition(vec![
+                json!(1),
+                json!({"a": 1})
+            ]))
+        );
+        for params in [
+            r#"{"a": 1, "a": 2}"#,
+            r#"{"b": {"a": 1, "a": 1}}"#,
+            r#"[{"x": [{"a": 1, "a": 2}]}]"#,
+        ] {
+            let error = strict(params).unwrap_err();
+            assert!(error.starts_with("duplicate member `a`"), "{}", error);
+        }
+        // Without the opt-in, the last value wins.
+        let lenient = serde_json::from_str::<RequestParameters>(r#"{"a": 1, "a": 2}"#).unwrap();
+        assert_eq!(
+            lenient,
+            RequestParameters::ByName(json!({"a": 2}).as_object().unwrap().clone())
+        );
+    }
+
+    #[test]
+    fn no_duplicate_keys() {
+        let parse = |it: &str| serde_json::from_str::<NoDuplicateKeys<Request>>(it);
+        let NoDuplicateKeys(request) =
+            parse(r#"{"jsonrpc": "2.0", "method": "a", "params": {"a": {"b": 1}}, "id": 1}"#)
+                .unwrap();
+        assert_eq!(request.method, "a");
+        assert!(parse(r#"{"jsonrpc": "2.0", "method": "a", "method": "b", "id": 1}"#).is_err());
+        assert!(
+            parse(r#"{"jsonrpc": "2.0", "method": "a", "params": {"a": {"b": 1, "b": 2}}}"#)
+                .is_err()
+        );
+    }
+}
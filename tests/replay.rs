@@ -0,0 +1,125 @@
+//! Repeating recordings with `repro`, against a `proxy` which answers from them with `--replay`.
+
+use std::{
+    fs,
+    net::{TcpListener, TcpStream},
+    path::Path,
+    process::{Child, Command, Output},
+    thread,
+    time::{Duration, Instant},
+};
+
+const RECORDING: &str = r#"{"name": "m0", "params": [{"value": 0}], "result": {"name": "result", "value": "zero"}}
+{"name": "m1", "params": [{"value": 1}], "result": {"name": "result", "value": [1, "one"]}}
+{"name": "notify", "params": [], "x-jsonrpcli-notification": true}
+"#;
+
+/// Kills the proxy when dropped.
+struct Proxy {
+    child: Child,
+    url: String,
+}
+
+impl Proxy {
+    fn replay(recording: &Path) -> Self {
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let child = Command::new(env!("CARGO_BIN_EXE_proxy"))
+            .arg(addr.to_string())
+            // Calls which weren't recorded are forwarded here, and fail.
+            .arg("http://127.0.0.1:1")
+            .arg("--replay")
+            .arg(recording)
+            .env("RUST_LOG", "off")
+            .spawn()
+            .unwrap();
+        let started = Instant::now();
+        while TcpStream::connect(addr).is_err() {
+            assert!(
+                started.elapsed() < Duration::from_secs(10),
+                "proxy didn't start"
+            );
+            thread::sleep(Duration::from_millis(20))
+        }
+        Self {
+            child,
+            url: format!("http://{}", addr),
+        }
+    }
+    fn repro(&self, recording: &Path) -> Output {
+        Command::new(env!("CARGO_BIN_EXE_repro"))
+            .arg(&self.url)
+            .arg(recording)
+            .env("NO_COLOR", "1")
+            .output()
+            .unwrap()
+    }
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        self.child.kill().ok();
+        self.child.wait().ok();
+    }
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).into_owned()
+}
+
+#[test]
+fn passes() {
+    let dir = tempfile::tempdir().unwrap();
+    let recording = dir.path().join("recording.ndjson");
+    fs::write(&recording, RECORDING).unwrap();
+    let proxy = Proxy::replay(&recording);
+    let output = proxy.repro(&recording);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("2 passed, 0 mismatched, 0 errored, 1 skipped"),
+        "{}",
+        stderr(&output)
+    );
+}
+
+#[test]
+fn mismatches() {
+    let dir = tempfile::tempdir().unwrap();
+    let recording = dir.path().join("recording.ndjson");
+    fs::write(&recording, RECORDING).unwrap();
+    let proxy = Proxy::replay(&recording);
+    let changed = dir.path().join("changed.ndjson");
+    fs::write(&changed, RECORDING.replace("\"one\"", "\"uno\"")).unwrap();
+    let output = proxy.repro(&changed);
+    assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+    let stderr = stderr(&output);
+    assert!(
+        stderr.contains(&format!("mismatch for m1 at {}:2", changed.display())),
+        "{}",
+        stderr
+    );
+    assert!(stderr.contains(r#"/1: "uno" -> "one""#), "{}", stderr);
+}
+
+#[test]
+fn errors_for_calls_which_werent_recorded() {
+    let dir = tempfile::tempdir().unwrap();
+    let recording = dir.path().join("recording.ndjson");
+    fs::write(&recording, RECORDING).unwrap();
+    let proxy = Proxy::replay(&recording);
+    let other = dir.path().join("other.ndjson");
+    fs::write(
+        &other,
+        RECORDING.replace("[{\"value\": 1}]", "[{\"value\": 2}]"),
+    )
+    .unwrap();
+    let output = proxy.repro(&other);
+    assert_eq!(output.status.code(), Some(4), "{}", stderr(&output));
+    assert!(
+        stderr(&output).contains("1 passed, 0 mismatched, 1 errored, 1 skipped"),
+        "{}",
+        stderr(&output)
+    );
+}